@@ -0,0 +1,86 @@
+//! Throughput benchmarks for the lexer and token stream over a large
+//! generated schema. Run with `cargo bench --bench lexer`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use spew::ast::ASTSource;
+use spew::lexer::{Lexer, TokenSet};
+
+const RUNS: usize = 10;
+const TARGET_SIZE: usize = 8 * 1024 * 1024;
+
+// Builds a schema file made of many structs similar to what our generators emit
+fn generate_schema() -> String {
+    let mut out = String::with_capacity(TARGET_SIZE + 1024);
+    let mut index = 0;
+    while out.len() < TARGET_SIZE {
+        out.push_str(&format!(
+            "// Generated record {index}\n\
+             /* Fields mirror the upstream table\n   definition for record {index} */\n\
+             struct Record{index} {{\n  \
+               identifier: str\n  \
+               _private_value: num?\n  \
+               größe_{index}: num\n  \
+               description: str?\n  \
+               enabled: bool\n\
+             }}\n\n"
+        ));
+        index += 1;
+    }
+    out
+}
+
+// Runs the closure several times returning the fastest run
+fn measure<F: FnMut() -> usize>(mut run: F) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut count = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        count = black_box(run());
+        best = best.min(start.elapsed());
+    }
+    (best, count)
+}
+
+fn report(name: &str, bytes: usize, (elapsed, count): (Duration, usize)) {
+    let seconds = elapsed.as_secs_f64();
+    println!(
+        "{:<12} {:>10.2?} {:>10.1} MB/s {:>12.0} items/s ({} items)",
+        name,
+        elapsed,
+        bytes as f64 / seconds / (1024.0 * 1024.0),
+        count as f64 / seconds,
+        count
+    );
+}
+
+fn main() {
+    let source = generate_schema();
+    let bytes = source.len();
+    println!("input: {:.1} MB", bytes as f64 / (1024.0 * 1024.0));
+
+    report("lexer", bytes, measure(|| {
+        let mut count = 0;
+        for token in Lexer::new(&source) {
+            black_box(token.expect("generated schema should lex"));
+            count += 1;
+        }
+        count
+    }));
+
+    report("token_set", bytes, measure(|| {
+        let mut tokens = TokenSet::new(&source);
+        let mut count = 0;
+        while tokens.next_token().is_some() {
+            count += 1;
+        }
+        count
+    }));
+
+    report("parse_ast", bytes, measure(|| {
+        ASTSource::parse_ast(TokenSet::new(&source))
+            .expect("generated schema should parse")
+            .len()
+    }));
+}
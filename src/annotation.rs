@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+
+use crate::ast::{ASTSource, Annotation, AnnotationArgument, Literal, AST};
+use crate::intern::Name;
+use crate::source::Span;
+
+/// Kind of declaration an annotation is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationTarget {
+    Struct,
+    Property,
+    Function,
+    Trait,
+}
+
+impl AnnotationTarget {
+    pub const ALL: &'static [AnnotationTarget] = &[
+        AnnotationTarget::Struct,
+        AnnotationTarget::Property,
+        AnnotationTarget::Function,
+        AnnotationTarget::Trait,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            AnnotationTarget::Struct => "struct",
+            AnnotationTarget::Property => "property",
+            AnnotationTarget::Function => "function",
+            AnnotationTarget::Trait => "trait",
+        };
+    }
+}
+
+/// Type of literal an annotation argument accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentKind {
+    String,
+    Number,
+    Boolean,
+}
+
+impl ArgumentKind {
+    fn matches(&self, value: &Literal) -> bool {
+        return matches!(
+            (self, value),
+            (ArgumentKind::String, Literal::String(_))
+                | (ArgumentKind::Number, Literal::Number(_))
+                | (ArgumentKind::Boolean, Literal::Boolean(_))
+        );
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            ArgumentKind::String => "str",
+            ArgumentKind::Number => "num",
+            ArgumentKind::Boolean => "bool",
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArgumentSpec {
+    // None for positional arguments, these are matched in order
+    pub name: Option<&'static str>,
+    pub kind: ArgumentKind,
+    pub required: bool,
+}
+
+/// Describes an annotation that the registry accepts
+#[derive(Debug, Clone)]
+pub struct AnnotationSpec {
+    pub name: &'static str,
+    pub targets: &'static [AnnotationTarget],
+    pub arguments: Vec<ArgumentSpec>,
+}
+
+#[derive(Debug, Clone)]
+pub enum AnnotationError {
+    Unknown(Span, Name),
+    InvalidTarget(Span, Name, AnnotationTarget),
+    Duplicate(Span, Name),
+    // Argument that doesn't exist on the annotation, None for a positional argument
+    UnknownArgument(Span, Name, Option<Name>),
+    ArgumentType(Span, Name, ArgumentKind),
+    MissingArgument(Span, Name, &'static str),
+}
+
+impl AnnotationError {
+    pub fn span(&self) -> Span {
+        return match self {
+            AnnotationError::Unknown(span, _) => *span,
+            AnnotationError::InvalidTarget(span, _, _) => *span,
+            AnnotationError::Duplicate(span, _) => *span,
+            AnnotationError::UnknownArgument(span, _, _) => *span,
+            AnnotationError::ArgumentType(span, _, _) => *span,
+            AnnotationError::MissingArgument(span, _, _) => *span,
+        };
+    }
+}
+
+impl Display for AnnotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnnotationError::Unknown(_, name) => write!(f, "unknown annotation `@{}`", name),
+            AnnotationError::InvalidTarget(_, name, target) => {
+                write!(f, "`@{}` cannot be used on a {}", name, target.as_str())
+            }
+            AnnotationError::Duplicate(_, name) => write!(f, "`@{}` is used more than once", name),
+            AnnotationError::UnknownArgument(_, name, Some(argument)) => {
+                write!(f, "`@{}` has no argument named `{}`", name, argument)
+            }
+            AnnotationError::UnknownArgument(_, name, None) => {
+                write!(f, "too many arguments for `@{}`", name)
+            }
+            AnnotationError::ArgumentType(_, name, kind) => {
+                write!(f, "argument of `@{}` must be a {}", name, kind.as_str())
+            }
+            AnnotationError::MissingArgument(_, name, argument) => {
+                write!(f, "`@{}` is missing the `{}` argument", name, argument)
+            }
+        }
+    }
+}
+
+/// Set of known annotations used to validate the annotations in a tree,
+/// later passes look up the specs to find out what an annotation means
+#[derive(Debug, Clone)]
+pub struct AnnotationRegistry {
+    specs: HashMap<Name, AnnotationSpec>,
+}
+
+impl Default for AnnotationRegistry {
+    /// Registry containing the built in annotations
+    fn default() -> Self {
+        let mut registry = AnnotationRegistry::empty();
+        registry.register(AnnotationSpec {
+            name: "deprecated",
+            targets: AnnotationTarget::ALL,
+            arguments: vec![ArgumentSpec {
+                name: None,
+                kind: ArgumentKind::String,
+                required: false,
+            }],
+        });
+        registry.register(AnnotationSpec {
+            name: "json",
+            targets: &[AnnotationTarget::Struct, AnnotationTarget::Property],
+            arguments: vec![
+                ArgumentSpec {
+                    name: Some("name"),
+                    kind: ArgumentKind::String,
+                    required: false,
+                },
+                ArgumentSpec {
+                    name: Some("skip"),
+                    kind: ArgumentKind::Boolean,
+                    required: false,
+                },
+            ],
+        });
+        registry.register(AnnotationSpec {
+            name: "test",
+            targets: &[AnnotationTarget::Function],
+            arguments: Vec::new(),
+        });
+        return registry;
+    }
+}
+
+impl AnnotationRegistry {
+    pub fn empty() -> AnnotationRegistry {
+        return AnnotationRegistry {
+            specs: HashMap::new(),
+        };
+    }
+
+    pub fn register(&mut self, spec: AnnotationSpec) {
+        self.specs.insert(Name::intern(spec.name), spec);
+    }
+
+    pub fn get(&self, name: Name) -> Option<&AnnotationSpec> {
+        return self.specs.get(&name);
+    }
+
+    /// Checks every annotation in the tree returning all the problems found
+    pub fn validate(&self, source: &ASTSource) -> Vec<AnnotationError> {
+        let mut errors = Vec::new();
+        for id in source.ids() {
+            let (annotations, target) = match source.get(id) {
+                AST::Struct(value) => (&value.annotations, AnnotationTarget::Struct),
+                AST::Property(value) => (&value.annotations, AnnotationTarget::Property),
+                AST::Trait(value) => (&value.annotations, AnnotationTarget::Trait),
+                AST::FunctionStub(value) => (&value.annotations, AnnotationTarget::Function),
+                AST::Function(value) => (&value.stub.annotations, AnnotationTarget::Function),
+                _ => continue,
+            };
+            self.validate_all(annotations, target, &mut errors);
+        }
+        // Nodes are allocated children first so sort back into source order
+        errors.sort_by_key(|err| err.span().start);
+        return errors;
+    }
+
+    fn validate_all(
+        &self,
+        annotations: &[Annotation],
+        target: AnnotationTarget,
+        errors: &mut Vec<AnnotationError>,
+    ) {
+        for (index, annotation) in annotations.iter().enumerate() {
+            if annotations[..index].iter().any(|other| other.name == annotation.name) {
+                errors.push(AnnotationError::Duplicate(annotation.span, annotation.name));
+            }
+            self.validate_annotation(annotation, target, errors);
+        }
+    }
+
+    fn validate_annotation(
+        &self,
+        annotation: &Annotation,
+        target: AnnotationTarget,
+        errors: &mut Vec<AnnotationError>,
+    ) {
+        let name = annotation.name;
+        let spec = match self.get(name) {
+            Some(spec) => spec,
+            None => {
+                errors.push(AnnotationError::Unknown(annotation.span, name));
+                return;
+            }
+        };
+        if !spec.targets.contains(&target) {
+            errors.push(AnnotationError::InvalidTarget(annotation.span, name, target));
+        }
+
+        let mut positional = spec.arguments.iter().filter(|argument| argument.name.is_none());
+        let mut provided: Vec<&ArgumentSpec> = Vec::new();
+        for argument in &annotation.arguments {
+            let argument_spec = match argument.name {
+                Some(argument_name) => spec
+                    .arguments
+                    .iter()
+                    .find(|spec| matches!(spec.name, Some(value) if argument_name == value)),
+                None => positional.next(),
+            };
+            match argument_spec {
+                Some(argument_spec) => {
+                    Self::validate_argument(name, argument, argument_spec, errors);
+                    provided.push(argument_spec);
+                }
+                None => errors.push(AnnotationError::UnknownArgument(argument.span, name, argument.name)),
+            }
+        }
+
+        for argument_spec in &spec.arguments {
+            if argument_spec.required && !provided.iter().any(|value| std::ptr::eq(*value, argument_spec)) {
+                let argument_name = argument_spec.name.unwrap_or("value");
+                errors.push(AnnotationError::MissingArgument(annotation.span, name, argument_name));
+            }
+        }
+    }
+
+    fn validate_argument(
+        name: Name,
+        argument: &AnnotationArgument,
+        spec: &ArgumentSpec,
+        errors: &mut Vec<AnnotationError>,
+    ) {
+        if !spec.kind.matches(&argument.value) {
+            errors.push(AnnotationError::ArgumentType(argument.span, name, spec.kind));
+        }
+    }
+}
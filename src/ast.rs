@@ -1,335 +1,1973 @@
-use lazy_static::lazy_static;
+use std::fmt;
+use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
 
-use crate::{KeywordType, Literal, Modifier, Symbol, Token, TokenSet};
+use crate::intern::Name;
+use crate::lexer::{self, KeywordType, LexError, Modifier, Symbol, Token, TokenKind, TokenSet};
+use crate::source::Span;
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-enum Operator {
+/// Index of a node in an ASTSource arena. Ids are typed by the kind of node
+/// they point to, `NodeId` on its own (`NodeId<AST>`) can point at any node.
+pub struct NodeId<T = AST> {
+    index: u32,
+    kind: PhantomData<fn() -> T>,
+}
+
+impl<T> NodeId<T> {
+    fn new(index: usize) -> NodeId<T> {
+        return NodeId {
+            index: index as u32,
+            kind: PhantomData,
+        };
+    }
+
+    pub fn index(self) -> usize {
+        return self.index as usize;
+    }
+
+    /// Forgets the kind of node this id points to
+    pub fn untyped(self) -> NodeId {
+        return NodeId::new(self.index());
+    }
+}
+
+// Implemented by hand as deriving would require T to implement them too
+impl<T> Clone for NodeId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for NodeId<T> {}
+
+impl<T> PartialEq for NodeId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        return self.index == other.index;
+    }
+}
+
+impl<T> Eq for NodeId<T> {}
+
+impl<T> PartialOrd for NodeId<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for NodeId<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        return self.index.cmp(&other.index);
+    }
+}
+
+impl<T> Hash for NodeId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> Debug for NodeId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.index)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
     Equals,
+    NotEquals,
+    Less,
+    Greater,
+    LessEquals,
+    GreaterEquals,
     Plus,
     Minus,
     Multiply,
     Divide,
+    Modulo,
     Or,
     Xor,
     And,
     AndAnd,
     OrOr,
+    // `??`, the right hand side when the left is null or ndef
+    Coalesce,
+    // Unary operators, these have no right hand side
+    Not,
+    Negate,
+}
+
+impl Operator {
+    fn from_binary(symbol: Symbol) -> Option<Operator> {
+        return match symbol {
+            Symbol::EqualsEquals => Some(Operator::Equals),
+            Symbol::NotEquals => Some(Operator::NotEquals),
+            Symbol::Left => Some(Operator::Less),
+            Symbol::Right => Some(Operator::Greater),
+            Symbol::LeftEquals => Some(Operator::LessEquals),
+            Symbol::RightEquals => Some(Operator::GreaterEquals),
+            Symbol::Plus => Some(Operator::Plus),
+            Symbol::Minus => Some(Operator::Minus),
+            Symbol::Multiply => Some(Operator::Multiply),
+            Symbol::Divide => Some(Operator::Divide),
+            Symbol::Percent => Some(Operator::Modulo),
+            Symbol::Pipe => Some(Operator::Or),
+            Symbol::Caret => Some(Operator::Xor),
+            Symbol::And => Some(Operator::And),
+            Symbol::AndAnd => Some(Operator::AndAnd),
+            Symbol::OrOr => Some(Operator::OrOr),
+            Symbol::QuestionQuestion => Some(Operator::Coalesce),
+            _ => None,
+        };
+    }
+
+    fn from_unary(symbol: Symbol) -> Option<Operator> {
+        return match symbol {
+            Symbol::Exclamation => Some(Operator::Not),
+            Symbol::Minus => Some(Operator::Negate),
+            _ => None,
+        };
+    }
+
+    /// Binding power of a binary operator, higher binds tighter
+    pub fn precedence(&self) -> u8 {
+        return match self {
+            Operator::Coalesce => 1,
+            Operator::OrOr => 2,
+            Operator::AndAnd => 3,
+            Operator::Equals | Operator::NotEquals => 4,
+            Operator::Less | Operator::Greater | Operator::LessEquals | Operator::GreaterEquals => 5,
+            Operator::Or => 6,
+            Operator::Xor => 7,
+            Operator::And => 8,
+            Operator::Plus | Operator::Minus => 9,
+            Operator::Multiply | Operator::Divide | Operator::Modulo => 10,
+            Operator::Not | Operator::Negate => 11,
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Operator::Equals => "==",
+            Operator::NotEquals => "!=",
+            Operator::Less => "<",
+            Operator::Greater => ">",
+            Operator::LessEquals => "<=",
+            Operator::GreaterEquals => ">=",
+            Operator::Plus => "+",
+            Operator::Minus | Operator::Negate => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Modulo => "%",
+            Operator::Or => "|",
+            Operator::Xor => "^",
+            Operator::And => "&",
+            Operator::AndAnd => "&&",
+            Operator::OrOr => "||",
+            Operator::Coalesce => "??",
+            Operator::Not => "!",
+        };
+    }
 }
 
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
-struct Operation {
-    left: AST,
-    operator: Operator,
-    right: Option<AST>,
+pub struct Operation {
+    pub left: NodeId,
+    pub operator: Operator,
+    pub right: Option<NodeId>,
 }
 
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
-enum ConditionType {
-    If,
-    IfElse,
-    Else,
+pub struct Variable {
+    pub constant: bool,
+    pub name: Name,
+    pub modifiers: Vec<Modifier>,
+    pub type_of: Option<DataType>,
 }
 
+/// Metadata attached to a declaration e.g. `@json(name = "user_id")`
 #[derive(Debug, Clone)]
-struct Variable {
-    constant: bool,
-    name: String,
-    modifiers: Vec<Modifier>,
-    type_of: DataType,
+pub struct Annotation {
+    pub name: Name,
+    pub arguments: Vec<AnnotationArgument>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
-struct StructProperty {
-    name: String,
-    type_of: DataType,
+pub struct AnnotationArgument {
+    // None for positional arguments
+    pub name: Option<Name>,
+    pub value: Literal,
+    pub span: Span,
+}
+
+impl Annotation {
+    pub fn argument(&self, name: &str) -> Option<&Literal> {
+        return self
+            .arguments
+            .iter()
+            .find(|argument| matches!(argument.name, Some(value) if value == name))
+            .map(|argument| &argument.value);
+    }
+
+    pub fn positional(&self, index: usize) -> Option<&Literal> {
+        return self
+            .arguments
+            .iter()
+            .filter(|argument| argument.name.is_none())
+            .nth(index)
+            .map(|argument| &argument.value);
+    }
+}
+
+/// Declarations that can carry annotations
+pub trait Annotated {
+    fn annotations(&self) -> &[Annotation];
+
+    fn annotation(&self, name: &str) -> Option<&Annotation> {
+        return self.annotations().iter().find(|annotation| annotation.name == name);
+    }
 }
 
 #[derive(Debug, Clone)]
-struct SpewStruct {
-    name: String,
-    properties: Vec<StructProperty>,
+pub struct StructProperty {
+    pub annotations: Vec<Annotation>,
+    // `pub` fields can be accessed from other files
+    pub public: bool,
+    pub name: Name,
+    // `name?: T` properties can be left out, reading them gives ndef
+    pub optional: bool,
+    pub type_of: DataType,
 }
 
 #[derive(Debug, Clone)]
+pub struct SpewStruct {
+    pub annotations: Vec<Annotation>,
+    pub public: bool,
+    pub name: Name,
+    pub properties: Vec<NodeId<StructProperty>>,
+}
+
+/// Type as written, a union like `str | num?` is its first member with the
+/// others in `union`
+#[derive(Debug, Clone, PartialEq)]
 pub struct DataType {
-    name: String,
-    nullable: bool,
+    pub name: Name,
+    pub nullable: bool,
+    // Of the first member only
+    pub span: Span,
+    pub union: Vec<DataType>,
+}
+
+impl DataType {
+    /// Every member of a union, or only the type itself
+    pub fn members(&self) -> impl Iterator<Item = &DataType> {
+        return std::iter::once(self).chain(&self.union);
+    }
+
+    /// Span of the whole type, with every member of a union
+    pub fn full_span(&self) -> Span {
+        let end = self.union.last().map_or(self.span.end, |member| member.span.end);
+        return Span::new(self.span.start, end);
+    }
+}
+
+impl Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, member) in self.members().enumerate() {
+            if index > 0 {
+                f.write_str(" | ")?;
+            }
+            write!(f, "{}", member.name)?;
+            if member.nullable {
+                f.write_str("?")?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct FunctionArgument {
-    pub name: String,
-    pub data_type: DataType,
+    pub name: Name,
+    // Only lambda arguments can leave out their type
+    pub data_type: Option<DataType>,
 }
 
 #[derive(Debug, Clone)]
 pub struct FunctionStub {
-    pub name: String,
+    pub annotations: Vec<Annotation>,
+    pub name: Name,
     pub modifiers: Vec<Modifier>,
-    pub arguments: Vec<FunctionArgument>,
+    pub arguments: Vec<NodeId<FunctionArgument>>,
     pub return_type: Option<DataType>,
 }
 
 #[derive(Debug, Clone)]
 pub struct SpewFunction {
     pub stub: FunctionStub,
-    pub body: Vec<AST>,
+    pub body: Vec<NodeId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpewTrait {
+    pub annotations: Vec<Annotation>,
+    pub public: bool,
+    pub name: Name,
+    // Traits every implementor also has to implement, `trait A: B + C`
+    pub supertraits: Vec<Name>,
+    pub functions: Vec<NodeId<FunctionStub>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct SpewImpl {
-    pub functions: Vec<SpewFunction>,
+    // The trait being implemented, None for inherent impls
+    pub trait_name: Option<Name>,
+    pub name: Name,
+    // `const` variables, read through the type like static functions
+    pub constants: Vec<NodeId>,
+    pub functions: Vec<NodeId<SpewFunction>>,
+}
+
+/// Pattern a match arm compares its value against
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Literal(Literal),
+    // `_` matches anything
+    Wildcard,
+    // Matches anything and binds the value to the name inside the arm
+    Binding(Name),
+    // `is T` matches values of the type
+    Type(DataType),
+}
+
+/// Named type, `type Both = A & B`. The value is the intersection of every
+/// type listed, or any one of them for unions like `type Value = str | num`.
+/// Plain aliases have a single type.
+#[derive(Debug, Clone)]
+pub struct TypeAlias {
+    pub public: bool,
+    pub name: Name,
+    pub types: Vec<DataType>,
+    pub union: bool,
+}
+
+/// Anonymous function, `fun (a, b: num) -> num { ... }`
+#[derive(Debug, Clone)]
+pub struct Lambda {
+    pub arguments: Vec<NodeId<FunctionArgument>>,
+    pub return_type: Option<DataType>,
+    pub body: Vec<NodeId>,
+}
+
+/// Name listed by an import, `B as C` makes `B` available as `C`
+#[derive(Debug, Clone)]
+pub struct ImportName {
+    pub name: Name,
+    pub alias: Option<Name>,
+    pub span: Span,
+}
+
+/// What an import makes available from the file
+#[derive(Debug, Clone)]
+pub enum ImportNames {
+    // `*`, every `pub` item under its own name
+    All,
+    // `* as other`, items are used as `other.name` and types as `other.Name`
+    Namespace(Name),
+    // `{ a, B as C }`
+    Listed(Vec<ImportName>),
+}
+
+/// Items of another file, `import * from "other"`. The path is relative to
+/// the importing file and kept as the text of a double quoted literal.
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub names: ImportNames,
+    pub path: String,
+}
+
+impl Import {
+    /// Path of the imported file as written, without its extension
+    pub fn file(&self) -> String {
+        return unescape(&self.path);
+    }
+}
+
+/// Name of a type reached through a namespace import, `other.Name`
+pub fn qualified(namespace: Name, name: Name) -> Name {
+    return Name::intern(&format!("{}.{}", namespace, name));
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    // Expression or block the arm evaluates to
+    pub body: NodeId,
+}
+
+impl Annotated for StructProperty {
+    fn annotations(&self) -> &[Annotation] {
+        return &self.annotations;
+    }
+}
+
+impl Annotated for SpewStruct {
+    fn annotations(&self) -> &[Annotation] {
+        return &self.annotations;
+    }
+}
+
+impl Annotated for FunctionStub {
+    fn annotations(&self) -> &[Annotation] {
+        return &self.annotations;
+    }
+}
+
+impl Annotated for SpewFunction {
+    fn annotations(&self) -> &[Annotation] {
+        return &self.stub.annotations;
+    }
+}
+
+impl Annotated for SpewTrait {
+    fn annotations(&self) -> &[Annotation] {
+        return &self.annotations;
+    }
+}
+
+// Owned copy of a lexer literal so the tree doesn't borrow from the source,
+// strings are kept as the text of a double quoted literal
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    String(String),
+    Number(String),
+    Boolean(bool),
+    Null,
+    Undefined,
+}
+
+/// Value of a string literal from its text. Unknown escapes stand for the
+/// character they escape.
+pub fn unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            out.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('0') => out.push('\0'),
+            Some(escaped) => out.push(escaped),
+            None => {}
+        }
+    }
+    return out;
+}
+
+// Text of a single quoted string as it is written between double quotes
+fn requote(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '"' => out.push_str("\\\""),
+            '\\' => match chars.next() {
+                Some('\'') => out.push('\''),
+                Some(escaped) => {
+                    out.push('\\');
+                    out.push(escaped);
+                }
+                None => out.push('\\'),
+            },
+            char => out.push(char),
+        }
+    }
+    return out;
 }
 
+impl From<&lexer::Literal<'_>> for Literal {
+    fn from(value: &lexer::Literal<'_>) -> Literal {
+        return match value {
+            lexer::Literal::String(value, '"') => Literal::String(value.to_string()),
+            lexer::Literal::String(value, _) => Literal::String(requote(value)),
+            lexer::Literal::Number(value) => Literal::Number(value.to_string()),
+            lexer::Literal::Boolean(value) => Literal::Boolean(*value),
+            lexer::Literal::Null => Literal::Null,
+            lexer::Literal::Undefined => Literal::Undefined,
+        };
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // Strings are kept with their escapes so they print back as written
+            Literal::String(value) => write!(f, "\"{}\"", value),
+            Literal::Number(value) => f.write_str(value),
+            Literal::Boolean(value) => write!(f, "{}", value),
+            Literal::Null => f.write_str("null"),
+            Literal::Undefined => f.write_str("ndef"),
+        }
+    }
+}
+
+/// A node in the tree, children are referenced by id into the ASTSource
+/// the node belongs to
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub enum AST {
     Variable {
-        name: String,
-        modifiers: Vec<Modifier>,
-        value: Option<Box<AST>>,
+        variable: Variable,
+        value: Option<NodeId>,
     },
     Literal(Literal),
-    Operation(Box<Operation>),
+    Identifier(Name),
+    Operation(Operation),
+    Call {
+        target: NodeId,
+        arguments: Vec<NodeId>,
+    },
+    Member {
+        target: NodeId,
+        name: Name,
+    },
+    // `Type::name`, a static function or constant of a type
+    Path {
+        data_type: DataType,
+        name: Name,
+    },
+    // `value is T`, whether the value is of a type
+    Is {
+        value: NodeId,
+        data_type: DataType,
+    },
+    // `target = value` where the target is a variable or a property
+    Assign {
+        target: NodeId,
+        value: NodeId,
+    },
     ConditionBlock {
-        condition: Box<Vec<Operation>>,
-        contents: Box<Vec<AST>>,
+        condition: NodeId,
+        contents: Vec<NodeId>,
+        // Either another condition block for `else if` or a plain block
+        otherwise: Option<NodeId>,
+    },
+    // `while condition { ... }`
+    While {
+        condition: NodeId,
+        contents: Vec<NodeId>,
+    },
+    // Leaves the innermost `while`
+    Break,
+    Return(Option<NodeId>),
+    Block(Vec<NodeId>),
+    Match {
+        value: NodeId,
+        arms: Vec<NodeId<MatchArm>>,
     },
-    Block(Box<Vec<AST>>),
+    Arm(MatchArm),
+    Lambda(Lambda),
     Struct(SpewStruct),
+    Property(StructProperty),
+    Trait(SpewTrait),
+    // Function declared without a body inside of a trait
+    FunctionStub(FunctionStub),
     Impl(SpewImpl),
     Function(SpewFunction),
+    Argument(FunctionArgument),
+    TypeAlias(TypeAlias),
+    Import(Import),
 }
 
+impl AST {
+    /// Ids of the direct children of this node in source order
+    pub fn children(&self) -> Vec<NodeId> {
+        let mut children = Vec::new();
+        match self {
+            AST::Variable { value, .. } => children.extend(*value),
+            AST::Literal(_)
+            | AST::Identifier(_)
+            | AST::Path { .. }
+            | AST::Property(_)
+            | AST::Argument(_)
+            | AST::TypeAlias(_)
+            | AST::Import(_) => {}
+            AST::Operation(operation) => {
+                children.push(operation.left);
+                children.extend(operation.right);
+            }
+            AST::Call { target, arguments } => {
+                children.push(*target);
+                children.extend(arguments);
+            }
+            AST::Member { target, .. } => children.push(*target),
+            AST::Is { value, .. } => children.push(*value),
+            AST::Assign { target, value } => {
+                children.push(*target);
+                children.push(*value);
+            }
+            AST::ConditionBlock {
+                condition,
+                contents,
+                otherwise,
+            } => {
+                children.push(*condition);
+                children.extend(contents);
+                children.extend(*otherwise);
+            }
+            AST::While { condition, contents } => {
+                children.push(*condition);
+                children.extend(contents);
+            }
+            AST::Break => {}
+            AST::Return(value) => children.extend(*value),
+            AST::Block(contents) => children.extend(contents),
+            AST::Match { value, arms } => {
+                children.push(*value);
+                children.extend(arms.iter().map(|id| id.untyped()));
+            }
+            AST::Arm(arm) => children.push(arm.body),
+            AST::Lambda(value) => {
+                children.extend(value.arguments.iter().map(|id| id.untyped()));
+                children.extend(&value.body);
+            }
+            AST::Struct(value) => children.extend(value.properties.iter().map(|id| id.untyped())),
+            AST::Trait(value) => children.extend(value.functions.iter().map(|id| id.untyped())),
+            AST::FunctionStub(value) => children.extend(value.arguments.iter().map(|id| id.untyped())),
+            AST::Impl(value) => {
+                children.extend(&value.constants);
+                children.extend(value.functions.iter().map(|id| id.untyped()));
+            }
+            AST::Function(value) => {
+                children.extend(value.stub.arguments.iter().map(|id| id.untyped()));
+                children.extend(&value.body);
+            }
+        }
+        return children;
+    }
+
+    /// Replaces the ids of the direct children with what `map` returns for
+    /// them, visiting them in the same order as `children`. Typed ids keep
+    /// their kind so `map` has to return a node of the same kind.
+    pub fn map_children(&mut self, mut map: impl FnMut(NodeId) -> NodeId) {
+        fn remap<T>(id: &mut NodeId<T>, map: &mut impl FnMut(NodeId) -> NodeId) {
+            *id = NodeId::new(map(id.untyped()).index());
+        }
+        let map = &mut map;
+        match self {
+            AST::Variable { value, .. } => value.iter_mut().for_each(|id| remap(id, map)),
+            AST::Literal(_)
+            | AST::Identifier(_)
+            | AST::Path { .. }
+            | AST::Property(_)
+            | AST::Argument(_)
+            | AST::TypeAlias(_)
+            | AST::Import(_) => {}
+            AST::Operation(operation) => {
+                remap(&mut operation.left, map);
+                operation.right.iter_mut().for_each(|id| remap(id, map));
+            }
+            AST::Call { target, arguments } => {
+                remap(target, map);
+                arguments.iter_mut().for_each(|id| remap(id, map));
+            }
+            AST::Member { target, .. } => remap(target, map),
+            AST::Is { value, .. } => remap(value, map),
+            AST::Assign { target, value } => {
+                remap(target, map);
+                remap(value, map);
+            }
+            AST::ConditionBlock {
+                condition,
+                contents,
+                otherwise,
+            } => {
+                remap(condition, map);
+                contents.iter_mut().for_each(|id| remap(id, map));
+                otherwise.iter_mut().for_each(|id| remap(id, map));
+            }
+            AST::While { condition, contents } => {
+                remap(condition, map);
+                contents.iter_mut().for_each(|id| remap(id, map));
+            }
+            AST::Break => {}
+            AST::Return(value) => value.iter_mut().for_each(|id| remap(id, map)),
+            AST::Block(contents) => contents.iter_mut().for_each(|id| remap(id, map)),
+            AST::Match { value, arms } => {
+                remap(value, map);
+                arms.iter_mut().for_each(|id| remap(id, map));
+            }
+            AST::Arm(arm) => remap(&mut arm.body, map),
+            AST::Lambda(value) => {
+                value.arguments.iter_mut().for_each(|id| remap(id, map));
+                value.body.iter_mut().for_each(|id| remap(id, map));
+            }
+            AST::Struct(value) => value.properties.iter_mut().for_each(|id| remap(id, map)),
+            AST::Trait(value) => value.functions.iter_mut().for_each(|id| remap(id, map)),
+            AST::FunctionStub(value) => value.arguments.iter_mut().for_each(|id| remap(id, map)),
+            AST::Impl(value) => {
+                value.constants.iter_mut().for_each(|id| remap(id, map));
+                value.functions.iter_mut().for_each(|id| remap(id, map));
+            }
+            AST::Function(value) => {
+                value.stub.arguments.iter_mut().for_each(|id| remap(id, map));
+                value.body.iter_mut().for_each(|id| remap(id, map));
+            }
+        }
+    }
+}
+
+/// Kinds of node that can be stored in the arena and looked up through a
+/// typed NodeId
+pub trait NodeKind: Sized {
+    fn into_ast(self) -> AST;
+
+    fn from_ast(value: &AST) -> Option<&Self>;
+
+    fn from_ast_mut(value: &mut AST) -> Option<&mut Self>;
+}
+
+impl NodeKind for AST {
+    fn into_ast(self) -> AST {
+        return self;
+    }
+
+    fn from_ast(value: &AST) -> Option<&Self> {
+        return Some(value);
+    }
+
+    fn from_ast_mut(value: &mut AST) -> Option<&mut Self> {
+        return Some(value);
+    }
+}
+
+macro_rules! node_kinds {
+    ($($kind:ident: $variant:ident),* $(,)?) => {
+        $(
+            impl NodeKind for $kind {
+                fn into_ast(self) -> AST {
+                    return AST::$variant(self);
+                }
+
+                fn from_ast(value: &AST) -> Option<&Self> {
+                    return match value {
+                        AST::$variant(value) => Some(value),
+                        _ => None,
+                    };
+                }
+
+                fn from_ast_mut(value: &mut AST) -> Option<&mut Self> {
+                    return match value {
+                        AST::$variant(value) => Some(value),
+                        _ => None,
+                    };
+                }
+            }
+        )*
+    };
+}
+
+node_kinds! {
+    SpewStruct: Struct,
+    StructProperty: Property,
+    SpewTrait: Trait,
+    SpewImpl: Impl,
+    SpewFunction: Function,
+    FunctionArgument: Argument,
+    MatchArm: Arm,
+    Lambda: Lambda,
+    TypeAlias: TypeAlias,
+    Import: Import,
+}
 
+// Functions are also viewable as their stub so trait stubs and implemented
+// functions can be handled the same way
+impl NodeKind for FunctionStub {
+    fn into_ast(self) -> AST {
+        return AST::FunctionStub(self);
+    }
+
+    fn from_ast(value: &AST) -> Option<&Self> {
+        return match value {
+            AST::FunctionStub(value) => Some(value),
+            AST::Function(value) => Some(&value.stub),
+            _ => None,
+        };
+    }
+
+    fn from_ast_mut(value: &mut AST) -> Option<&mut Self> {
+        return match value {
+            AST::FunctionStub(value) => Some(value),
+            AST::Function(value) => Some(&mut value.stub),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NodeData {
+    value: AST,
+    span: Span,
+    parent: Option<NodeId>,
+}
+
+/// File parsed into an arena. Its spans start at `offset` so they never
+/// overlap those of the other files.
 #[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: String,
+    pub offset: usize,
+    // Index of its first item in the roots of the arena
+    first_root: usize,
+}
+
+/// Arena holding every node of a parsed source. Nodes are addressed by
+/// NodeId which stays valid for the life of the arena, so analysis passes
+/// can keep their results in a NodeMap instead of changing the tree.
+#[derive(Debug, Clone, Default)]
 pub struct ASTSource {
-    values: Vec<AST>,
+    nodes: Vec<NodeData>,
+    // Top level items in source order, file after file
+    roots: Vec<NodeId>,
+    files: Vec<SourceFile>,
+    // File each import was linked to once it was loaded
+    imports: NodeMap<usize>,
+    // Offset the spans of the next file parsed into the arena start at
+    end: usize,
+}
+
+impl ASTSource {
+    pub fn new() -> ASTSource {
+        return ASTSource::default();
+    }
+
+    /// Adds a node to the arena, its children become parented to it
+    pub fn alloc<T: NodeKind>(&mut self, value: T, span: Span) -> NodeId<T> {
+        let id: NodeId<T> = NodeId::new(self.nodes.len());
+        let value = value.into_ast();
+        for child in value.children() {
+            self.nodes[child.index()].parent = Some(id.untyped());
+        }
+        self.nodes.push(NodeData {
+            value,
+            span,
+            parent: None,
+        });
+        return id;
+    }
+
+    pub fn push_root<T>(&mut self, id: NodeId<T>) {
+        self.roots.push(id.untyped());
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        return &self.roots;
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        return &self.files;
+    }
+
+    /// Top level items of one file, every item for trees built by hand
+    pub fn file_roots(&self, file: usize) -> &[NodeId] {
+        let Some(value) = self.files.get(file) else { return &self.roots };
+        let end = self.files.get(file + 1).map_or(self.roots.len(), |next| next.first_root);
+        return &self.roots[value.first_root..end];
+    }
+
+    /// Index of the file a span is in
+    pub fn file_of(&self, span: Span) -> usize {
+        return self.files.iter().rposition(|file| file.offset <= span.start).unwrap_or(0);
+    }
+
+    pub fn same_file(&self, left: Span, right: Span) -> bool {
+        return self.file_of(left) == self.file_of(right);
+    }
+
+    /// Records the file an import refers to
+    pub fn link(&mut self, id: NodeId<Import>, file: usize) {
+        self.imports.insert(id, file);
+    }
+
+    /// File an import refers to, None until it has been loaded
+    pub fn imported(&self, id: NodeId<Import>) -> Option<usize> {
+        return self.imports.get(id).copied();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.nodes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.nodes.is_empty();
+    }
+
+    /// Every node id in the arena in allocation order, children always
+    /// come before their parents
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> {
+        return (0..self.nodes.len()).map(NodeId::new);
+    }
+
+    pub fn get<T: NodeKind>(&self, id: NodeId<T>) -> &T {
+        return T::from_ast(&self.nodes[id.index()].value).expect("node id points at a node of another kind");
+    }
+
+    pub fn get_mut<T: NodeKind>(&mut self, id: NodeId<T>) -> &mut T {
+        return T::from_ast_mut(&mut self.nodes[id.index()].value).expect("node id points at a node of another kind");
+    }
+
+    /// Narrows an untyped id to a specific kind of node
+    pub fn cast<T: NodeKind>(&self, id: NodeId) -> Option<NodeId<T>> {
+        return T::from_ast(&self.nodes[id.index()].value).map(|_| NodeId::new(id.index()));
+    }
+
+    /// Swaps out the value of a node keeping its id, span and parent so
+    /// anything referencing the node now sees the new value
+    pub fn replace<T: NodeKind>(&mut self, id: NodeId, value: T) -> AST {
+        let value = value.into_ast();
+        for child in value.children() {
+            self.nodes[child.index()].parent = Some(id);
+        }
+        return std::mem::replace(&mut self.nodes[id.index()].value, value);
+    }
+
+    pub fn span<T>(&self, id: NodeId<T>) -> Span {
+        return self.nodes[id.index()].span;
+    }
+
+    pub fn parent<T>(&self, id: NodeId<T>) -> Option<NodeId> {
+        return self.nodes[id.index()].parent;
+    }
+
+    /// The parent of the node then its parent and so on up to the root
+    pub fn ancestors<T>(&self, id: NodeId<T>) -> impl Iterator<Item = NodeId> + '_ {
+        return std::iter::successors(self.parent(id), move |id| self.parent(*id));
+    }
+
+    /// Splits binary operations nested on their left such as `a + b * c - d`
+    /// into the first operand and the operations applied to it, innermost
+    /// first. Chains can be arbitrarily long so passes walk them in a loop
+    /// with this instead of recursing on the left operand.
+    pub fn chain(&self, id: NodeId) -> (NodeId, Vec<NodeId>) {
+        let mut operations = Vec::new();
+        let mut current = id;
+        while let AST::Operation(Operation { left, right: Some(_), .. }) = self.get(current) {
+            operations.push(current);
+            current = *left;
+        }
+        operations.reverse();
+        return (current, operations);
+    }
 }
 
+/// Side table storing a value per node, used by passes to attach results
+/// such as types or resolved declarations to the tree
 #[derive(Debug, Clone)]
-pub struct ASTState {
-    result: Vec<AST>,
-    token_set: TokenSet,
+pub struct NodeMap<V> {
+    values: Vec<Option<V>>,
 }
 
-impl ASTState {
-    fn next_token(&mut self) -> Option<Token> {
-        return self.token_set.next_token();
+impl<V> Default for NodeMap<V> {
+    fn default() -> Self {
+        return NodeMap { values: Vec::new() };
+    }
+}
+
+impl<V> NodeMap<V> {
+    pub fn new() -> NodeMap<V> {
+        return NodeMap::default();
+    }
+
+    pub fn insert<T>(&mut self, id: NodeId<T>, value: V) -> Option<V> {
+        let index = id.index();
+        if self.values.len() <= index {
+            self.values.resize_with(index + 1, || None);
+        }
+        return self.values[index].replace(value);
+    }
+
+    pub fn get<T>(&self, id: NodeId<T>) -> Option<&V> {
+        return self.values.get(id.index()).and_then(|value| value.as_ref());
     }
 
-    fn back_token(&mut self) {
-        self.token_set.back(1)
+    pub fn get_mut<T>(&mut self, id: NodeId<T>) -> Option<&mut V> {
+        return self.values.get_mut(id.index()).and_then(|value| value.as_mut());
     }
 
-    fn push_ast(&mut self, value: AST) {
-        self.result.push(value);
+    /// Value of a node, the default is inserted first when it has none
+    pub fn get_or_default<T>(&mut self, id: NodeId<T>) -> &mut V
+        where
+            V: Default,
+    {
+        if !self.contains(id) {
+            self.insert(id, V::default());
+        }
+        return self.get_mut(id).unwrap();
+    }
+
+    pub fn remove<T>(&mut self, id: NodeId<T>) -> Option<V> {
+        return self.values.get_mut(id.index()).and_then(|value| value.take());
+    }
+
+    pub fn contains<T>(&self, id: NodeId<T>) -> bool {
+        return self.get(id).is_some();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &V)> {
+        return self
+            .values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| value.as_ref().map(|value| (NodeId::new(index), value)));
     }
 }
 
-macro_rules! expect_ident {
-    ($state:ident) => {
-         if let Some(token) = $state.next_token() {
-            if let Token::Ident(ident) = token {
-                ident
-            } else {
-                return Err(ASTError::UnexpectedToken(Some(token)));
+#[derive(Debug, Clone)]
+pub struct ASTState<'a> {
+    source: ASTSource,
+    token_set: TokenSet<'a>,
+    // Kinds that were checked for at the current position without matching,
+    // reported as the alternatives when the parser gives up there
+    expected: Vec<TokenKind>,
+    // Current and maximum nesting of blocks and expressions, the limit keeps
+    // deeply nested input from overflowing the stack
+    depth: usize,
+    max_depth: usize,
+}
+
+/// Default limit for how deeply blocks and expressions can be nested
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub max_depth: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        return ParseOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+        };
+    }
+}
+
+impl<'a> ASTState<'a> {
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.expected.clear();
+        return self.token_set.next_token();
+    }
+
+    fn peek_token(&mut self) -> Option<Token<'a>> {
+        return self.token_set.peek(0);
+    }
+
+    /// Whether the next token is of the provided kind, when it isn't the
+    /// kind is remembered as something that would have been accepted
+    fn check(&mut self, kind: TokenKind) -> bool {
+        if let Some(token) = self.peek_token() {
+            if token.is(kind) {
+                return true;
             }
-        } else {
-            return Err(ASTError::Incomplete);
         }
-    };
-}
-macro_rules! expect_symbol {
-    ($state:ident, $symbol:ident) => {
-         if let Some(token) = $state.next_token() {
-            if let Token::Symbol(ref symbol) = token {
-                match symbol {
-                    Symbol::$symbol => {},
-                    _ => return Err(ASTError::UnexpectedToken(Some(token.clone())))
-                }
-            } else {
-               return Err(ASTError::UnexpectedToken(Some(token)))
+        self.remember(kind);
+        return false;
+    }
+
+    fn remember(&mut self, kind: TokenKind) {
+        if !self.expected.contains(&kind) {
+            self.expected.push(kind);
+        }
+    }
+
+    /// Consumes the next token if it is of the provided kind
+    fn eat(&mut self, kind: TokenKind) -> Option<Token<'a>> {
+        return match self.token_set.expect(kind) {
+            Ok(token) => {
+                self.expected.clear();
+                Some(token)
+            }
+            Err(_) => {
+                self.remember(kind);
+                None
+            }
+        };
+    }
+
+    fn eat_symbol(&mut self, symbol: Symbol) -> bool {
+        return self.eat(TokenKind::Symbol(symbol)).is_some();
+    }
+
+    /// Consumes the next token failing if it isn't of the provided kind
+    fn expect(&mut self, kind: TokenKind) -> ASTResult<Token<'a>> {
+        return match self.eat(kind) {
+            Some(token) => Ok(token),
+            None => Err(self.unexpected()),
+        };
+    }
+
+    fn expect_symbol(&mut self, symbol: Symbol) -> ASTResult<()> {
+        self.expect(TokenKind::Symbol(symbol))?;
+        Ok(())
+    }
+
+    fn expect_ident(&mut self) -> ASTResult<Name> {
+        return match self.expect(TokenKind::Ident)? {
+            Token::Ident(ident) => Ok(ident),
+            _ => unreachable!(),
+        };
+    }
+
+    /// Consumes the next token if it is any of the provided kinds
+    fn one_of(&mut self, kinds: &[TokenKind]) -> ASTResult<Token<'a>> {
+        for kind in kinds {
+            if let Some(token) = self.eat(*kind) {
+                return Ok(token);
             }
-        } else {
-            return Err(ASTError::Incomplete)
         }
-    };
-}
-macro_rules! is_symbol_next {
-    ($state:ident, $symbol:ident) => {
-         if let Some(token) = $state.next_token() {
-            if let Token::Symbol(ref symbol) = token {
-                match symbol {
-                    Symbol::$symbol => true,
-                    _ => {
-                        $state.back_token();
-                        false
-                    }
-                }
-            } else {
-                $state.back_token();
-                false
+        return Err(self.unexpected());
+    }
+
+    /// Error for the next token listing everything that was expected in its place
+    fn unexpected(&mut self) -> ASTError {
+        let span = self.token_set.peek_span(0);
+        let expected = self.expected.clone();
+        return match self.peek_token() {
+            Some(token) => ASTError::UnexpectedToken(span, token.to_string(), expected),
+            None => ASTError::Incomplete(span, expected),
+        };
+    }
+
+    /// Parses items until the closing symbol, which is consumed
+    fn until<T, F>(&mut self, close: Symbol, mut parse: F) -> ASTResult<Vec<T>>
+        where
+            F: FnMut(&mut Self) -> ASTResult<T>,
+    {
+        let mut values = Vec::new();
+        while !self.eat_symbol(close) {
+            values.push(parse(self)?);
+        }
+        Ok(values)
+    }
+
+    /// Parses a list of items between the open and close symbols, e.g. a
+    /// block of struct properties
+    fn delimited<T, F>(&mut self, open: Symbol, close: Symbol, parse: F) -> ASTResult<Vec<T>>
+        where
+            F: FnMut(&mut Self) -> ASTResult<T>,
+    {
+        self.expect_symbol(open)?;
+        return self.until(close, parse);
+    }
+
+    /// Parses items separated by the separator symbol until the closing
+    /// symbol, a trailing separator is allowed
+    fn separated<T, F>(&mut self, separator: Symbol, close: Symbol, mut parse: F) -> ASTResult<Vec<T>>
+        where
+            F: FnMut(&mut Self) -> ASTResult<T>,
+    {
+        let mut values = Vec::new();
+        while !self.eat_symbol(close) {
+            values.push(parse(self)?);
+            if !self.eat_symbol(separator) {
+                self.expect_symbol(close)?;
+                break;
             }
-        } else {
-            $state.back_token();
-            false
         }
-    };
+        Ok(values)
+    }
+
+    /// Runs a parser one nesting level deeper failing cleanly once the
+    /// nesting limit has been reached
+    fn nested<T, F>(&mut self, parse: F) -> ASTResult<T>
+        where
+            F: FnOnce(&mut Self) -> ASTResult<T>,
+    {
+        if self.depth >= self.max_depth {
+            return Err(ASTError::TooDeep(self.token_set.peek_span(0), self.max_depth));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        return result;
+    }
+
+    /// Start offset of the next token, used as the start of a node's span
+    fn start(&mut self) -> usize {
+        return self.token_set.peek_span(0).start;
+    }
+
+    /// Adds a node spanning from `start` to the end of the last consumed token
+    fn alloc<T: NodeKind>(&mut self, value: T, start: usize) -> NodeId<T> {
+        let span = Span::new(start, self.token_set.span().end);
+        return self.alloc_span(value, span);
+    }
+
+    fn alloc_span<T: NodeKind>(&mut self, value: T, span: Span) -> NodeId<T> {
+        return self.source.alloc(value, span);
+    }
+
+    /// Prefix and postfix chains like `!!x` or `a.b()` nest every node in the
+    /// one before but are built in a loop, fails once a chain of `layers`
+    /// nodes would nest past the nesting limit
+    fn layered(&mut self, layers: usize) -> ASTResult<()> {
+        if self.depth + layers > self.max_depth {
+            return Err(ASTError::TooDeep(self.token_set.span(), self.max_depth));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ASTError {
-    UnexpectedToken(Option<Token>),
-    Incomplete,
+    UnexpectedToken(Span, String, Vec<TokenKind>),
+    Incomplete(Span, Vec<TokenKind>),
+    // Nesting went past the limit which is included
+    TooDeep(Span, usize),
+    // Literal other than a string given as the file of an import
+    ImportPath(Span, String),
+    Lexer(LexError),
+}
+
+impl ASTError {
+    pub fn span(&self) -> Span {
+        return match self {
+            ASTError::UnexpectedToken(span, _, _) => *span,
+            ASTError::Incomplete(span, _) => *span,
+            ASTError::TooDeep(span, _) => *span,
+            ASTError::ImportPath(span, _) => *span,
+            ASTError::Lexer(err) => Span::new(err.position, err.position),
+        };
+    }
+}
+
+fn write_expected(f: &mut fmt::Formatter<'_>, expected: &[TokenKind]) -> fmt::Result {
+    match expected {
+        [] => Ok(()),
+        [kind] => write!(f, ", expected {}", kind),
+        [kinds @ .., last] => {
+            f.write_str(", expected one of ")?;
+            for kind in kinds {
+                write!(f, "{}", kind)?;
+                f.write_str(if kinds.len() > 1 { ", " } else { " " })?;
+            }
+            write!(f, "or {}", last)
+        }
+    }
+}
+
+impl Display for ASTError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ASTError::UnexpectedToken(_, found, expected) => {
+                write!(f, "unexpected {}", found)?;
+                write_expected(f, expected)
+            }
+            ASTError::Incomplete(_, expected) => {
+                f.write_str("unexpected end of input")?;
+                write_expected(f, expected)
+            }
+            ASTError::TooDeep(_, limit) => {
+                write!(f, "nesting too deep, blocks and expressions can be nested at most {} levels", limit)
+            }
+            ASTError::ImportPath(_, found) => write!(f, "expected the file to import as a string, found {}", found),
+            ASTError::Lexer(err) => write!(f, "{:?}", err.kind),
+        }
+    }
 }
 
 type ASTResult<T> = Result<T, ASTError>;
 
 impl ASTSource {
+    // Type of a declaration, which can be a union
     fn parse_datatype(state: &mut ASTState) -> ASTResult<DataType> {
-        let base_name = expect_ident!(state);
-        let mut nullable = false;
-        if let Some(next_token) = state.next_token() {
-            if let Token::Symbol(symbol) = next_token {
-                if let Symbol::Question = symbol {
-                    nullable = true
-                }
-            }
-        }
-        if !nullable {
-            state.back_token();
+        let mut value = Self::parse_type_name(state)?;
+        while state.eat_symbol(Symbol::Pipe) {
+            value.union.push(Self::parse_type_name(state)?);
         }
+        Ok(value)
+    }
 
+    // A single type, `|` after `is` or in an alias is parsed by the caller
+    fn parse_type_name(state: &mut ASTState) -> ASTResult<DataType> {
+        let start = state.start();
+        let mut name = state.expect_ident()?;
+        // Types of a namespace import are written `other.Name`
+        if state.eat_symbol(Symbol::Period) {
+            name = qualified(name, state.expect_ident()?);
+        }
+        let nullable = state.eat_symbol(Symbol::Question);
         Ok(DataType {
-            name: base_name,
+            name,
             nullable,
+            span: Span::new(start, state.token_set.span().end),
+            union: Vec::new(),
         })
     }
 
-    fn parse_struct(state: &mut ASTState) -> ASTResult<()> {
-        let name = expect_ident!(state);
-        expect_symbol!(state, OpenCurly);
-        let mut closed = false;
-        let mut properties: Vec<StructProperty> = Vec::new();
-        while let Some(next_token) = state.next_token() {
-            match next_token {
-                Token::Ident(name) => {
-                    expect_symbol!(state, Colon);
-                    let type_of = Self::parse_datatype(state)?;
-                    properties.push(StructProperty {
-                        name,
-                        type_of,
-                    });
-                }
-                Token::Symbol(ref symbol) => {
-                    if let Symbol::CloseCurly = symbol {
-                        closed = true;
-                        break;
-                    } else {
-                        return Err(ASTError::UnexpectedToken(Some(next_token.clone())));
-                    }
-                }
-                token => return Err(ASTError::UnexpectedToken(Some(token)))
-            }
+    fn parse_modifiers(state: &mut ASTState) -> Vec<Modifier> {
+        let mut modifiers = Vec::new();
+        while let Some(Token::Keyword(KeywordType::Modifier(modifier))) = state.peek_token() {
+            state.next_token();
+            modifiers.push(modifier);
         }
-        if !closed {
-            return Err(ASTError::UnexpectedToken(None));
+        return modifiers;
+    }
+
+    fn parse_annotation_argument(state: &mut ASTState) -> ASTResult<AnnotationArgument> {
+        let start = state.start();
+        let mut name = None;
+        // Named arguments are an identifier followed by `=`
+        if let (Some(Token::Ident(ident)), Some(Token::Symbol(Symbol::Equals))) =
+            (state.token_set.peek(0), state.token_set.peek(1)) {
+            state.next_token();
+            state.next_token();
+            name = Some(ident);
         }
+        let value = match state.expect(TokenKind::Literal)? {
+            Token::Literal(literal) => Literal::from(&literal),
+            _ => unreachable!(),
+        };
+        Ok(AnnotationArgument {
+            name,
+            value,
+            span: Span::new(start, state.token_set.span().end),
+        })
+    }
 
-        state.push_ast(AST::Struct(SpewStruct {
+    fn parse_annotations(state: &mut ASTState) -> ASTResult<Vec<Annotation>> {
+        let mut annotations = Vec::new();
+        while state.check(TokenKind::Symbol(Symbol::At)) {
+            let start = state.start();
+            state.next_token();
+            let name = state.expect_ident()?;
+            let arguments = if state.eat_symbol(Symbol::OpenParen) {
+                state.separated(Symbol::Comma, Symbol::CloseParen, Self::parse_annotation_argument)?
+            } else {
+                Vec::new()
+            };
+            annotations.push(Annotation {
+                name,
+                arguments,
+                span: Span::new(start, state.token_set.span().end),
+            });
+        }
+        Ok(annotations)
+    }
+
+    fn parse_struct(
+        state: &mut ASTState,
+        start: usize,
+        annotations: Vec<Annotation>,
+        public: bool,
+    ) -> ASTResult<NodeId<SpewStruct>> {
+        let name = state.expect_ident()?;
+        let properties = state.delimited(Symbol::OpenCurly, Symbol::CloseCurly, |state| {
+            let start = state.start();
+            let annotations = Self::parse_annotations(state)?;
+            let public = state.eat(TokenKind::Keyword(KeywordType::Modifier(Modifier::Public))).is_some();
+            let name = state.expect_ident()?;
+            let optional = state.eat_symbol(Symbol::Question);
+            state.expect_symbol(Symbol::Colon)?;
+            let type_of = Self::parse_datatype(state)?;
+            let property = StructProperty {
+                annotations,
+                public,
+                name,
+                optional,
+                type_of,
+            };
+            Ok(state.alloc(property, start))
+        })?;
+        let value = SpewStruct {
+            annotations,
+            public,
             name,
             properties,
-        }));
-        Ok(())
+        };
+        Ok(state.alloc(value, start))
     }
 
-    fn parse_function_stub(state: &mut ASTState, modifiers: Vec<Modifier>) -> ASTResult<FunctionStub> {
-        let name = expect_ident!(state);
-        expect_symbol!(state, OpenParen);
-        let mut closed = false;
-        let mut arguments: Vec<FunctionArgument> = Vec::new();
-        if let Some(next_token) = state.next_token() {
-            match next_token {
-                Token::Ident(argument_name) => {
-                    expect_symbol!(state, Colon);
-                    let data_type = Self::parse_datatype(state)?;
-                    arguments.push(FunctionArgument {
-                        name: argument_name,
-                        data_type,
-                    })
-                }
-                Token::Symbol(symbol) => {
-                    if let Symbol::CloseParen = symbol {
-                        closed = true
-                    }
-                }
-                token => return Err(ASTError::UnexpectedToken(Some(token)))
-            }
-        }
+    fn parse_function_stub(
+        state: &mut ASTState,
+        annotations: Vec<Annotation>,
+        modifiers: Vec<Modifier>,
+    ) -> ASTResult<FunctionStub> {
+        let name = state.expect_ident()?;
+        state.expect_symbol(Symbol::OpenParen)?;
+        let arguments = Self::parse_arguments(state, false)?;
+        let return_type = Self::parse_return_type(state)?;
+        Ok(FunctionStub {
+            annotations,
+            name,
+            modifiers,
+            arguments,
+            return_type,
+        })
+    }
 
-        let return_type =
-            if is_symbol_next!(state, Minus) {
-                expect_symbol!(state, Right); // Parsing for the return type arrow
+    // Arguments after the opening paren, `inferred` allows leaving out types
+    fn parse_arguments(state: &mut ASTState, inferred: bool) -> ASTResult<Vec<NodeId<FunctionArgument>>> {
+        return state.separated(Symbol::Comma, Symbol::CloseParen, |state| {
+            let start = state.start();
+            let name = state.expect_ident()?;
+            let data_type = if !inferred || state.check(TokenKind::Symbol(Symbol::Colon)) {
+                state.expect_symbol(Symbol::Colon)?;
                 Some(Self::parse_datatype(state)?)
             } else {
                 None
             };
+            Ok(state.alloc(FunctionArgument { name, data_type }, start))
+        });
+    }
 
+    fn parse_return_type(state: &mut ASTState) -> ASTResult<Option<DataType>> {
+        if state.eat_symbol(Symbol::Arrow) {
+            return Ok(Some(Self::parse_datatype(state)?));
+        }
+        return Ok(None);
+    }
 
-        Ok(FunctionStub {
-            name,
-            modifiers,
+    // `fun (a, b) { ... }` after the `fun` keyword
+    fn parse_lambda(state: &mut ASTState, start: usize) -> ASTResult<NodeId> {
+        state.expect_symbol(Symbol::OpenParen)?;
+        let arguments = Self::parse_arguments(state, true)?;
+        let return_type = Self::parse_return_type(state)?;
+        let body = Self::parse_block(state)?;
+        let value = Lambda {
             arguments,
             return_type,
-        })
+            body,
+        };
+        Ok(state.alloc(value, start).untyped())
+    }
+
+    fn parse_function(
+        state: &mut ASTState,
+        start: usize,
+        annotations: Vec<Annotation>,
+        modifiers: Vec<Modifier>,
+    ) -> ASTResult<NodeId<SpewFunction>> {
+        let stub = Self::parse_function_stub(state, annotations, modifiers)?;
+        let body = Self::parse_block(state)?;
+        Ok(state.alloc(SpewFunction { stub, body }, start))
     }
 
-    fn parse_trait(state: &mut ASTState) -> ASTResult<()> {
-        let name = expect_ident!(state);
-        expect_symbol!(state, OpenCurly);
+    fn parse_trait(
+        state: &mut ASTState,
+        start: usize,
+        annotations: Vec<Annotation>,
+        public: bool,
+    ) -> ASTResult<NodeId<SpewTrait>> {
+        let name = state.expect_ident()?;
+        let mut supertraits = Vec::new();
+        if state.eat_symbol(Symbol::Colon) {
+            supertraits.push(state.expect_ident()?);
+            while state.eat_symbol(Symbol::Plus) {
+                supertraits.push(state.expect_ident()?);
+            }
+        }
+        let functions = state.delimited(Symbol::OpenCurly, Symbol::CloseCurly, |state| {
+            let start = state.start();
+            let annotations = Self::parse_annotations(state)?;
+            let modifiers = Self::parse_modifiers(state);
+            state.expect(TokenKind::Keyword(KeywordType::Function))?;
+            let stub = Self::parse_function_stub(state, annotations, modifiers)?;
+            Ok(state.alloc(stub, start))
+        })?;
+        let value = SpewTrait {
+            annotations,
+            public,
+            name,
+            supertraits,
+            functions,
+        };
+        Ok(state.alloc(value, start))
+    }
 
+    fn parse_type_alias(state: &mut ASTState, start: usize, public: bool) -> ASTResult<NodeId<TypeAlias>> {
+        let name = state.expect_ident()?;
+        state.expect_symbol(Symbol::Equals)?;
+        let mut types = vec![Self::parse_type_name(state)?];
+        // Both operators can't be mixed so the first one decides
+        let union = state.check(TokenKind::Symbol(Symbol::Pipe));
+        let separator = if union { Symbol::Pipe } else { Symbol::And };
+        while state.eat_symbol(separator) {
+            types.push(Self::parse_type_name(state)?);
+        }
+        let value = TypeAlias {
+            public,
+            name,
+            types,
+            union,
+        };
+        Ok(state.alloc(value, start))
+    }
 
-        Ok(())
+    // import * from "other", import * as other from "other" or
+    // import { a, B as C } from "other" with the keyword consumed
+    fn parse_import(state: &mut ASTState, start: usize) -> ASTResult<NodeId<Import>> {
+        let names = if state.eat_symbol(Symbol::Multiply) {
+            match state.eat(TokenKind::Keyword(KeywordType::As)) {
+                Some(_) => ImportNames::Namespace(state.expect_ident()?),
+                None => ImportNames::All,
+            }
+        } else {
+            state.expect_symbol(Symbol::OpenCurly)?;
+            let names = state.separated(Symbol::Comma, Symbol::CloseCurly, |state| {
+                let start = state.start();
+                let name = state.expect_ident()?;
+                let alias = match state.eat(TokenKind::Keyword(KeywordType::As)) {
+                    Some(_) => Some(state.expect_ident()?),
+                    None => None,
+                };
+                Ok(ImportName {
+                    name,
+                    alias,
+                    span: Span::new(start, state.token_set.span().end),
+                })
+            })?;
+            ImportNames::Listed(names)
+        };
+        state.expect(TokenKind::Keyword(KeywordType::From))?;
+        let value = match state.expect(TokenKind::Literal)? {
+            Token::Literal(literal) => Literal::from(&literal),
+            _ => unreachable!(),
+        };
+        // Only a string can name a file
+        let Literal::String(path) = value else {
+            return Err(ASTError::ImportPath(state.token_set.span(), value.to_string()));
+        };
+        Ok(state.alloc(Import { names, path }, start))
     }
 
-    pub fn parse_ast(value: TokenSet) -> ASTResult<Vec<AST>> {
-        let mut token_set = value;
-        let mut under_state = ASTState {
-            result: Vec::new(),
-            token_set,
+    fn parse_impl(state: &mut ASTState, start: usize) -> ASTResult<NodeId<SpewImpl>> {
+        let mut name = state.expect_ident()?;
+        let mut trait_name = None;
+        if state.eat(TokenKind::Keyword(KeywordType::For)).is_some() {
+            // impl Trait for Type
+            trait_name = Some(name);
+            name = state.expect_ident()?;
+        }
+        let items = state.delimited(Symbol::OpenCurly, Symbol::CloseCurly, |state| {
+            let start = state.start();
+            let annotations = Self::parse_annotations(state)?;
+            let modifiers = Self::parse_modifiers(state);
+            if annotations.is_empty() && state.check(TokenKind::Keyword(KeywordType::Constant)) {
+                return Self::parse_variable(state, start, modifiers);
+            }
+            state.expect(TokenKind::Keyword(KeywordType::Function))?;
+            Ok(Self::parse_function(state, start, annotations, modifiers)?.untyped())
+        })?;
+        let (functions, constants): (Vec<_>, Vec<_>) = items.into_iter()
+            .partition(|item| state.source.cast::<SpewFunction>(*item).is_some());
+        let value = SpewImpl {
+            trait_name,
+            name,
+            constants,
+            functions: functions.into_iter().map(|item| state.source.cast(item).unwrap()).collect(),
         };
-        let mut state = &mut under_state;
-        while let Some(token) = state.next_token() {
-            match token {
-                Token::Comment(_) => {
-                    // Comments are ignored
-                }
-                Token::Keyword(keyword) => {
-                    match keyword {
-                        KeywordType::Constant => {}
-                        KeywordType::Let => {}
-                        KeywordType::Function => {}
-                        KeywordType::Unknown => {}
-                        KeywordType::Static => {}
-                        KeywordType::Struct => {
-                            Self::parse_struct(state)?;
-                        }
-                        KeywordType::Trait => {}
-                        KeywordType::Impl => {}
-                        KeywordType::For => {}
-                        KeywordType::Modifier(_) => {}
+        Ok(state.alloc(value, start))
+    }
+
+    // let and const declarations, the keyword hasn't been consumed yet
+    fn parse_variable(state: &mut ASTState, start: usize, mut modifiers: Vec<Modifier>) -> ASTResult<NodeId> {
+        let keyword = state.one_of(&[
+            TokenKind::Keyword(KeywordType::Let),
+            TokenKind::Keyword(KeywordType::Constant),
+        ])?;
+        let constant = keyword.is(TokenKind::Keyword(KeywordType::Constant));
+        modifiers.extend(Self::parse_modifiers(state));
+        let name = state.expect_ident()?;
+        let type_of = if state.eat_symbol(Symbol::Colon) {
+            Some(Self::parse_datatype(state)?)
+        } else {
+            None
+        };
+        // Constants must always be initialized
+        let value = if constant || state.check(TokenKind::Symbol(Symbol::Equals)) {
+            state.expect_symbol(Symbol::Equals)?;
+            Some(Self::parse_expression(state)?)
+        } else {
+            None
+        };
+        let variable = AST::Variable {
+            variable: Variable {
+                constant,
+                name,
+                modifiers,
+                type_of,
+            },
+            value,
+        };
+        Ok(state.alloc(variable, start))
+    }
+
+    fn parse_block(state: &mut ASTState) -> ASTResult<Vec<NodeId>> {
+        return state.nested(|state| {
+            state.delimited(Symbol::OpenCurly, Symbol::CloseCurly, Self::parse_statement)
+        });
+    }
+
+    fn parse_statement(state: &mut ASTState) -> ASTResult<NodeId> {
+        let start = state.start();
+        let value = if state.check(TokenKind::Keyword(KeywordType::Let))
+            || state.check(TokenKind::Keyword(KeywordType::Constant)) {
+            Self::parse_variable(state, start, Vec::new())?
+        } else if let Some(Token::Keyword(KeywordType::Modifier(_))) = state.peek_token() {
+            // compile const, modifiers locals can't have are reported later
+            let modifiers = Self::parse_modifiers(state);
+            Self::parse_variable(state, start, modifiers)?
+        } else if state.eat(TokenKind::Keyword(KeywordType::Return)).is_some() {
+            // Semicolons are optional so a value on the next line is the
+            // next statement instead
+            let value = match state.peek_token() {
+                None | Some(Token::Symbol(Symbol::CloseCurly)) | Some(Token::Symbol(Symbol::Semicolon)) => None,
+                Some(_) if state.token_set.line_break() => None,
+                Some(_) => Some(Self::parse_expression(state)?),
+            };
+            state.alloc(AST::Return(value), start)
+        } else if state.check(TokenKind::Keyword(KeywordType::If)) {
+            Self::parse_condition(state)?
+        } else if state.eat(TokenKind::Keyword(KeywordType::While)).is_some() {
+            let condition = Self::parse_expression(state)?;
+            let contents = Self::parse_block(state)?;
+            state.alloc(AST::While { condition, contents }, start)
+        } else if state.eat(TokenKind::Keyword(KeywordType::Break)).is_some() {
+            state.alloc(AST::Break, start)
+        } else if state.check(TokenKind::Symbol(Symbol::OpenCurly)) {
+            let contents = Self::parse_block(state)?;
+            state.alloc(AST::Block(contents), start)
+        } else {
+            Self::parse_expression(state)?
+        };
+        // Semicolons are optional statement terminators
+        state.eat_symbol(Symbol::Semicolon);
+        Ok(value)
+    }
+
+    fn parse_condition(state: &mut ASTState) -> ASTResult<NodeId> {
+        let start = state.start();
+        state.expect(TokenKind::Keyword(KeywordType::If))?;
+        let condition = Self::parse_expression(state)?;
+        let contents = Self::parse_block(state)?;
+        let otherwise = if state.eat(TokenKind::Keyword(KeywordType::Else)).is_some() {
+            if state.check(TokenKind::Keyword(KeywordType::If)) {
+                Some(state.nested(Self::parse_condition)?)
+            } else {
+                let start = state.start();
+                let contents = Self::parse_block(state)?;
+                Some(state.alloc(AST::Block(contents), start))
+            }
+        } else {
+            None
+        };
+        let value = AST::ConditionBlock {
+            condition,
+            contents,
+            otherwise,
+        };
+        Ok(state.alloc(value, start))
+    }
+
+    // Pops the top operator and its two operands off the stacks replacing
+    // them with the combined operation
+    fn reduce(state: &mut ASTState, operands: &mut Vec<NodeId>, operators: &mut Vec<Operator>) {
+        let operator = operators.pop().unwrap();
+        let right = operands.pop().unwrap();
+        let left = operands.pop().unwrap();
+        let span = Span::new(state.source.span(left).start, state.source.span(right).end);
+        let operation = AST::Operation(Operation {
+            left,
+            operator,
+            right: Some(right),
+        });
+        operands.push(state.alloc_span(operation, span));
+    }
+
+    /// Binary expressions are parsed with an explicit operator stack rather
+    /// than recursion so long chains like `a + b + c + ...` use no extra stack
+    /// and don't count towards the nesting limit
+    fn parse_expression(state: &mut ASTState) -> ASTResult<NodeId> {
+        return state.nested(|state| {
+            let start = state.start();
+            let mut operands = vec![Self::parse_unary(state)?];
+            let mut operators: Vec<Operator> = Vec::new();
+            while let Some(Token::Symbol(symbol)) = state.peek_token() {
+                let operator = match Operator::from_binary(symbol) {
+                    Some(operator) => operator,
+                    None => break,
+                };
+                state.next_token();
+                // Operators are left associative so equal precedence reduces
+                while let Some(top) = operators.last() {
+                    if top.precedence() < operator.precedence() {
+                        break;
                     }
+                    Self::reduce(state, &mut operands, &mut operators);
+                }
+                operators.push(operator);
+                operands.push(Self::parse_unary(state)?);
+            }
+            while !operators.is_empty() {
+                Self::reduce(state, &mut operands, &mut operators);
+            }
+            let target = operands.pop().unwrap();
+            // Assignments bind loosest and group to the right
+            if state.eat_symbol(Symbol::Equals) {
+                let value = Self::parse_expression(state)?;
+                return Ok(state.alloc(AST::Assign { target, value }, start));
+            }
+            Ok(target)
+        });
+    }
+
+    fn parse_unary(state: &mut ASTState) -> ASTResult<NodeId> {
+        // Operators paired with where they start
+        let mut operators = Vec::new();
+        while let Some(Token::Symbol(symbol)) = state.peek_token() {
+            match Operator::from_unary(symbol) {
+                Some(operator) => operators.push((operator, state.start())),
+                None => break,
+            }
+            state.next_token();
+            state.layered(operators.len())?;
+        }
+        let mut value = Self::parse_postfix(state)?;
+        while let Some((operator, start)) = operators.pop() {
+            let operation = AST::Operation(Operation {
+                left: value,
+                operator,
+                right: None,
+            });
+            value = state.alloc(operation, start);
+        }
+        Ok(value)
+    }
+
+    fn parse_postfix(state: &mut ASTState) -> ASTResult<NodeId> {
+        let start = state.start();
+        let mut value = Self::parse_primary(state)?;
+        let mut layers = 0;
+        loop {
+            layers += 1;
+            if state.eat_symbol(Symbol::OpenParen) {
+                state.layered(layers)?;
+                let arguments = state.separated(Symbol::Comma, Symbol::CloseParen, Self::parse_expression)?;
+                value = state.alloc(AST::Call { target: value, arguments }, start);
+            } else if state.eat_symbol(Symbol::Period) {
+                state.layered(layers)?;
+                let name = state.expect_ident()?;
+                value = state.alloc(AST::Member { target: value, name }, start);
+            } else if state.check(TokenKind::Keyword(KeywordType::Is)) {
+                state.next_token();
+                state.layered(layers)?;
+                let data_type = Self::parse_type_name(state)?;
+                value = state.alloc(AST::Is { value, data_type }, start);
+            } else {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    // match value { pattern => body, ... } with the keyword consumed
+    fn parse_match(state: &mut ASTState, start: usize) -> ASTResult<NodeId> {
+        let value = Self::parse_expression(state)?;
+        state.expect_symbol(Symbol::OpenCurly)?;
+        let arms = state.separated(Symbol::Comma, Symbol::CloseCurly, |state| {
+            let start = state.start();
+            let token = state.one_of(&[
+                TokenKind::Literal,
+                TokenKind::Ident,
+                TokenKind::Symbol(Symbol::Underscore),
+                TokenKind::Keyword(KeywordType::Is),
+            ])?;
+            let pattern = match token {
+                Token::Literal(literal) => Pattern::Literal(Literal::from(&literal)),
+                Token::Ident(name) => Pattern::Binding(name),
+                Token::Keyword(KeywordType::Is) => Pattern::Type(Self::parse_type_name(state)?),
+                _ => Pattern::Wildcard,
+            };
+            state.expect_symbol(Symbol::FatArrow)?;
+            let body = if state.check(TokenKind::Symbol(Symbol::OpenCurly)) {
+                let start = state.start();
+                let contents = Self::parse_block(state)?;
+                state.alloc(AST::Block(contents), start)
+            } else {
+                Self::parse_expression(state)?
+            };
+            Ok(state.alloc(MatchArm { pattern, body }, start))
+        })?;
+        Ok(state.alloc(AST::Match { value, arms }, start))
+    }
+
+    fn parse_primary(state: &mut ASTState) -> ASTResult<NodeId> {
+        let start = state.start();
+        let token = state.one_of(&[
+            TokenKind::Literal,
+            TokenKind::Ident,
+            TokenKind::Symbol(Symbol::OpenParen),
+            TokenKind::Keyword(KeywordType::Match),
+            TokenKind::Keyword(KeywordType::Function),
+        ])?;
+        return match token {
+            Token::Literal(literal) => Ok(state.alloc(AST::Literal(Literal::from(&literal)), start)),
+            Token::Ident(mut name) => {
+                // `other.Name::member` goes through a type of a namespace
+                let period = matches!(state.token_set.peek(0), Some(Token::Symbol(Symbol::Period)));
+                let path = matches!(state.token_set.peek(2), Some(Token::Symbol(Symbol::ColonColon)));
+                if let (true, Some(Token::Ident(member)), true) = (period, state.token_set.peek(1), path) {
+                    state.next_token();
+                    state.next_token();
+                    name = qualified(name, member);
                 }
-                Token::Ident(ident) => {}
-                Token::Symbol(_) => {}
-                Token::Literal(_) => {}
+                let span = Span::new(start, state.token_set.span().end);
+                if !state.eat_symbol(Symbol::ColonColon) {
+                    return Ok(state.alloc(AST::Identifier(name), start));
+                }
+                let data_type = DataType {
+                    name,
+                    nullable: false,
+                    span,
+                    union: Vec::new(),
+                };
+                let name = state.expect_ident()?;
+                Ok(state.alloc(AST::Path { data_type, name }, start))
+            }
+            Token::Keyword(KeywordType::Match) => state.nested(|state| Self::parse_match(state, start)),
+            Token::Keyword(KeywordType::Function) => Self::parse_lambda(state, start),
+            _ => {
+                let value = Self::parse_expression(state)?;
+                state.expect_symbol(Symbol::CloseParen)?;
+                Ok(value)
             }
+        };
+    }
+
+    fn parse_item(state: &mut ASTState) -> ASTResult<NodeId> {
+        // Item spans cover their annotations and modifiers
+        let start = state.start();
+        let annotations = Self::parse_annotations(state)?;
+        let modifiers = Self::parse_modifiers(state);
+        if !modifiers.is_empty() {
+            // Only functions and variables take modifiers, structs, traits
+            // and aliases can only be `pub`
+            return if state.check(TokenKind::Keyword(KeywordType::Function)) {
+                state.next_token();
+                Ok(Self::parse_function(state, start, annotations, modifiers)?.untyped())
+            } else if annotations.is_empty() && (state.check(TokenKind::Keyword(KeywordType::Let))
+                || state.check(TokenKind::Keyword(KeywordType::Constant))) {
+                Self::parse_variable(state, start, modifiers)
+            } else if modifiers == [Modifier::Public] && state.check(TokenKind::Keyword(KeywordType::Struct)) {
+                state.next_token();
+                Ok(Self::parse_struct(state, start, annotations, true)?.untyped())
+            } else if modifiers == [Modifier::Public] && state.check(TokenKind::Keyword(KeywordType::Trait)) {
+                state.next_token();
+                Ok(Self::parse_trait(state, start, annotations, true)?.untyped())
+            } else if modifiers == [Modifier::Public] && annotations.is_empty()
+                && state.check(TokenKind::Keyword(KeywordType::Type)) {
+                state.next_token();
+                Ok(Self::parse_type_alias(state, start, true)?.untyped())
+            } else {
+                Err(state.unexpected())
+            };
+        }
+
+        if !annotations.is_empty() {
+            // Annotations can only be placed on structs, traits and functions
+            let token = state.one_of(&[
+                TokenKind::Keyword(KeywordType::Struct),
+                TokenKind::Keyword(KeywordType::Trait),
+                TokenKind::Keyword(KeywordType::Function),
+            ])?;
+            return match token {
+                Token::Keyword(KeywordType::Struct) => Ok(Self::parse_struct(state, start, annotations, false)?.untyped()),
+                Token::Keyword(KeywordType::Trait) => Ok(Self::parse_trait(state, start, annotations, false)?.untyped()),
+                _ => Ok(Self::parse_function(state, start, annotations, modifiers)?.untyped()),
+            };
+        }
+
+        if state.check(TokenKind::Keyword(KeywordType::Let))
+            || state.check(TokenKind::Keyword(KeywordType::Constant)) {
+            return Self::parse_variable(state, start, modifiers);
+        }
+        let token = state.one_of(&[
+            TokenKind::Keyword(KeywordType::Struct),
+            TokenKind::Keyword(KeywordType::Trait),
+            TokenKind::Keyword(KeywordType::Impl),
+            TokenKind::Keyword(KeywordType::Function),
+            TokenKind::Keyword(KeywordType::Type),
+            TokenKind::Keyword(KeywordType::Import),
+        ])?;
+        return match token {
+            Token::Keyword(KeywordType::Struct) => Ok(Self::parse_struct(state, start, annotations, false)?.untyped()),
+            Token::Keyword(KeywordType::Trait) => Ok(Self::parse_trait(state, start, annotations, false)?.untyped()),
+            Token::Keyword(KeywordType::Impl) => Ok(Self::parse_impl(state, start)?.untyped()),
+            Token::Keyword(KeywordType::Type) => Ok(Self::parse_type_alias(state, start, false)?.untyped()),
+            Token::Keyword(KeywordType::Import) => Ok(Self::parse_import(state, start)?.untyped()),
+            _ => Ok(Self::parse_function(state, start, annotations, modifiers)?.untyped()),
+        };
+    }
+
+    fn parse_items(state: &mut ASTState) -> ASTResult<()> {
+        while state.peek_token().is_some() {
+            let item = Self::parse_item(state)?;
+            state.source.push_root(item);
+        }
+        Ok(())
+    }
+
+    pub fn parse_ast(value: TokenSet) -> ASTResult<ASTSource> {
+        return Self::parse_ast_with(value, ParseOptions::default());
+    }
+
+    pub fn parse_ast_with(value: TokenSet, options: ParseOptions) -> ASTResult<ASTSource> {
+        let mut source = ASTSource::new();
+        source.parse_into(String::new(), value, options)?;
+        Ok(source)
+    }
+
+    /// Parses another file into the arena after those already in it,
+    /// returning its index
+    pub fn parse_file(&mut self, path: &str, text: &str, options: ParseOptions) -> ASTResult<usize> {
+        let offset = self.end;
+        let parsed = self.parse_into(path.to_string(), TokenSet::with_offset(text, offset), options);
+        // Files are kept apart so an empty span at the end of one isn't
+        // also at the start of the next, errors may be past the last token
+        self.end = offset + text.len() + 1;
+        parsed
+    }
+
+    fn parse_into(&mut self, path: String, value: TokenSet, options: ParseOptions) -> ASTResult<usize> {
+        // Nothing has been read yet so this is where the spans start
+        let offset = value.span().start;
+        self.files.push(SourceFile {
+            path,
+            offset,
+            first_root: self.roots.len(),
+        });
+        let mut state = ASTState {
+            source: std::mem::take(self),
+            token_set: value,
+            expected: Vec::new(),
+            depth: 0,
+            max_depth: options.max_depth,
+        };
+        let parsed = Self::parse_items(&mut state);
+        *self = state.source;
+        // Anything parsed into the arena later starts after this
+        self.end = state.token_set.span().end + 1;
+        // A lexer error cuts the token stream short so it takes priority
+        // over whatever the parser made of the truncated input
+        if let Some(err) = state.token_set.error() {
+            return Err(ASTError::Lexer(err.clone()));
         }
-        let result = under_state.result;
-        Ok(result)
+        parsed?;
+        Ok(self.files.len() - 1)
     }
 }
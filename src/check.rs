@@ -0,0 +1,1571 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Display;
+
+use crate::ast::{
+    ASTSource, DataType, FunctionStub, Lambda, Literal, MatchArm, NodeId, NodeMap, Operation, Operator, Pattern,
+    SpewFunction, SpewTrait, StructProperty, TypeAlias, AST,
+};
+use crate::intern::Name;
+use crate::resolve::{is_private, is_static, Declaration, Resolution};
+use crate::source::Span;
+use crate::types::Type;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+    // Expected type followed by the type that was found
+    Mismatch(Span, Type, Type),
+    // Like a mismatch but the found type only differs by null or ndef
+    MaybeMissing(Span, Type, Type),
+    // Operand types, the second is None for unary operators
+    Operator(Span, Operator, Type, Option<Type>),
+    // Expected number of arguments followed by the number given
+    ArgumentCount(Span, usize, usize),
+    NotCallable(Span, Type),
+    NoMember(Span, Type, Name),
+    // Member accessed on a value that may be null or ndef
+    MemberOfMissing(Span, Type, Name),
+    // Field or function of an impl that isn't `pub` used outside of its file
+    Private(Span, Type, Name),
+    // Match pattern that the value can never be equal to
+    Pattern(Span, Type, Type),
+    NoValue(Span),
+    // `void` used as the type of a value
+    InvalidType(Span),
+    // Value whose type couldn't be inferred before it was needed
+    Unknown(Span),
+    // Part of an intersection that isn't a struct or trait
+    Intersect(Span, Type),
+    // Property followed by the types two parts of an intersection give it
+    Conflict(Span, Name, Type, Type),
+    // Alias that refers back to itself
+    Recursive(Span, Name),
+    // `is` check that can never hold, the value type followed by the tested one
+    Impossible(Span, Type, Type),
+}
+
+impl TypeError {
+    pub fn span(&self) -> Span {
+        return match self {
+            TypeError::Mismatch(span, _, _) => *span,
+            TypeError::MaybeMissing(span, _, _) => *span,
+            TypeError::Operator(span, _, _, _) => *span,
+            TypeError::ArgumentCount(span, _, _) => *span,
+            TypeError::NotCallable(span, _) => *span,
+            TypeError::NoMember(span, _, _) => *span,
+            TypeError::MemberOfMissing(span, _, _) => *span,
+            TypeError::Private(span, _, _) => *span,
+            TypeError::Pattern(span, _, _) => *span,
+            TypeError::NoValue(span) => *span,
+            TypeError::InvalidType(span) => *span,
+            TypeError::Unknown(span) => *span,
+            TypeError::Intersect(span, _) => *span,
+            TypeError::Conflict(span, _, _, _) => *span,
+            TypeError::Recursive(span, _) => *span,
+            TypeError::Impossible(span, _, _) => *span,
+        };
+    }
+}
+
+// How a value that may be missing can be ruled out
+fn missing(value: &Type) -> &'static str {
+    return if value.includes_null() {
+        "may be null, check it with `!= null` or give a default with `??`"
+    } else {
+        "may be ndef, check it with `!= ndef` or give a default with `??`"
+    };
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeError::Mismatch(_, expected, found) => write!(f, "expected `{}`, found `{}`", expected, found),
+            TypeError::MaybeMissing(_, expected, found) => {
+                write!(f, "expected `{}`, found `{}` which {}", expected, found, missing(found))
+            }
+            TypeError::Operator(_, operator, value, None) => {
+                write!(f, "cannot apply `{}` to `{}`", operator.as_str(), value)
+            }
+            TypeError::Operator(_, operator, left, Some(right)) => {
+                write!(f, "cannot apply `{}` to `{}` and `{}`", operator.as_str(), left, right)
+            }
+            TypeError::ArgumentCount(_, expected, found) => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                write!(f, "expected {} argument{}, found {}", expected, plural, found)
+            }
+            TypeError::NotCallable(_, value) => write!(f, "`{}` cannot be called", value),
+            TypeError::NoMember(_, value, name) => write!(f, "`{}` has no member `{}`", value, name),
+            TypeError::Private(_, value, name) => {
+                write!(f, "`{}` of `{}` isn't `pub` so it can only be used in its own file", name, value)
+            }
+            TypeError::MemberOfMissing(_, value, name) => {
+                write!(f, "cannot access `{}` on `{}` which {}", name, value, missing(value))
+            }
+            TypeError::Pattern(_, value, pattern) => {
+                write!(f, "a `{}` can never match a `{}` pattern", value, pattern)
+            }
+            TypeError::NoValue(_) => write!(f, "expression has no value"),
+            TypeError::InvalidType(_) => write!(f, "`void` can only be used as a return type"),
+            TypeError::Unknown(_) => write!(f, "type must be known at this point, add a type annotation"),
+            TypeError::Intersect(_, value) => write!(f, "only structs and traits can be intersected, found `{}`", value),
+            TypeError::Conflict(_, name, left, right) => {
+                write!(f, "`{}` is `{}` in one part of the intersection and `{}` in another", name, left, right)
+            }
+            TypeError::Recursive(_, name) => write!(f, "`{}` is defined in terms of itself", name),
+            TypeError::Impossible(_, value, tested) => write!(f, "a `{}` can never be a `{}`", value, tested),
+        }
+    }
+}
+
+/// Result of checking a tree
+#[derive(Debug, Clone, Default)]
+pub struct TypeCheck {
+    /// Type of every expression and of the binding of every variable,
+    /// argument and match arm binding. Expressions have their type after
+    /// narrowing.
+    pub types: NodeMap<Type>,
+    pub errors: Vec<TypeError>,
+}
+
+impl TypeCheck {
+    pub fn type_of(&self, id: NodeId) -> Option<&Type> {
+        return self.types.get(id);
+    }
+
+    /// Innermost expression or binding with a type at a byte offset, for
+    /// showing inferred types in editors
+    pub fn hover(&self, source: &ASTSource, offset: usize) -> Option<(NodeId, &Type)> {
+        return self.types.iter()
+            .filter(|(id, _)| {
+                let span = source.span(*id);
+                span.start <= offset && offset < span.end
+            })
+            .min_by_key(|(id, _)| source.span(*id).end - source.span(*id).start);
+    }
+}
+
+/// Variable, argument or `this` followed by the properties read from it,
+/// the things whose type can be narrowed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Place {
+    root: Declaration,
+    members: Vec<Name>,
+}
+
+// Struct and trait parts of an intersection alias and the properties of its
+// structs merged by name
+#[derive(Debug, Clone, Default)]
+struct Intersection {
+    parts: Vec<Type>,
+    properties: Vec<(Name, Type)>,
+}
+
+struct Checker<'a> {
+    source: &'a ASTSource,
+    resolution: &'a Resolution,
+    // Functions of every impl by the type they are implemented for
+    methods: HashMap<Declaration, Vec<NodeId<SpewFunction>>>,
+    // Traits implemented by each type
+    implements: HashSet<(NodeId<SpewTrait>, Declaration)>,
+    // Type every alias stands for
+    aliases: NodeMap<Type>,
+    intersections: NodeMap<Intersection>,
+    // Return type of the function being checked
+    return_type: Type,
+    // Whether a value was returned from the function being checked so far
+    returns_value: bool,
+    // Types places have been narrowed to at the current point of the function
+    narrowed: HashMap<Place, Type>,
+    // What each `Type::Var` has been inferred to be so far
+    variables: Vec<Option<Type>>,
+    // Variables in the types of lambdas bound by `let` that are inferred
+    // separately at every use
+    generalized: NodeMap<Vec<usize>>,
+    check: TypeCheck,
+}
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, err: TypeError) {
+        self.check.errors.push(err);
+    }
+
+    // Type to be inferred from how a value is used
+    fn fresh(&mut self) -> Type {
+        self.variables.push(None);
+        return Type::Var(self.variables.len() - 1);
+    }
+
+    /// The type with every variable replaced by what it was inferred to be,
+    /// those that are still unknown are replaced by `unknown` when given
+    fn resolved(&self, value: &Type, unknown: Option<&Type>) -> Type {
+        return match value {
+            Type::Var(index) => match &self.variables[*index] {
+                Some(inferred) => self.resolved(inferred, unknown),
+                None => unknown.cloned().unwrap_or(Type::Var(*index)),
+            },
+            Type::Function(signature) => Type::function(
+                signature.arguments.iter().map(|argument| self.resolved(argument, unknown)).collect(),
+                self.resolved(&signature.return_type, unknown),
+            ),
+            Type::Union(members) => Type::union(members.iter().map(|member| self.resolved(member, unknown)).collect()),
+            Type::Nullable(value) => self.resolved(value, unknown).nullable(),
+            Type::Optional(value) => self.resolved(value, unknown).optional(),
+            value => value.clone(),
+        };
+    }
+
+    // Infers a variable to be `value`, fails if it would have to contain itself
+    fn bind(&mut self, index: usize, value: &Type) -> bool {
+        if occurs(index, value) {
+            return false;
+        }
+        self.variables[index] = Some(value.clone());
+        return true;
+    }
+
+    // Copy of a generalized type with fresh variables for each use
+    fn instantiate(&mut self, value: &Type, generic: &[usize]) -> Type {
+        let fresh: HashMap<_, _> = generic.iter().map(|index| (*index, self.fresh())).collect();
+        return substitute(&self.resolved(value, None), &fresh);
+    }
+
+    // Variables of a lambda bound to `id` that nothing outside of it refers to,
+    // `start` is the first variable created while checking it
+    fn generalize(&mut self, id: NodeId, value: &Type, start: usize) {
+        let mut outer = Vec::new();
+        for index in 0..start {
+            free(&self.resolved(&Type::Var(index), None), &mut outer);
+        }
+        let mut generic = Vec::new();
+        free(&self.resolved(value, None), &mut generic);
+        generic.retain(|index| *index >= start && !outer.contains(index));
+        if !generic.is_empty() {
+            self.generalized.insert(id, generic);
+        }
+    }
+
+    // Infers `value` to be `inferred` if its type is still unknown
+    fn infer(&mut self, value: &Type, inferred: Type) {
+        if let Type::Var(index) = self.resolved(value, None) {
+            self.bind(index, &inferred);
+        }
+    }
+
+    // Type one member of a `DataType` written in a node names, `index`
+    // counts the members and types written in the node before it
+    fn member_type(&self, id: NodeId, index: usize, value: &DataType) -> Type {
+        let base = match self.resolution.type_at(id, index) {
+            Some(declaration) => self.declared_type(declaration),
+            None => Type::Any,
+        };
+        return if value.nullable { base.nullable() } else { base };
+    }
+
+    /// Type the `DataType` written in a node refers to, unresolved names were
+    /// already reported so they are treated as `any` and so is `void` in a union
+    fn return_type(&self, id: NodeId, value: &DataType) -> Type {
+        if value.union.is_empty() {
+            return self.member_type(id, 0, value);
+        }
+        let members = value.members()
+            .enumerate()
+            .map(|(index, member)| match self.member_type(id, index, member) {
+                Type::Void => Type::Any,
+                result => result,
+            })
+            .collect();
+        return Type::union(members);
+    }
+
+    /// Type of a value, `void` was reported where it was written and is
+    /// treated as `any` so it doesn't cause further errors
+    fn data_type(&self, id: NodeId, value: &DataType) -> Type {
+        return match self.return_type(id, value) {
+            Type::Void => Type::Any,
+            result => result,
+        };
+    }
+
+    fn value_type(&mut self, id: NodeId, value: &DataType) -> Type {
+        self.check_void(id, value, false);
+        return self.data_type(id, value);
+    }
+
+    // Reports `void` written anywhere but as a whole return type
+    fn check_void(&mut self, id: NodeId, value: &DataType, returned: bool) {
+        if returned && value.union.is_empty() {
+            return;
+        }
+        for (index, member) in value.members().enumerate() {
+            if self.member_type(id, index, member) == Type::Void {
+                self.error(TypeError::InvalidType(member.span));
+            }
+        }
+    }
+
+    // Type named by a declaration in type position
+    fn declared_type(&self, declaration: Declaration) -> Type {
+        return match declaration {
+            Declaration::Builtin(name) => Type::builtin(name.as_str()).unwrap_or(Type::Any),
+            Declaration::Node(id) => match self.source.get(id) {
+                AST::Struct(value) => Type::Struct(value.name, self.source.cast(id).unwrap()),
+                AST::Trait(value) => Type::Trait(value.name, self.source.cast(id).unwrap()),
+                AST::TypeAlias(_) => self.aliases.get(id).cloned().unwrap_or(Type::Any),
+                _ => Type::Any,
+            },
+            Declaration::This(_) => Type::Any,
+        };
+    }
+
+    // Type an alias stands for, the aliases it refers to are worked out first
+    fn alias(&mut self, id: NodeId<TypeAlias>, visiting: &mut Vec<NodeId<TypeAlias>>) -> Type {
+        if let Some(value) = self.aliases.get(id) {
+            return value.clone();
+        }
+        let value = self.source.get(id);
+        if visiting.contains(&id) {
+            self.error(TypeError::Recursive(self.source.span(id.untyped()), value.name));
+            self.aliases.insert(id, Type::Any);
+            return Type::Any;
+        }
+        visiting.push(id);
+        if value.union {
+            let mut members = Vec::new();
+            for (index, data_type) in value.types.iter().enumerate() {
+                if let Some(Declaration::Node(node)) = self.resolution.type_at(id, index) {
+                    if let Some(other) = self.source.cast::<TypeAlias>(node) {
+                        self.alias(other, visiting);
+                    }
+                }
+                members.push(match self.member_type(id.untyped(), index, data_type) {
+                    Type::Void => Type::Any,
+                    member => member,
+                });
+            }
+            visiting.pop();
+            let result = Type::union(members);
+            return self.aliases.get(id).cloned().unwrap_or_else(|| {
+                self.aliases.insert(id, result.clone());
+                result
+            });
+        }
+        let mut parts = Vec::new();
+        let mut nullable = true;
+        for (index, data_type) in value.types.iter().enumerate() {
+            if let Some(Declaration::Node(node)) = self.resolution.type_at(id, index) {
+                if let Some(other) = self.source.cast::<TypeAlias>(node) {
+                    self.alias(other, visiting);
+                }
+            }
+            let part = self.member_type(id.untyped(), index, data_type);
+            nullable &= part.includes_null();
+            match part.without_null() {
+                // Nested intersections are flattened
+                Type::Intersection(_, other) => {
+                    for part in self.intersections.get(other).map_or(&[][..], |other| &other.parts) {
+                        if !parts.contains(part) {
+                            parts.push(part.clone());
+                        }
+                    }
+                }
+                // Names that couldn't be resolved don't narrow anything
+                Type::Any => {}
+                part @ (Type::Struct(..) | Type::Trait(..)) if !parts.contains(&part) => parts.push(part),
+                Type::Struct(..) | Type::Trait(..) => {}
+                part if value.types.len() > 1 => self.error(TypeError::Intersect(data_type.span, part)),
+                _ => {}
+            }
+        }
+        visiting.pop();
+        // Already set when the alias turned out to be recursive
+        if let Some(found) = self.aliases.get(id) {
+            return found.clone();
+        }
+        let result = if value.types.len() == 1 {
+            // Plain aliases are only another name for their type
+            self.member_type(id.untyped(), 0, &value.types[0])
+        } else if parts.len() > 1 {
+            let intersection = Intersection {
+                parts,
+                properties: Vec::new(),
+            };
+            self.intersections.insert(id, intersection);
+            let result = Type::Intersection(value.name, id);
+            if nullable { result.nullable() } else { result }
+        } else {
+            let result = parts.pop().unwrap_or(Type::Any);
+            if nullable { result.nullable() } else { result }
+        };
+        self.aliases.insert(id, result.clone());
+        return result;
+    }
+
+    // Merges the properties of the struct parts of an intersection, a
+    // property has to be given a value that fits every part
+    fn merge(&mut self, id: NodeId<TypeAlias>) {
+        let Some(intersection) = self.intersections.get(id) else { return };
+        let mut properties: Vec<(Name, Type)> = Vec::new();
+        for part in intersection.parts.clone() {
+            let Type::Struct(_, struct_id) = part else { continue };
+            for property in &self.source.get(struct_id).properties {
+                let name = self.source.get(*property).name;
+                let found = self.property_type(*property);
+                let Some(index) = properties.iter().position(|(other, _)| *other == name) else {
+                    properties.push((name, found));
+                    continue;
+                };
+                let merged = self.meet(&properties[index].1, &found).unwrap_or_else(|| {
+                    let span = self.source.span(id.untyped());
+                    self.error(TypeError::Conflict(span, name, properties[index].1.clone(), found));
+                    Type::Any
+                });
+                properties[index].1 = merged;
+            }
+        }
+        self.intersections.get_mut(id).unwrap().properties = properties;
+    }
+
+    // Narrowest type that is both types, null and ndef are only kept when
+    // both include them. None when no value can be both.
+    fn meet(&mut self, left: &Type, right: &Type) -> Option<Type> {
+        if left == right || *right == Type::Any {
+            return Some(left.clone());
+        }
+        if *left == Type::Any {
+            return Some(right.clone());
+        }
+        let present_left = left.without_null().without_ndef();
+        let present_right = right.without_null().without_ndef();
+        let mut result = if self.assignable(&present_left, &present_right) {
+            present_left
+        } else if self.assignable(&present_right, &present_left) {
+            present_right
+        } else {
+            return None;
+        };
+        if left.includes_null() && right.includes_null() {
+            result = result.nullable();
+        }
+        if left.includes_ndef() && right.includes_ndef() {
+            result = result.optional();
+        }
+        return Some(result);
+    }
+
+    // Type of a property of a struct or intersection value
+    fn property(&self, value: &Type, name: Name) -> Option<Type> {
+        return match value {
+            Type::Struct(_, id) => self.source.get(*id).properties.iter()
+                .find(|property| self.source.get(**property).name == name)
+                .map(|property| self.property_type(*property)),
+            Type::Intersection(_, id) => self.intersections.get(*id)?.properties.iter()
+                .find(|(other, _)| *other == name)
+                .map(|(_, value)| value.clone()),
+            _ => None,
+        };
+    }
+
+    // Whether a value has every property and trait of an intersection,
+    // properties that may be ndef can be left out
+    fn fits(&mut self, from: &Type, to: NodeId<TypeAlias>) -> bool {
+        let Some(intersection) = self.intersections.get(to).cloned() else { return true };
+        for part in &intersection.parts {
+            if matches!(part, Type::Trait(..)) && !self.assignable(from, part) {
+                return false;
+            }
+        }
+        for (name, expected) in &intersection.properties {
+            let fits = match self.property(from, *name) {
+                Some(found) => self.assignable(&found, expected),
+                None => expected.includes_ndef(),
+            };
+            if !fits {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    fn signature(&self, id: NodeId) -> Type {
+        let stub = self.source.get::<FunctionStub>(self.source.cast(id).unwrap());
+        let arguments = stub.arguments.iter()
+            .map(|argument| {
+                let value = self.source.get(*argument).data_type.as_ref();
+                value.map_or(Type::Any, |value| self.data_type(argument.untyped(), value))
+            })
+            .collect();
+        let return_type = match &stub.return_type {
+            Some(value) => self.return_type(id, value),
+            None => Type::Void,
+        };
+        return Type::function(arguments, return_type);
+    }
+
+    // Type of a property, optional ones may be ndef
+    fn property_type(&self, id: NodeId<StructProperty>) -> Type {
+        let property = self.source.get(id);
+        let value = self.data_type(id.untyped(), &property.type_of);
+        return if property.optional { value.optional() } else { value };
+    }
+
+    // Type of a declaration used as a value
+    fn value_of(&self, declaration: Declaration) -> Type {
+        return match declaration {
+            Declaration::Builtin(name) => match name.as_str() {
+                "print" => Type::function(vec![Type::Any], Type::Void),
+                "assert" => Type::function(vec![Type::Bool], Type::Void),
+                _ => Type::Any,
+            },
+            Declaration::Node(id) => match self.source.get(id) {
+                AST::Variable { variable, .. } => match self.check.types.get(id) {
+                    Some(value) => value.clone(),
+                    // Top level variables can be used ahead of being checked
+                    None => variable.type_of.as_ref().map_or(Type::Any, |value| self.data_type(id, value)),
+                },
+                AST::Argument(argument) => match &argument.data_type {
+                    Some(data_type) => self.data_type(id, data_type),
+                    // Lambda arguments are inferred when the lambda is checked
+                    None => self.check.types.get(id).cloned().unwrap_or(Type::Any),
+                },
+                AST::Arm(_) => self.check.types.get(id).cloned().unwrap_or(Type::Any),
+                AST::Function(_) | AST::FunctionStub(_) => self.signature(id),
+                // Structs are constructed by calling them with every property
+                AST::Struct(value) => {
+                    let arguments = value.properties.iter().map(|property| self.property_type(*property)).collect();
+                    Type::function(arguments, self.declared_type(declaration))
+                }
+                _ => Type::Any,
+            },
+            Declaration::This(id) => match self.resolution.impls.get(id).and_then(|target| target.target) {
+                Some(target) => self.declared_type(target),
+                None => Type::Any,
+            },
+        };
+    }
+
+    /// Whether a value of type `from` can be used where `to` is expected.
+    /// Types that are still being inferred are inferred to fit.
+    fn assignable(&mut self, from: &Type, to: &Type) -> bool {
+        let (from, to) = (self.resolved(from, None), self.resolved(to, None));
+        return match (&from, &to) {
+            (Type::Var(from), Type::Var(to)) if from == to => true,
+            (Type::Var(index), value) | (value, Type::Var(index)) => self.bind(*index, value),
+            (Type::Void, Type::Void) => true,
+            (Type::Void, _) | (_, Type::Void) => false,
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Ndef, Type::Optional(_)) => true,
+            (Type::Optional(from), Type::Optional(to)) => self.assignable(from, to),
+            (Type::Optional(_), _) => false,
+            (from, Type::Optional(to)) => self.assignable(from, to),
+            (Type::Null, Type::Nullable(_)) => true,
+            (Type::Nullable(from), Type::Nullable(to)) => self.assignable(from, to),
+            (Type::Nullable(_), _) => false,
+            (from, Type::Nullable(to)) => self.assignable(from, to),
+            // Every type a union can be has to fit, and a value fits a union
+            // when it fits any of its types
+            (Type::Union(members), to) => {
+                let to = to.clone();
+                members.clone().iter().all(|member| self.assignable(member, &to))
+            }
+            (from, Type::Union(members)) => {
+                let from = from.clone();
+                members.clone().iter().any(|member| self.assignable(&from, member))
+            }
+            (Type::Intersection(_, from), Type::Intersection(_, to)) if from == to => true,
+            // Intersections are structural, any value with the properties
+            // and traits fits
+            (Type::Struct(..) | Type::Trait(..) | Type::Intersection(..), Type::Intersection(_, to)) => {
+                let to = *to;
+                self.fits(&from, to)
+            }
+            // A value of an intersection is a value of each of its parts and
+            // of the traits implemented for the alias
+            (Type::Intersection(_, id), to) => {
+                let parts = self.intersections.get(*id).map_or(Vec::new(), |value| value.parts.clone());
+                let implemented = matches!(to, Type::Trait(_, trait_id)
+                    if self.implements.contains(&(*trait_id, Declaration::Node(id.untyped()))));
+                implemented || parts.iter().any(|part| self.assignable(part, to))
+            }
+            (Type::Struct(..) | Type::Trait(..) | Type::Obj, Type::Obj) => true,
+            (Type::Struct(_, from), Type::Trait(_, to)) => {
+                self.implements.contains(&(*to, Declaration::Node(from.untyped())))
+            }
+            (Type::Trait(_, from), Type::Trait(_, to)) => self.supertraits(*from).contains(to),
+            // Arguments are contravariant and return types covariant
+            (Type::Function(from), Type::Function(to)) => {
+                from.arguments.len() == to.arguments.len()
+                    && from.arguments.iter().zip(&to.arguments).all(|(from, to)| self.assignable(to, from))
+                    && self.assignable(&from.return_type, &to.return_type)
+            }
+            (from, to) => from == to,
+        };
+    }
+
+    // Whether values of the two types can ever be equal
+    fn comparable(&mut self, left: &Type, right: &Type) -> bool {
+        return *left != Type::Void
+            && *right != Type::Void
+            && (self.assignable(left, right) || self.assignable(right, left));
+    }
+
+    /// Narrowest type both types can be used as, for the branches of a match
+    /// and the sides of `??`
+    fn join(&mut self, left: Type, right: Type) -> Type {
+        return match (left, right) {
+            (left, right) if left == right => left,
+            (Type::Any, _) | (_, Type::Any) => Type::Any,
+            (Type::Null, value) | (value, Type::Null) => value.nullable(),
+            (Type::Ndef, value) | (value, Type::Ndef) => value.optional(),
+            (left, right) if self.assignable(&left, &right) => right,
+            (left, right) if self.assignable(&right, &left) => left,
+            (left, right) => Type::union(vec![left, right]),
+        };
+    }
+
+    // Reports a mismatch when the type of the node can't be used as `expected`
+    fn expect(&mut self, id: NodeId, found: &Type, expected: &Type) {
+        if self.assignable(found, expected) {
+            return;
+        }
+        let span = self.source.span(id);
+        let present = found.without_null().without_ndef();
+        if *found == Type::Void {
+            self.error(TypeError::NoValue(span));
+        } else if present != *found && self.assignable(&present, expected) {
+            self.error(TypeError::MaybeMissing(span, expected.clone(), found.clone()));
+        } else {
+            self.error(TypeError::Mismatch(span, expected.clone(), found.clone()));
+        }
+    }
+
+    // Identifiers and items of a namespace, which are resolved like them
+    fn is_name(&self, id: NodeId) -> bool {
+        return match self.source.get(id) {
+            AST::Identifier(_) => true,
+            AST::Member { .. } => self.resolution.value(id).is_some(),
+            _ => false,
+        };
+    }
+
+    // The place an expression reads from, if it is one
+    fn place(&self, id: NodeId) -> Option<Place> {
+        return match self.source.get(id) {
+            AST::Identifier(_) | AST::Member { .. } if self.is_name(id) => match self.resolution.value(id)? {
+                Declaration::Builtin(_) => None,
+                root => Some(Place {
+                    root,
+                    members: Vec::new(),
+                }),
+            },
+            AST::Member { target, name } => {
+                let mut place = self.place(*target)?;
+                place.members.push(*name);
+                Some(place)
+            }
+            _ => None,
+        };
+    }
+
+    /// What is known about places when `condition` evaluates to `truthy`
+    fn narrow(&mut self, condition: NodeId, truthy: bool) -> Vec<(Place, Type)> {
+        let mut facts = Vec::new();
+        // Conditions still to look at, left operands on top so facts stay in
+        // source order. Chains of `&&` can be long so this doesn't recurse.
+        let mut pending = vec![(condition, truthy)];
+        while let Some((condition, truthy)) = pending.pop() {
+            match self.source.get(condition) {
+                AST::Operation(operation) => match (operation.operator, operation.right) {
+                    (Operator::Not, None) => pending.push((operation.left, !truthy)),
+                    (Operator::AndAnd, Some(right)) if truthy => {
+                        pending.push((right, true));
+                        pending.push((operation.left, true));
+                    }
+                    (Operator::OrOr, Some(right)) if !truthy => {
+                        pending.push((right, false));
+                        pending.push((operation.left, false));
+                    }
+                    (Operator::Equals, Some(right)) => facts.extend(self.narrow_missing(operation.left, right, truthy)),
+                    (Operator::NotEquals, Some(right)) => facts.extend(self.narrow_missing(operation.left, right, !truthy)),
+                    _ => {}
+                },
+                AST::Is { value, data_type } => {
+                    let (Some(place), Some(current)) = (self.place(*value), self.check.types.get(*value).cloned()) else {
+                        continue;
+                    };
+                    let tested = self.data_type(condition, data_type);
+                    let narrowed = if truthy {
+                        self.refine(&current, &tested)
+                    } else {
+                        self.exclude(&current, &tested)
+                    };
+                    facts.push((place, narrowed));
+                }
+                _ => {}
+            }
+        }
+        return facts;
+    }
+
+    // The types of `value` that are a `tested`, or `tested` itself when the
+    // value could be anything
+    fn refine(&mut self, value: &Type, tested: &Type) -> Type {
+        let value = self.resolved(value, None);
+        if matches!(value, Type::Any | Type::Obj | Type::Var(_)) {
+            return tested.clone();
+        }
+        let mut kept = Vec::new();
+        for member in value.alternatives() {
+            if self.assignable(&member, tested) {
+                kept.push(member);
+            }
+        }
+        return if kept.is_empty() { tested.clone() } else { Type::union(kept) };
+    }
+
+    // The types of `value` that aren't a `tested`
+    fn exclude(&mut self, value: &Type, tested: &Type) -> Type {
+        let value = self.resolved(value, None);
+        if matches!(value, Type::Any | Type::Var(_)) {
+            return value;
+        }
+        let mut kept = Vec::new();
+        for member in value.alternatives() {
+            if !self.assignable(&member, tested) {
+                kept.push(member);
+            }
+        }
+        // Nothing left means the check always holds, which isn't worth
+        // narrowing to nothing for
+        return if kept.is_empty() { value } else { Type::union(kept) };
+    }
+
+    // Comparisons of a place against null or ndef, `equal` is whether the
+    // two sides are known to be equal
+    fn narrow_missing(&self, left: NodeId, right: NodeId, equal: bool) -> Vec<(Place, Type)> {
+        let (value, literal) = match (self.source.get(left), self.source.get(right)) {
+            (_, AST::Literal(literal @ (Literal::Null | Literal::Undefined))) => (left, literal),
+            (AST::Literal(literal @ (Literal::Null | Literal::Undefined)), _) => (right, literal),
+            _ => return Vec::new(),
+        };
+        let (Some(place), Some(current)) = (self.place(value), self.check.types.get(value)) else {
+            return Vec::new();
+        };
+        let narrowed = match (literal, equal) {
+            (Literal::Null, true) => Type::Null,
+            (Literal::Null, false) => current.without_null(),
+            (_, true) => Type::Ndef,
+            (_, false) => current.without_ndef(),
+        };
+        return vec![(place, narrowed)];
+    }
+
+    fn assume(&mut self, facts: Vec<(Place, Type)>) {
+        self.narrowed.extend(facts);
+    }
+
+    fn unary(&mut self, id: NodeId, operator: Operator, value: Type) -> Type {
+        // Each operator only takes one type
+        let inferred = if matches!(operator, Operator::Not) { Type::Bool } else { Type::Num };
+        self.infer(&value, inferred);
+        let value = self.resolved(&value, None);
+        let result = match (operator, &value) {
+            (_, Type::Any) => Some(Type::Any),
+            (Operator::Not, Type::Bool) => Some(Type::Bool),
+            (Operator::Negate, Type::Num) => Some(Type::Num),
+            _ => None,
+        };
+        return result.unwrap_or_else(|| {
+            self.error(TypeError::Operator(self.source.span(id), operator, value, None));
+            Type::Any
+        });
+    }
+
+    fn binary(&mut self, id: NodeId, operator: Operator, left: Type, right: Type) -> Type {
+        match operator {
+            Operator::Equals | Operator::NotEquals | Operator::Coalesce => {}
+            Operator::AndAnd | Operator::OrOr => {
+                self.infer(&left, Type::Bool);
+                self.infer(&right, Type::Bool);
+            }
+            Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Modulo => {
+                self.infer(&left, Type::Num);
+                self.infer(&right, Type::Num);
+            }
+            // The rest take two values of the same type
+            _ => {
+                let (resolved_left, resolved_right) = (self.resolved(&left, None), self.resolved(&right, None));
+                if matches!(resolved_left, Type::Var(_)) || matches!(resolved_right, Type::Var(_)) {
+                    self.assignable(&resolved_left, &resolved_right);
+                }
+            }
+        }
+        let (left, right) = (self.resolved(&left, None), self.resolved(&right, None));
+        let result = match operator {
+            // Equality is defined between any two types that overlap
+            Operator::Equals | Operator::NotEquals => self.comparable(&left, &right).then_some(Type::Bool),
+            Operator::Coalesce => Some(self.join(left.without_null().without_ndef(), right.clone())),
+            _ => match (&left, &right) {
+                (Type::Any, _) | (_, Type::Any) => match operator {
+                    Operator::Less
+                    | Operator::Greater
+                    | Operator::LessEquals
+                    | Operator::GreaterEquals
+                    | Operator::AndAnd
+                    | Operator::OrOr => Some(Type::Bool),
+                    _ => Some(Type::Any),
+                },
+                // Both sides are unknown and were inferred to be the same
+                (Type::Var(_), Type::Var(_)) => match operator {
+                    Operator::Less | Operator::Greater | Operator::LessEquals | Operator::GreaterEquals => {
+                        Some(Type::Bool)
+                    }
+                    _ => Some(left.clone()),
+                },
+                (Type::Num, Type::Num) => match operator {
+                    Operator::AndAnd | Operator::OrOr => None,
+                    Operator::Less | Operator::Greater | Operator::LessEquals | Operator::GreaterEquals => {
+                        Some(Type::Bool)
+                    }
+                    _ => Some(Type::Num),
+                },
+                (Type::Str, Type::Str) => match operator {
+                    Operator::Plus => Some(Type::Str),
+                    Operator::Less | Operator::Greater | Operator::LessEquals | Operator::GreaterEquals => {
+                        Some(Type::Bool)
+                    }
+                    _ => None,
+                },
+                (Type::Bool, Type::Bool) => match operator {
+                    Operator::And | Operator::Or | Operator::Xor | Operator::AndAnd | Operator::OrOr => {
+                        Some(Type::Bool)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            },
+        };
+        return result.unwrap_or_else(|| {
+            self.error(TypeError::Operator(self.source.span(id), operator, left, Some(right)));
+            Type::Any
+        });
+    }
+
+    fn operation(&mut self, id: NodeId, operator: Operator, left: NodeId, right: Option<NodeId>) -> Type {
+        if right.is_none() {
+            let left_type = self.expression(left);
+            return self.unary(id, operator, left_type);
+        }
+        // Chains are checked in a loop as they can be arbitrarily long, the
+        // operations inside of one are recorded like any other expression
+        let (first, operations) = self.source.chain(id);
+        let mut left = first;
+        let mut left_type = self.expression(first);
+        // What the left operand being true (or false) tells, carried along
+        // `a && b && c` so the chain isn't narrowed again for every operand
+        let mut known: Option<(bool, Vec<(Place, Type)>)> = None;
+        for operation in operations {
+            let AST::Operation(Operation { operator, right: Some(right), .. }) = self.source.get(operation) else {
+                continue;
+            };
+            let (operator, right) = (*operator, *right);
+            // The right side of `&&` only runs when the left is true and of `||`
+            // when it is false
+            let truthy = match operator {
+                Operator::AndAnd => Some(true),
+                Operator::OrOr => Some(false),
+                _ => None,
+            };
+            let facts = match (truthy, known.take()) {
+                (Some(truthy), Some((known_truthy, facts))) if truthy == known_truthy => facts,
+                (Some(truthy), _) => self.narrow(left, truthy),
+                (None, _) => Vec::new(),
+            };
+            let saved = self.narrowed.clone();
+            self.assume(facts.clone());
+            let right_type = self.expression(right);
+            self.narrowed = saved;
+            if let Some(truthy) = truthy {
+                let mut chained = facts;
+                chained.extend(self.narrow(right, truthy));
+                known = Some((truthy, chained));
+            }
+            left_type = self.binary(operation, operator, left_type, right_type);
+            if operation != id {
+                left_type = self.resolved(&left_type, None);
+                self.check.types.insert(operation, left_type.clone());
+            }
+            left = operation;
+        }
+        return left_type;
+    }
+
+    // Type of the member `name` of a value of type `target`
+    fn member(&mut self, id: NodeId, target: &Type, name: Name) -> Type {
+        let target = &self.resolved(target, None);
+        if let Type::Var(index) = target {
+            // Members can't be looked up without knowing the type
+            self.bind(*index, &Type::Any);
+            self.error(TypeError::Unknown(self.source.span(id)));
+            return Type::Any;
+        }
+        if target.includes_null() || target.includes_ndef() {
+            let present = target.without_null().without_ndef();
+            if present != Type::Any && present != Type::Null && present != Type::Ndef {
+                self.error(TypeError::MemberOfMissing(self.source.span(id), target.clone(), name));
+                return Type::Any;
+            }
+        }
+        let Some(found) = self.lookup(target, name) else {
+            self.error(TypeError::NoMember(self.source.span(id), target.clone(), name));
+            return Type::Any;
+        };
+        let span = self.source.span(id);
+        if let Some(member) = self.member_node(target, name) {
+            if is_private(self.source, member) && !self.source.same_file(self.source.span(member), span) {
+                self.error(TypeError::Private(span, target.clone(), name));
+            }
+        }
+        return found;
+    }
+
+    // Property or function of an impl a member of a value refers to
+    fn member_node(&self, target: &Type, name: Name) -> Option<NodeId> {
+        return match target {
+            Type::Struct(_, struct_id) => self.source.get(*struct_id).properties.iter()
+                .find(|property| self.source.get(**property).name == name)
+                .map(|property| property.untyped())
+                .or_else(|| self.method_node(Declaration::Node(struct_id.untyped()), name)),
+            Type::Intersection(_, alias_id) => self.method_node(Declaration::Node(alias_id.untyped()), name)
+                .or_else(|| {
+                    let parts = self.intersections.get(*alias_id)?;
+                    parts.parts.iter().find_map(|part| self.member_node(part, name))
+                }),
+            value => {
+                let builtin = value.builtin_name()?;
+                self.method_node(Declaration::Builtin(Name::intern(builtin)), name)
+            }
+        };
+    }
+
+    // Type of a member of a value that is present
+    fn lookup(&self, target: &Type, name: Name) -> Option<Type> {
+        return match target {
+            Type::Any | Type::Obj => Some(Type::Any),
+            Type::Struct(_, struct_id) => self.property(target, name)
+                .or_else(|| self.method(Declaration::Node(struct_id.untyped()), name)),
+            Type::Trait(_, trait_id) => self.trait_function(*trait_id, name),
+            // Functions come from impls of the alias and of each part
+            Type::Intersection(_, alias_id) => self.property(target, name)
+                .or_else(|| self.method(Declaration::Node(alias_id.untyped()), name))
+                .or_else(|| {
+                    let parts = self.intersections.get(*alias_id)?;
+                    parts.parts.iter().find_map(|part| match part {
+                        Type::Struct(_, struct_id) => self.method(Declaration::Node(struct_id.untyped()), name),
+                        Type::Trait(_, trait_id) => self.trait_function(*trait_id, name),
+                        _ => None,
+                    })
+                }),
+            // Members shared by every type of a union, with the types of all of them
+            Type::Union(members) => {
+                let found: Option<Vec<_>> = members.iter().map(|member| self.lookup(member, name)).collect();
+                found.map(Type::union)
+            }
+            // Builtin types can be given functions through impls
+            value => value.builtin_name().and_then(|builtin| self.method(Declaration::Builtin(Name::intern(builtin)), name)),
+        };
+    }
+
+    // Functions of supertraits can be called on trait values
+    fn trait_function(&self, id: NodeId<SpewTrait>, name: Name) -> Option<Type> {
+        return self.supertraits(id).iter()
+            .flat_map(|trait_id| &self.source.get(*trait_id).functions)
+            .find(|function| self.source.get(**function).name == name && !is_static(self.source.get(**function)))
+            .map(|function| self.signature(function.untyped()));
+    }
+
+    // The trait followed by all of its supertraits, nearest first
+    fn supertraits(&self, id: NodeId<SpewTrait>) -> Vec<NodeId<SpewTrait>> {
+        let mut found = vec![id];
+        let mut index = 0;
+        while index < found.len() {
+            for supertrait in self.resolution.supertraits.get(found[index]).into_iter().flatten() {
+                if !found.contains(supertrait) {
+                    found.push(*supertrait);
+                }
+            }
+            index += 1;
+        }
+        return found;
+    }
+
+    fn method(&self, target: Declaration, name: Name) -> Option<Type> {
+        return self.method_node(target, name).map(|function| self.signature(function));
+    }
+
+    fn method_node(&self, target: Declaration, name: Name) -> Option<NodeId> {
+        return self.methods.get(&target)?.iter()
+            .find(|function| self.source.get(**function).stub.name == name)
+            .map(|function| function.untyped());
+    }
+
+    fn call(&mut self, id: NodeId, target: NodeId, arguments: &[NodeId]) -> Type {
+        let callee = self.expression(target);
+        let types: Vec<_> = arguments.iter().map(|argument| self.expression(*argument)).collect();
+        // Values of unknown type that are called are inferred to be
+        // functions taking what they are called with
+        if let Type::Var(index) = callee {
+            let inferred = Type::function(arguments.iter().map(|_| self.fresh()).collect(), self.fresh());
+            self.bind(index, &inferred);
+        }
+        let callee = self.resolved(&callee, None);
+        return match &callee {
+            Type::Any => Type::Any,
+            Type::Function(signature) => {
+                if signature.arguments.len() != arguments.len() {
+                    let count = TypeError::ArgumentCount(self.source.span(id), signature.arguments.len(), arguments.len());
+                    self.error(count);
+                }
+                for ((argument, found), expected) in arguments.iter().zip(&types).zip(&signature.arguments) {
+                    self.expect(*argument, found, expected);
+                }
+                signature.return_type.clone()
+            }
+            _ => {
+                self.error(TypeError::NotCallable(self.source.span(target), callee.clone()));
+                Type::Any
+            }
+        };
+    }
+
+    // Type of a match, void when any of the arms is a block
+    fn match_value(&mut self, value: NodeId, arms: &[NodeId<MatchArm>]) -> Type {
+        let scrutinee = self.expression(value);
+        let place = self.place(value);
+        // What the value can still be after the arms so far didn't match
+        let mut remaining = scrutinee.clone();
+        let mut result: Option<Type> = None;
+        let saved = self.narrowed.clone();
+        // What each arm that didn't return knew at its end
+        let mut ends = Vec::new();
+        for arm in arms {
+            let value = self.source.get(*arm);
+            let matched = match &value.pattern {
+                Pattern::Literal(literal) => {
+                    let pattern = literal_type(literal);
+                    if !self.comparable(&pattern, &scrutinee) {
+                        self.error(TypeError::Pattern(self.source.span(arm.untyped()), scrutinee.clone(), pattern.clone()));
+                    }
+                    match literal {
+                        Literal::Null => remaining = remaining.without_null(),
+                        Literal::Undefined => remaining = remaining.without_ndef(),
+                        _ => {}
+                    }
+                    pattern
+                }
+                Pattern::Type(data_type) => {
+                    let tested = self.value_type(arm.untyped(), data_type);
+                    if !self.comparable(&scrutinee, &tested) {
+                        let span = self.source.span(arm.untyped());
+                        self.error(TypeError::Impossible(span, scrutinee.clone(), tested.clone()));
+                    }
+                    let matched = self.refine(&remaining, &tested);
+                    remaining = self.exclude(&remaining, &tested);
+                    matched
+                }
+                Pattern::Wildcard | Pattern::Binding(_) => remaining.clone(),
+            };
+            if let Pattern::Binding(_) = value.pattern {
+                self.check.types.insert(*arm, matched.clone());
+            }
+
+            if let Some(place) = &place {
+                self.narrowed.insert(place.clone(), matched);
+            }
+            let (found, returns) = match self.source.get(value.body) {
+                AST::Block(contents) => (Type::Void, self.statements(contents)),
+                _ => (self.expression(value.body), false),
+            };
+            let end = std::mem::replace(&mut self.narrowed, saved.clone());
+            if !returns {
+                ends.push(end);
+            }
+            result = Some(match result {
+                Some(Type::Void) => Type::Void,
+                Some(_) if found == Type::Void => Type::Void,
+                Some(previous) => self.join(previous, found),
+                None => found,
+            });
+        }
+        // Without a catch all arm the value may not have matched any of them
+        let exhaustive = arms.iter()
+            .any(|arm| matches!(self.source.get(*arm).pattern, Pattern::Wildcard | Pattern::Binding(_)));
+        if !exhaustive {
+            let mut end = saved;
+            if let Some(place) = place {
+                end.insert(place, remaining);
+            }
+            ends.push(end);
+        }
+        if !ends.is_empty() {
+            self.narrowed = common(ends);
+        }
+        return result.unwrap_or(Type::Void);
+    }
+
+    fn expression(&mut self, id: NodeId) -> Type {
+        let result = match self.source.get(id) {
+            AST::Literal(value) => literal_type(value),
+            AST::Identifier(_) | AST::Member { .. } if self.is_name(id) => {
+                match self.place(id).and_then(|place| self.narrowed.get(&place)) {
+                    Some(narrowed) => narrowed.clone(),
+                    None => match self.resolution.value(id) {
+                        Some(declaration @ Declaration::Node(node)) => match self.generalized.get(node).cloned() {
+                            Some(generic) => {
+                                let found = self.value_of(declaration);
+                                self.instantiate(&found, &generic)
+                            }
+                            None => self.value_of(declaration),
+                        },
+                        Some(declaration) => self.value_of(declaration),
+                        None => Type::Any,
+                    },
+                }
+            }
+            AST::Path { .. } => self.resolution.value(id).map_or(Type::Any, |declaration| self.value_of(declaration)),
+            AST::Operation(operation) => self.operation(id, operation.operator, operation.left, operation.right),
+            AST::Call { target, arguments } => self.call(id, *target, arguments),
+            AST::Member { target, name } => {
+                let target = self.expression(*target);
+                let found = self.member(id, &target, *name);
+                match self.place(id).and_then(|place| self.narrowed.get(&place)) {
+                    Some(narrowed) => narrowed.clone(),
+                    None => found,
+                }
+            }
+            AST::Is { value, data_type } => {
+                let found = self.expression(*value);
+                let tested = self.value_type(id, data_type);
+                if !self.comparable(&found, &tested) {
+                    self.error(TypeError::Impossible(self.source.span(id), found, tested));
+                }
+                Type::Bool
+            }
+            AST::Match { value, arms } => self.match_value(*value, arms),
+            AST::Lambda(value) => self.lambda(id, value),
+            AST::Assign { target, value } => {
+                self.assign(*target, *value);
+                Type::Void
+            }
+            // Statements have no value of their own
+            _ => {
+                self.statement(id);
+                return Type::Void;
+            }
+        };
+        let result = self.resolved(&result, None);
+        self.check.types.insert(id, result.clone());
+        return result;
+    }
+
+    // Checks the value against the declared type of the target, which then
+    // holds the type of the value until it is narrowed again
+    fn assign(&mut self, target: NodeId, value: NodeId) {
+        let expected = match self.source.get(target) {
+            AST::Identifier(_) | AST::Member { .. } if self.is_name(target) => match self.resolution.value(target) {
+                Some(declaration) => self.value_of(declaration),
+                None => Type::Any,
+            },
+            AST::Member { target: object, name } => {
+                let object = self.expression(*object);
+                self.member(target, &object, *name)
+            }
+            _ => self.expression(target),
+        };
+        let expected = self.resolved(&expected, None);
+        self.check.types.insert(target, expected.clone());
+        let found = self.expression(value);
+        self.expect(value, &found, &expected);
+        let Some(place) = self.place(target) else { return };
+        self.forget(&place);
+        let found = self.resolved(&found, None);
+        if found != expected && !matches!(found, Type::Any | Type::Var(_)) && self.assignable(&found, &expected) {
+            self.narrowed.insert(place, found);
+        }
+    }
+
+    // Nothing known about the place or what it held still holds once it is
+    // assigned
+    fn forget(&mut self, place: &Place) {
+        self.narrowed.retain(|known, _| known.root != place.root || !known.members.starts_with(&place.members));
+    }
+
+    // Forgets every place assigned anywhere in a loop body, as the loop
+    // runs the body again with whatever the last pass left behind
+    fn forget_assigned(&mut self, contents: &[NodeId]) {
+        let mut pending = contents.to_vec();
+        while let Some(id) = pending.pop() {
+            let node = self.source.get(id);
+            if let Some(place) = match node {
+                AST::Assign { target, .. } => self.place(*target),
+                _ => None,
+            } {
+                self.forget(&place);
+            }
+            pending.extend(node.children());
+        }
+    }
+
+    // Checks statements in order, returns whether they always return
+    fn statements(&mut self, contents: &[NodeId]) -> bool {
+        let mut returns = false;
+        for statement in contents {
+            returns |= self.statement(*statement);
+        }
+        return returns;
+    }
+
+    /// Checks a statement, returns whether it always returns so code after
+    /// it only runs when it didn't
+    fn statement(&mut self, id: NodeId) -> bool {
+        match self.source.get(id) {
+            AST::Variable { variable, value } => {
+                let start = self.variables.len();
+                let declared = variable.type_of.as_ref().map(|value| self.value_type(id, value));
+                let found = value.map(|value| (value, self.expression(value)));
+                let binding = match (declared, found) {
+                    (Some(declared), Some((value, found))) => {
+                        self.expect(value, &found, &declared);
+                        declared
+                    }
+                    (Some(declared), None) => declared,
+                    (None, Some((value, found))) => {
+                        if found == Type::Void {
+                            self.error(TypeError::NoValue(self.source.span(value)));
+                            Type::Any
+                        } else {
+                            // Lambdas can be used with different types
+                            // wherever what they do allows it
+                            if let AST::Lambda(_) = self.source.get(value) {
+                                self.generalize(id, &found, start);
+                            }
+                            found
+                        }
+                    }
+                    // Inferred from how the variable is used
+                    (None, None) => self.fresh(),
+                };
+                self.check.types.insert(id, binding);
+                return false;
+            }
+            AST::Return(value) => {
+                let expected = self.return_type.clone();
+                match value {
+                    Some(value) => {
+                        let found = self.expression(*value);
+                        self.expect(*value, &found, &expected);
+                        self.returns_value = true;
+                    }
+                    None if !self.assignable(&Type::Void, &expected) => {
+                        self.error(TypeError::Mismatch(self.source.span(id), expected, Type::Void));
+                    }
+                    None => {}
+                }
+                return true;
+            }
+            AST::ConditionBlock {
+                condition,
+                contents,
+                otherwise,
+            } => {
+                let found = self.expression(*condition);
+                self.expect(*condition, &found, &Type::Bool);
+                let saved = self.narrowed.clone();
+                let facts = self.narrow(*condition, true);
+                self.assume(facts);
+                let then_returns = self.statements(contents);
+                let then_narrowed = std::mem::replace(&mut self.narrowed, saved.clone());
+                let facts = self.narrow(*condition, false);
+                self.assume(facts);
+                let else_returns = match otherwise {
+                    Some(otherwise) => self.statement(*otherwise),
+                    None => false,
+                };
+                let else_narrowed = std::mem::take(&mut self.narrowed);
+                // Code after the condition only runs through a branch that
+                // didn't return so what that branch knew still holds
+                self.narrowed = match (then_returns, else_returns) {
+                    (true, true) => saved,
+                    (true, false) => else_narrowed,
+                    (false, true) => then_narrowed,
+                    (false, false) => common(vec![then_narrowed, else_narrowed]),
+                };
+                return then_returns && else_returns;
+            }
+            AST::While { condition, contents } => {
+                self.forget_assigned(contents);
+                let found = self.expression(*condition);
+                self.expect(*condition, &found, &Type::Bool);
+                let saved = self.narrowed.clone();
+                let facts = self.narrow(*condition, true);
+                self.assume(facts);
+                self.statements(contents);
+                // The body may not run at all or leave through a `break`
+                self.narrowed = saved;
+                return false;
+            }
+            // Code after a `break` doesn't run either
+            AST::Break => return true,
+            AST::Block(contents) => return self.statements(contents),
+            _ => {
+                self.expression(id);
+                return false;
+            }
+        }
+    }
+
+    // Lambdas are checked where they are written with the state of the
+    // function around them put aside
+    fn lambda(&mut self, id: NodeId, value: &Lambda) -> Type {
+        let mut arguments = Vec::new();
+        for argument in &value.arguments {
+            let found = match &self.source.get(*argument).data_type {
+                Some(data_type) => self.value_type(argument.untyped(), data_type),
+                None => self.fresh(),
+            };
+            self.check.types.insert(*argument, found.clone());
+            arguments.push(found);
+        }
+        let return_type = match &value.return_type {
+            Some(return_type) => {
+                self.check_void(id, return_type, true);
+                self.return_type(id, return_type)
+            }
+            None => self.fresh(),
+        };
+        let outer_return = std::mem::replace(&mut self.return_type, return_type.clone());
+        let outer_narrowed = std::mem::take(&mut self.narrowed);
+        let outer_returns = std::mem::replace(&mut self.returns_value, false);
+        self.statements(&value.body);
+        if !self.returns_value {
+            self.infer(&return_type, Type::Void);
+        }
+        self.return_type = outer_return;
+        self.narrowed = outer_narrowed;
+        self.returns_value = outer_returns;
+        return Type::function(arguments, return_type);
+    }
+
+    // Error with the inferred types filled in
+    fn finish_error(&self, err: TypeError) -> TypeError {
+        let unknown = Some(&Type::Any);
+        return match err {
+            TypeError::Mismatch(span, expected, found) => {
+                TypeError::Mismatch(span, self.resolved(&expected, unknown), self.resolved(&found, unknown))
+            }
+            TypeError::MaybeMissing(span, expected, found) => {
+                TypeError::MaybeMissing(span, self.resolved(&expected, unknown), self.resolved(&found, unknown))
+            }
+            TypeError::Operator(span, operator, left, right) => TypeError::Operator(
+                span,
+                operator,
+                self.resolved(&left, unknown),
+                right.map(|right| self.resolved(&right, unknown)),
+            ),
+            TypeError::NotCallable(span, value) => TypeError::NotCallable(span, self.resolved(&value, unknown)),
+            TypeError::NoMember(span, value, name) => TypeError::NoMember(span, self.resolved(&value, unknown), name),
+            TypeError::Private(span, value, name) => TypeError::Private(span, self.resolved(&value, unknown), name),
+            TypeError::MemberOfMissing(span, value, name) => {
+                TypeError::MemberOfMissing(span, self.resolved(&value, unknown), name)
+            }
+            TypeError::Pattern(span, value, pattern) => {
+                TypeError::Pattern(span, self.resolved(&value, unknown), self.resolved(&pattern, unknown))
+            }
+            TypeError::Impossible(span, value, tested) => {
+                TypeError::Impossible(span, self.resolved(&value, unknown), self.resolved(&tested, unknown))
+            }
+            err => err,
+        };
+    }
+
+    fn function(&mut self, id: NodeId<SpewFunction>) {
+        let value = self.source.get(id);
+        for argument in &value.stub.arguments {
+            let found = match &self.source.get(*argument).data_type {
+                Some(data_type) => self.value_type(argument.untyped(), data_type),
+                None => Type::Any,
+            };
+            self.check.types.insert(*argument, found);
+        }
+        self.return_type = match &value.stub.return_type {
+            Some(return_type) => {
+                self.check_void(id.untyped(), return_type, true);
+                self.return_type(id.untyped(), return_type)
+            }
+            None => Type::Void,
+        };
+        self.narrowed.clear();
+        self.statements(&value.body);
+    }
+
+    fn item(&mut self, id: NodeId) {
+        match self.source.get(id) {
+            AST::Struct(value) => {
+                for property in &value.properties {
+                    self.value_type(property.untyped(), &self.source.get(*property).type_of);
+                }
+            }
+            AST::Trait(value) => {
+                for function in &value.functions {
+                    let stub = self.source.get(*function);
+                    for argument in &stub.arguments {
+                        if let Some(data_type) = &self.source.get(*argument).data_type {
+                            self.value_type(argument.untyped(), data_type);
+                        }
+                    }
+                    if let Some(return_type) = &stub.return_type {
+                        self.check_void(function.untyped(), return_type, true);
+                    }
+                }
+            }
+            AST::Impl(value) => {
+                for function in &value.functions {
+                    self.function(*function);
+                }
+            }
+            AST::Function(_) => self.function(self.source.cast(id).unwrap()),
+            // Top level variables were checked ahead of everything else
+            _ => {}
+        }
+    }
+}
+
+// What is known at the end of every one of several branches
+fn common(mut states: Vec<HashMap<Place, Type>>) -> HashMap<Place, Type> {
+    let mut result = states.pop().unwrap_or_default();
+    for state in &states {
+        result.retain(|place, value| state.get(place) == Some(value));
+    }
+    return result;
+}
+
+// Adds the variables of a resolved type that aren't in `found` yet
+fn free(value: &Type, found: &mut Vec<usize>) {
+    match value {
+        Type::Var(index) if !found.contains(index) => found.push(*index),
+        Type::Function(signature) => {
+            for argument in &signature.arguments {
+                free(argument, found);
+            }
+            free(&signature.return_type, found);
+        }
+        Type::Union(members) => {
+            for member in members {
+                free(member, found);
+            }
+        }
+        Type::Nullable(value) | Type::Optional(value) => free(value, found),
+        _ => {}
+    }
+}
+
+// Resolved type with some of its variables replaced
+fn substitute(value: &Type, replace: &HashMap<usize, Type>) -> Type {
+    return match value {
+        Type::Var(index) => replace.get(index).cloned().unwrap_or(Type::Var(*index)),
+        Type::Function(signature) => Type::function(
+            signature.arguments.iter().map(|argument| substitute(argument, replace)).collect(),
+            substitute(&signature.return_type, replace),
+        ),
+        Type::Union(members) => Type::union(members.iter().map(|member| substitute(member, replace)).collect()),
+        Type::Nullable(value) => substitute(value, replace).nullable(),
+        Type::Optional(value) => substitute(value, replace).optional(),
+        value => value.clone(),
+    };
+}
+
+// Whether a resolved type contains the variable
+fn occurs(index: usize, value: &Type) -> bool {
+    return match value {
+        Type::Var(other) => *other == index,
+        Type::Function(signature) => {
+            signature.arguments.iter().any(|argument| occurs(index, argument)) || occurs(index, &signature.return_type)
+        }
+        Type::Union(members) => members.iter().any(|member| occurs(index, member)),
+        Type::Nullable(value) | Type::Optional(value) => occurs(index, value),
+        _ => false,
+    };
+}
+
+fn literal_type(value: &Literal) -> Type {
+    return match value {
+        Literal::String(_) => Type::Str,
+        Literal::Number(_) => Type::Num,
+        Literal::Boolean(_) => Type::Bool,
+        Literal::Null => Type::Null,
+        Literal::Undefined => Type::Ndef,
+    };
+}
+
+/// Checks the types of every item and expression in a resolved tree.
+/// Errors are sorted by position.
+pub fn check(source: &ASTSource, resolution: &Resolution) -> TypeCheck {
+    let mut checker = Checker {
+        source,
+        resolution,
+        methods: HashMap::new(),
+        implements: HashSet::new(),
+        aliases: NodeMap::default(),
+        intersections: NodeMap::default(),
+        return_type: Type::Void,
+        returns_value: false,
+        narrowed: HashMap::new(),
+        variables: Vec::new(),
+        generalized: NodeMap::default(),
+        check: TypeCheck::default(),
+    };
+    for (id, target) in resolution.impls.iter() {
+        let Some(declaration) = target.target else { continue };
+        let AST::Impl(value) = source.get(id) else { continue };
+        // Static functions are only called through the type
+        let methods = value.functions.iter().filter(|function| !is_static(&source.get(**function).stub));
+        checker.methods.entry(declaration).or_default().extend(methods);
+        if let Some(trait_of) = target.trait_of {
+            checker.implements.insert((trait_of, declaration));
+        }
+    }
+    // Aliases first so every type written in the tree can be looked up
+    let aliases: Vec<_> = source.roots().iter().filter_map(|id| source.cast::<TypeAlias>(*id)).collect();
+    for id in &aliases {
+        checker.alias(*id, &mut Vec::new());
+    }
+    for id in &aliases {
+        checker.merge(*id);
+    }
+    // Variables first so functions see the types of globals declared after them
+    for id in source.roots() {
+        match source.get(*id) {
+            AST::Variable { .. } => {
+                checker.statement(*id);
+            }
+            AST::Impl(value) => {
+                for constant in &value.constants {
+                    checker.statement(*constant);
+                }
+            }
+            _ => {}
+        }
+    }
+    for id in source.roots() {
+        checker.item(*id);
+    }
+    // Whatever is still unknown had nothing to infer it from
+    let mut check = std::mem::take(&mut checker.check);
+    let ids: Vec<_> = check.types.iter().map(|(id, _)| id).collect();
+    for id in ids {
+        let value = check.types.get_mut(id).unwrap();
+        *value = checker.resolved(value, Some(&Type::Any));
+    }
+    check.errors = check.errors.into_iter().map(|err| checker.finish_error(err)).collect();
+    check.errors.sort_by_key(|err| err.span().start);
+    return check;
+}
@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Display;
+
+use crate::ast::{ASTSource, DataType, FunctionStub, NodeId, SpewFunction, SpewImpl, SpewTrait, AST};
+use crate::intern::Name;
+use crate::resolve::{is_static, Declaration, Resolution};
+use crate::source::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConformError {
+    // Trait followed by the function the impl doesn't define
+    Missing(Span, Name, Name),
+    // Trait followed by the function of the impl it doesn't declare
+    Extra(Span, Name, Name),
+    // Function, expected number of arguments and the number given
+    ArgumentCount(Span, Name, usize, usize),
+    // Type declared by the trait followed by the one given by the impl
+    ArgumentType(Span, String, String),
+    ReturnType(Span, String, String),
+    // Type, trait and the supertrait the type doesn't implement
+    Supertrait(Span, Name, Name, Name),
+    // Trait that is a supertrait of itself
+    Cycle(Span, Name),
+    // Trait implemented for a type more than once
+    Overlap(Span, Name, Name),
+    // Type followed by a function defined by more than one of its impls
+    Ambiguous(Span, Name, Name),
+    // Trait, function and whether the trait declares it `static`
+    Static(Span, Name, Name, bool),
+}
+
+impl ConformError {
+    pub fn span(&self) -> Span {
+        return match self {
+            ConformError::Missing(span, _, _) => *span,
+            ConformError::Extra(span, _, _) => *span,
+            ConformError::ArgumentCount(span, _, _, _) => *span,
+            ConformError::ArgumentType(span, _, _) => *span,
+            ConformError::ReturnType(span, _, _) => *span,
+            ConformError::Supertrait(span, _, _, _) => *span,
+            ConformError::Cycle(span, _) => *span,
+            ConformError::Overlap(span, _, _) => *span,
+            ConformError::Ambiguous(span, _, _) => *span,
+            ConformError::Static(span, _, _, _) => *span,
+        };
+    }
+}
+
+impl Display for ConformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConformError::Missing(_, trait_name, name) => {
+                write!(f, "missing `{}` required by trait `{}`", name, trait_name)
+            }
+            ConformError::Extra(_, trait_name, name) => write!(f, "`{}` is not a function of trait `{}`", name, trait_name),
+            ConformError::ArgumentCount(_, name, expected, found) => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                write!(f, "`{}` should take {} argument{} like its trait, found {}", name, expected, plural, found)
+            }
+            ConformError::ArgumentType(_, expected, found) => {
+                write!(f, "argument should be `{}` like in the trait, found `{}`", expected, found)
+            }
+            ConformError::ReturnType(_, expected, found) => {
+                write!(f, "return type should be `{}` like in the trait, found `{}`", expected, found)
+            }
+            ConformError::Supertrait(_, name, trait_name, supertrait) => write!(
+                f,
+                "`{}` implements `{}` but not its supertrait `{}`",
+                name, trait_name, supertrait
+            ),
+            ConformError::Cycle(_, name) => write!(f, "`{}` is a supertrait of itself", name),
+            ConformError::Overlap(_, trait_name, name) => {
+                write!(f, "`{}` is implemented for `{}` more than once", trait_name, name)
+            }
+            ConformError::Ambiguous(_, name, function) => {
+                write!(f, "`{}` is defined by more than one impl of `{}`", function, name)
+            }
+            ConformError::Static(_, trait_name, name, true) => {
+                write!(f, "`{}` is static in trait `{}` so it has to be `static` here too", name, trait_name)
+            }
+            ConformError::Static(_, trait_name, name, false) => {
+                write!(f, "`{}` isn't static in trait `{}` so it can't be `static` here", name, trait_name)
+            }
+        }
+    }
+}
+
+// Text of a type as written, functions without a return type return void
+fn type_text(value: Option<&DataType>) -> String {
+    return match value {
+        Some(value) => value.to_string(),
+        None => "void".to_string(),
+    };
+}
+
+struct Conformance<'a> {
+    source: &'a ASTSource,
+    resolution: &'a Resolution,
+    errors: Vec<ConformError>,
+}
+
+impl<'a> Conformance<'a> {
+    // Whether types written in two nodes are the same, names that couldn't
+    // be resolved were already reported and match anything. Aliases of a
+    // single type are the type they stand for and unions are the same in
+    // any order.
+    fn same_type(&self, left: (NodeId, Option<&DataType>), right: (NodeId, Option<&DataType>)) -> bool {
+        let declarations = |(id, value): (NodeId, Option<&DataType>)| match value {
+            Some(value) => {
+                let mut declarations = Vec::new();
+                let mut nullable = false;
+                for (index, member) in value.members().enumerate() {
+                    let (declaration, null) = self.resolution.unalias(self.source, self.resolution.type_at(id, index)?);
+                    declarations.push(declaration);
+                    nullable |= null || member.nullable;
+                }
+                Some((declarations, nullable))
+            }
+            None => Some((vec![Declaration::Builtin(Name::intern("void"))], false)),
+        };
+        return match (declarations(left), declarations(right)) {
+            (Some((left, left_nullable)), Some((right, right_nullable))) => {
+                left_nullable == right_nullable
+                    && left.iter().all(|declaration| right.contains(declaration))
+                    && right.iter().all(|declaration| left.contains(declaration))
+            }
+            _ => true,
+        };
+    }
+
+    // Compares the signature of a function of an impl to the one its trait declares
+    fn compare(&mut self, span: Span, expected_id: NodeId<FunctionStub>, found_id: NodeId<SpewFunction>) {
+        let expected = self.source.get(expected_id);
+        let found = &self.source.get(found_id).stub;
+        if expected.arguments.len() != found.arguments.len() {
+            let count = ConformError::ArgumentCount(span, found.name, expected.arguments.len(), found.arguments.len());
+            self.errors.push(count);
+        }
+        for (expected_id, found_id) in expected.arguments.iter().zip(&found.arguments) {
+            // Arguments of named functions always have a type
+            let expected = &self.source.get(*expected_id).data_type;
+            let found = &self.source.get(*found_id).data_type;
+            let (Some(expected), Some(found)) = (expected, found) else { continue };
+            if !self.same_type((expected_id.untyped(), Some(expected)), (found_id.untyped(), Some(found))) {
+                let err = ConformError::ArgumentType(found.full_span(), type_text(Some(expected)), type_text(Some(found)));
+                self.errors.push(err);
+            }
+        }
+        let returns = (expected.return_type.as_ref(), found.return_type.as_ref());
+        if !self.same_type((expected_id.untyped(), returns.0), (found_id.untyped(), returns.1)) {
+            let span = found.return_type.as_ref().map_or(span, |value| value.full_span());
+            let err = ConformError::ReturnType(
+                span,
+                type_text(expected.return_type.as_ref()),
+                type_text(found.return_type.as_ref()),
+            );
+            self.errors.push(err);
+        }
+    }
+
+    // Checks an impl against every function its trait declares
+    fn conform(&mut self, id: NodeId<SpewImpl>, trait_id: NodeId<SpewTrait>) {
+        let value = self.source.get(id);
+        let trait_value = self.source.get(trait_id);
+        for function in &value.functions {
+            let found = &self.source.get(*function).stub;
+            let expected = trait_value.functions.iter().copied().find(|stub| self.source.get(*stub).name == found.name);
+            let span = self.source.span(function.untyped());
+            match expected {
+                Some(expected) if is_static(self.source.get(expected)) != is_static(found) => {
+                    let statics = is_static(self.source.get(expected));
+                    self.errors.push(ConformError::Static(span, trait_value.name, found.name, statics));
+                    self.compare(span, expected, *function);
+                }
+                Some(expected) => self.compare(span, expected, *function),
+                None => self.errors.push(ConformError::Extra(span, trait_value.name, found.name)),
+            }
+        }
+        for stub in &trait_value.functions {
+            let name = self.source.get(*stub).name;
+            if !value.functions.iter().any(|function| self.source.get(*function).stub.name == name) {
+                self.errors.push(ConformError::Missing(self.source.span(id.untyped()), trait_value.name, name));
+            }
+        }
+    }
+
+    // Reports every trait that can reach itself through its supertraits
+    fn cycles(&mut self) {
+        for (id, _) in self.resolution.supertraits.iter() {
+            let id = self.source.cast::<SpewTrait>(id).unwrap();
+            let mut seen = HashSet::new();
+            let mut pending = self.supertraits(id).to_vec();
+            while let Some(next) = pending.pop() {
+                if next == id {
+                    self.errors.push(ConformError::Cycle(self.source.span(id.untyped()), self.source.get(id).name));
+                    break;
+                }
+                if seen.insert(next) {
+                    pending.extend(self.supertraits(next));
+                }
+            }
+        }
+    }
+
+    fn supertraits(&self, id: NodeId<SpewTrait>) -> &'a [NodeId<SpewTrait>] {
+        return self.resolution.supertraits.get(id).map_or(&[], |supertraits| supertraits.as_slice());
+    }
+}
+
+/// Checks that every trait impl defines exactly the functions of its trait
+/// with the same signatures, that the type also implements the supertraits
+/// and that no two impls overlap. Errors are sorted by position.
+pub fn conform(source: &ASTSource, resolution: &Resolution) -> Vec<ConformError> {
+    let mut conformance = Conformance {
+        source,
+        resolution,
+        errors: Vec::new(),
+    };
+    conformance.cycles();
+
+    let mut implemented = HashSet::new();
+    // Impls that didn't overlap with an earlier one, in source order
+    let mut impls = Vec::new();
+    for id in source.ids() {
+        let Some(id) = source.cast::<SpewImpl>(id) else { continue };
+        let Some(target) = resolution.impls.get(id) else { continue };
+        let Some(declaration) = target.target else { continue };
+        // An impl for an alias is an impl for the type it stands for
+        let (declaration, _) = resolution.unalias(source, declaration);
+        if let Some(trait_id) = target.trait_of {
+            if !implemented.insert((trait_id, declaration)) {
+                let overlap = ConformError::Overlap(source.span(id.untyped()), source.get(trait_id).name, source.get(id).name);
+                conformance.errors.push(overlap);
+                continue;
+            }
+            conformance.conform(id, trait_id);
+        }
+        impls.push((id, declaration));
+    }
+
+    for (id, declaration) in &impls {
+        let value = source.get(*id);
+        let Some(trait_id) = resolution.impls.get(*id).and_then(|target| target.trait_of) else { continue };
+        for supertrait in conformance.supertraits(trait_id) {
+            if !implemented.contains(&(*supertrait, *declaration)) {
+                let err = ConformError::Supertrait(
+                    source.span(id.untyped()),
+                    value.name,
+                    source.get(trait_id).name,
+                    source.get(*supertrait).name,
+                );
+                conformance.errors.push(err);
+            }
+        }
+    }
+
+    // Members are looked up by name so a function or constant can only come
+    // from one impl of a type
+    let mut defined = HashMap::new();
+    for (id, declaration) in &impls {
+        let value = source.get(*id);
+        let constants = value.constants.iter().filter_map(|constant| match source.get(*constant) {
+            AST::Variable { variable, .. } => Some((*constant, variable.name)),
+            _ => None,
+        });
+        let functions = value.functions.iter().map(|function| (function.untyped(), source.get(*function).stub.name));
+        for (member, name) in constants.chain(functions) {
+            match defined.get(&(*declaration, name)) {
+                Some(other) if other != id => {
+                    let span = source.span(member);
+                    conformance.errors.push(ConformError::Ambiguous(span, value.name, name));
+                }
+                _ => {
+                    defined.insert((*declaration, name), *id);
+                }
+            }
+        }
+    }
+
+    let mut errors = conformance.errors;
+    errors.sort_by_key(|err| err.span().start);
+    return errors;
+}
@@ -0,0 +1,650 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Display;
+
+use crate::ast::{
+    unescape, ASTSource, DataType, Literal, NodeId, NodeMap, Operation, Operator, Pattern, SpewFunction, Variable, AST,
+};
+use crate::check::TypeCheck;
+use crate::intern::Name;
+use crate::lexer::Modifier;
+use crate::resolve::{Declaration, Resolution};
+use crate::source::Span;
+
+/// Most steps evaluating one constant or call may take, every expression and
+/// statement run is a step
+pub const STEP_LIMIT: usize = 100_000;
+
+// Compile functions calling each other deeper than this are reported rather
+// than overflowing the stack
+const DEPTH_LIMIT: usize = 200;
+
+/// Value computed at compile time, one of the kinds a literal can hold
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    // Without escapes, unlike string literals
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    Null,
+    Undefined,
+}
+
+impl Value {
+    fn from_literal(value: &Literal) -> Value {
+        return match value {
+            Literal::String(value) => Value::String(unescape(value)),
+            Literal::Number(value) => Value::Number(value.parse().unwrap_or(f64::NAN)),
+            Literal::Boolean(value) => Value::Boolean(*value),
+            Literal::Null => Value::Null,
+            Literal::Undefined => Value::Undefined,
+        };
+    }
+
+    /// Literal writing the value, negative numbers are written as a
+    /// negated literal. `None` for numbers that aren't finite.
+    pub fn to_literal(&self) -> Option<Literal> {
+        return match self {
+            Value::String(value) => Some(Literal::String(escape(value))),
+            Value::Number(value) if value.is_finite() => Some(Literal::Number(format!("{}", value.abs()))),
+            Value::Number(_) => None,
+            Value::Boolean(value) => Some(Literal::Boolean(*value)),
+            Value::Null => Some(Literal::Null),
+            Value::Undefined => Some(Literal::Undefined),
+        };
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(value) => write!(f, "\"{}\"", escape(value)),
+            Value::Number(value) => write!(f, "{}", value),
+            Value::Boolean(value) => write!(f, "{}", value),
+            Value::Null => f.write_str("null"),
+            Value::Undefined => f.write_str("ndef"),
+        }
+    }
+}
+
+// Value of a string to the text of a double quoted literal
+fn escape(value: &str) -> String {
+    let mut out = String::new();
+    for char in value.chars() {
+        match char {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            char => out.push(char),
+        }
+    }
+    return out;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    // Function, struct or builtin called that isn't a `compile` function
+    Runtime(Span, Name),
+    // Name whose value is only known when the program runs
+    Unknown(Span, Name),
+    // Expression the evaluator doesn't run, such as lambdas and members
+    Unsupported(Span),
+    // Constant or call that took more than the limit of steps
+    Steps(Span, usize),
+    Depth(Span),
+    // Constant whose initializer reads itself
+    Cycle(Span, Name),
+    // Operator followed by the values it was applied to
+    Operator(Span, Operator, Value, Option<Value>),
+    // Condition that wasn't a `bool`
+    Condition(Span, Value),
+    // Value no arm of a match fits
+    NoArm(Span, Value),
+    // Compile function that finished without returning a value
+    NoValue(Span, Name),
+    // Result that can't be written back as a literal
+    NotLiteral(Span, Value),
+}
+
+impl EvalError {
+    pub fn span(&self) -> Span {
+        return match self {
+            EvalError::Runtime(span, _) => *span,
+            EvalError::Unknown(span, _) => *span,
+            EvalError::Unsupported(span) => *span,
+            EvalError::Steps(span, _) => *span,
+            EvalError::Depth(span) => *span,
+            EvalError::Cycle(span, _) => *span,
+            EvalError::Operator(span, _, _, _) => *span,
+            EvalError::Condition(span, _) => *span,
+            EvalError::NoArm(span, _) => *span,
+            EvalError::NoValue(span, _) => *span,
+            EvalError::NotLiteral(span, _) => *span,
+        };
+    }
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Runtime(_, name) => {
+                write!(f, "`{}` isn't a `compile` function so it can't be called at compile time", name)
+            }
+            EvalError::Unknown(_, name) => write!(f, "`{}` isn't known at compile time", name),
+            EvalError::Unsupported(_) => f.write_str("this can't be evaluated at compile time"),
+            EvalError::Steps(_, limit) => write!(f, "compile time evaluation didn't finish within {} steps", limit),
+            EvalError::Depth(_) => write!(f, "compile functions are nested more than {} calls deep", DEPTH_LIMIT),
+            EvalError::Cycle(_, name) => write!(f, "the value of `{}` depends on itself", name),
+            EvalError::Operator(_, operator, left, Some(right)) => {
+                write!(f, "`{}` can't be applied to `{}` and `{}`", operator.as_str(), left, right)
+            }
+            EvalError::Operator(_, operator, value, None) => {
+                write!(f, "`{}` can't be applied to `{}`", operator.as_str(), value)
+            }
+            EvalError::Condition(_, value) => write!(f, "expected a `bool` condition, found `{}`", value),
+            EvalError::NoArm(_, value) => write!(f, "no arm matches `{}`", value),
+            EvalError::NoValue(_, name) => write!(f, "`{}` finished without returning a value", name),
+            EvalError::NotLiteral(_, value) => write!(f, "`{}` can't be written as a literal", value),
+        }
+    }
+}
+
+/// Values of the constant initializers and `compile` function calls that
+/// run at compile time
+#[derive(Debug, Default)]
+pub struct Evaluation {
+    pub values: NodeMap<Value>,
+    pub errors: Vec<EvalError>,
+}
+
+impl Evaluation {
+    /// Replaces every evaluated node with a literal of its value
+    pub fn inline(&self, source: &mut ASTSource) {
+        for (id, value) in self.values.iter() {
+            let Some(literal) = value.to_literal() else { continue };
+            match value {
+                Value::Number(number) if number.is_sign_negative() && *number != 0.0 => {
+                    let span = source.span(id);
+                    let left = source.alloc(AST::Literal(literal), span);
+                    let operation = Operation {
+                        left,
+                        operator: Operator::Negate,
+                        right: None,
+                    };
+                    source.replace(id, AST::Operation(operation));
+                }
+                _ => {
+                    source.replace(id, AST::Literal(literal));
+                }
+            }
+        }
+    }
+}
+
+// Why evaluation stopped before reaching the end of what it ran
+enum Interrupt {
+    // An error was reported
+    Stop,
+    Break,
+    Return(Option<Value>),
+}
+
+type Eval<T> = Result<T, Interrupt>;
+
+// Locals of a running `compile` function, or of a constant initializer
+// when `function` is `None`
+#[derive(Default)]
+struct Frame {
+    function: Option<NodeId>,
+    values: HashMap<NodeId, Value>,
+}
+
+// Constants and functions run at compile time, other names are only known
+// when the program runs
+fn compile_time(variable: &Variable) -> bool {
+    return (variable.constant || variable.modifiers.contains(&Modifier::Compile))
+        && !variable.modifiers.contains(&Modifier::Mutable);
+}
+
+struct Evaluator<'a> {
+    source: &'a ASTSource,
+    resolution: &'a Resolution,
+    check: &'a TypeCheck,
+    limit: usize,
+    steps: usize,
+    // Constant or call whose evaluation the steps count towards
+    root: Span,
+    frames: Vec<Frame>,
+    // Results of constants, `None` when evaluating them failed
+    constants: HashMap<NodeId, Option<Value>>,
+    evaluating: HashSet<NodeId>,
+    errors: Vec<EvalError>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn error<T>(&mut self, err: EvalError) -> Eval<T> {
+        self.errors.push(err);
+        return Err(Interrupt::Stop);
+    }
+
+    // Whether a node type checked, evaluating one that didn't would fail
+    // the same way its type errors already reported
+    fn typed(&self, id: NodeId) -> bool {
+        let span = self.source.span(id);
+        return !self.check.errors.iter().any(|err| span.contains(err.span()));
+    }
+
+    fn step(&mut self) -> Eval<()> {
+        self.steps += 1;
+        if self.steps > self.limit {
+            return self.error(EvalError::Steps(self.root, self.limit));
+        }
+        return Ok(());
+    }
+
+    // Evaluates a constant initializer or call written outside of compile
+    // functions, the value has to be written back as a literal. Constants
+    // that can be read at compile time are remembered for when they are.
+    fn root(&mut self, id: NodeId, constant: Option<(NodeId, Name)>) -> Option<Value> {
+        let span = self.source.span(id);
+        self.steps = 0;
+        self.root = span;
+        let found = match constant {
+            Some((declaration, name)) => self.constant(declaration, id, name, span),
+            None => self.expression(id),
+        };
+        // Jumps only happen inside of functions which catch them
+        let value = found.ok()?;
+        if value.to_literal().is_none() {
+            self.errors.push(EvalError::NotLiteral(span, value));
+            return None;
+        }
+        return Some(value);
+    }
+
+    // Value of a constant, evaluated on first use
+    fn constant(&mut self, id: NodeId, value: NodeId, name: Name, span: Span) -> Eval<Value> {
+        if let Some(found) = self.constants.get(&id) {
+            return found.clone().ok_or(Interrupt::Stop);
+        }
+        if !self.typed(value) {
+            self.constants.insert(id, None);
+            return Err(Interrupt::Stop);
+        }
+        if !self.evaluating.insert(id) {
+            return self.error(EvalError::Cycle(span, name));
+        }
+        let frames = std::mem::replace(&mut self.frames, vec![Frame::default()]);
+        let found = self.expression(value);
+        self.frames = frames;
+        self.evaluating.remove(&id);
+        let found = found.ok();
+        self.constants.insert(id, found.clone());
+        return found.ok_or(Interrupt::Stop);
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        return self.frames.last_mut().unwrap();
+    }
+
+    fn read(&mut self, id: NodeId, name: Name) -> Eval<Value> {
+        let span = self.source.span(id);
+        let declaration = match self.resolution.value(id) {
+            Some(Declaration::Node(declaration)) => declaration,
+            Some(Declaration::Builtin(_) | Declaration::This(_)) => return self.error(EvalError::Unknown(span, name)),
+            // Already reported as undefined
+            None => return Err(Interrupt::Stop),
+        };
+        if let Some(value) = self.frame().values.get(&declaration) {
+            return Ok(value.clone());
+        }
+        return match self.source.get(declaration) {
+            AST::Variable {
+                variable,
+                value: Some(value),
+            } if compile_time(variable) => self.constant(declaration, *value, name, span),
+            AST::Function(_) | AST::Struct(_) => self.error(EvalError::Unsupported(span)),
+            _ => self.error(EvalError::Unknown(span, name)),
+        };
+    }
+
+    fn assign(&mut self, target: NodeId, value: Value) -> Eval<()> {
+        let span = self.source.span(target);
+        let AST::Identifier(name) = self.source.get(target) else {
+            return self.error(EvalError::Unsupported(span));
+        };
+        let Some(Declaration::Node(declaration)) = self.resolution.value(target) else {
+            return self.error(EvalError::Unknown(span, *name));
+        };
+        // Only locals of the running function can be assigned
+        let function = self.frame().function;
+        if !function.is_some_and(|function| self.source.ancestors(declaration).any(|id| id == function)) {
+            return self.error(EvalError::Unknown(span, *name));
+        }
+        self.frame().values.insert(declaration, value);
+        return Ok(());
+    }
+
+    fn call(&mut self, id: NodeId, target: NodeId, arguments: &[NodeId]) -> Eval<Value> {
+        let span = self.source.span(target);
+        let name = match self.source.get(target) {
+            AST::Identifier(name) | AST::Path { name, .. } => name,
+            AST::Member { name, .. } if self.resolution.value(target).is_some() => name,
+            _ => return self.error(EvalError::Unsupported(self.source.span(id))),
+        };
+        let function = match self.resolution.value(target) {
+            Some(Declaration::Node(declaration)) => self.source.cast::<SpewFunction>(declaration),
+            Some(_) => None,
+            None => return Err(Interrupt::Stop),
+        };
+        let Some(function) = function.filter(|function| self.compile(*function)) else {
+            return self.error(EvalError::Runtime(span, *name));
+        };
+        if !self.typed(function.untyped()) {
+            return Err(Interrupt::Stop);
+        }
+        let mut values = HashMap::new();
+        let value = self.source.get(function);
+        for (argument, expression) in value.stub.arguments.iter().zip(arguments) {
+            let found = self.expression(*expression)?;
+            values.insert(argument.untyped(), found);
+        }
+        if self.frames.len() > DEPTH_LIMIT {
+            return self.error(EvalError::Depth(self.source.span(id)));
+        }
+        self.frames.push(Frame {
+            function: Some(function.untyped()),
+            values,
+        });
+        let result = self.statements(&value.body);
+        self.frames.pop();
+        return match result {
+            Err(Interrupt::Return(Some(value))) => Ok(value),
+            Err(Interrupt::Stop) => Err(Interrupt::Stop),
+            _ => self.error(EvalError::NoValue(self.source.span(id), *name)),
+        };
+    }
+
+    fn compile(&self, function: NodeId<SpewFunction>) -> bool {
+        return self.source.get(function).stub.modifiers.contains(&Modifier::Compile);
+    }
+
+    fn condition(&mut self, id: NodeId) -> Eval<bool> {
+        return match self.expression(id)? {
+            Value::Boolean(value) => Ok(value),
+            value => self.error(EvalError::Condition(self.source.span(id), value)),
+        };
+    }
+
+    fn statements(&mut self, contents: &[NodeId]) -> Eval<()> {
+        for statement in contents {
+            self.statement(*statement)?;
+        }
+        return Ok(());
+    }
+
+    fn statement(&mut self, id: NodeId) -> Eval<()> {
+        match self.source.get(id) {
+            AST::Variable { value, .. } => {
+                self.step()?;
+                if let Some(value) = value {
+                    let found = self.expression(*value)?;
+                    self.frame().values.insert(id, found);
+                }
+            }
+            AST::Return(value) => {
+                self.step()?;
+                let found = match value {
+                    Some(value) => Some(self.expression(*value)?),
+                    None => None,
+                };
+                return Err(Interrupt::Return(found));
+            }
+            AST::Break => return Err(Interrupt::Break),
+            AST::ConditionBlock {
+                condition,
+                contents,
+                otherwise,
+            } => {
+                if self.condition(*condition)? {
+                    self.statements(contents)?;
+                } else if let Some(otherwise) = otherwise {
+                    self.statement(*otherwise)?;
+                }
+            }
+            AST::While { condition, contents } => {
+                while self.condition(*condition)? {
+                    match self.statements(contents) {
+                        Err(Interrupt::Break) => break,
+                        result => result?,
+                    }
+                }
+            }
+            AST::Block(contents) => self.statements(contents)?,
+            _ => {
+                self.expression(id)?;
+            }
+        }
+        return Ok(());
+    }
+
+    fn expression(&mut self, id: NodeId) -> Eval<Value> {
+        self.step()?;
+        let span = self.source.span(id);
+        return match self.source.get(id) {
+            AST::Literal(value) => Ok(Value::from_literal(value)),
+            AST::Identifier(name) | AST::Path { name, .. } => self.read(id, *name),
+            // Items of a namespace
+            AST::Member { name, .. } if self.resolution.value(id).is_some() => self.read(id, *name),
+            AST::Operation(operation) => self.operation(id, operation),
+            AST::Call { target, arguments } => self.call(id, *target, arguments),
+            AST::Is { value, data_type } => {
+                let found = self.expression(*value)?;
+                Ok(Value::Boolean(self.is(&found, id, 0, data_type)))
+            }
+            AST::Assign { target, value } => {
+                let found = self.expression(*value)?;
+                self.assign(*target, found)?;
+                Ok(Value::Undefined)
+            }
+            AST::Match { value, arms } => {
+                let found = self.expression(*value)?;
+                for arm in arms {
+                    let arm_value = self.source.get(*arm);
+                    let matches = match &arm_value.pattern {
+                        Pattern::Literal(literal) => Value::from_literal(literal) == found,
+                        Pattern::Wildcard => true,
+                        Pattern::Binding(_) => {
+                            self.frame().values.insert(arm.untyped(), found.clone());
+                            true
+                        }
+                        Pattern::Type(data_type) => self.is(&found, arm.untyped(), 0, data_type),
+                    };
+                    if !matches {
+                        continue;
+                    }
+                    // Block arms have no value
+                    return match self.source.get(arm_value.body) {
+                        AST::Block(contents) => self.statements(contents).map(|_| Value::Undefined),
+                        _ => self.expression(arm_value.body),
+                    };
+                }
+                self.error(EvalError::NoArm(span, found))
+            }
+            _ => self.error(EvalError::Unsupported(span)),
+        };
+    }
+
+    fn operation(&mut self, id: NodeId, operation: &Operation) -> Eval<Value> {
+        if operation.right.is_none() {
+            let span = self.source.span(id);
+            return match (operation.operator, self.expression(operation.left)?) {
+                (Operator::Not, Value::Boolean(value)) => Ok(Value::Boolean(!value)),
+                (Operator::Negate, Value::Number(value)) => Ok(Value::Number(-value)),
+                (operator, value) => self.error(EvalError::Operator(span, operator, value, None)),
+            };
+        }
+        // Chains are evaluated in a loop as they can be arbitrarily long,
+        // every operation inside of one takes a step like any expression
+        let (first, operations) = self.source.chain(id);
+        let mut value = self.expression(first)?;
+        for operation in operations {
+            if operation != id {
+                self.step()?;
+            }
+            let AST::Operation(operation_value) = self.source.get(operation) else { continue };
+            value = self.binary(operation, operation_value, value)?;
+        }
+        return Ok(value);
+    }
+
+    // Applies a binary operation to the value of its left operand
+    fn binary(&mut self, id: NodeId, operation: &Operation, left: Value) -> Eval<Value> {
+        let span = self.source.span(id);
+        let right = operation.right.unwrap();
+        // The right hand side only runs when the left doesn't decide
+        match (operation.operator, &left) {
+            (Operator::AndAnd, Value::Boolean(false)) | (Operator::OrOr, Value::Boolean(true)) => return Ok(left),
+            (Operator::AndAnd | Operator::OrOr, Value::Boolean(_)) => return self.condition(right).map(Value::Boolean),
+            (Operator::AndAnd | Operator::OrOr, _) => return self.error(EvalError::Condition(span, left)),
+            (Operator::Coalesce, Value::Null | Value::Undefined) => return self.expression(right),
+            (Operator::Coalesce, _) => return Ok(left),
+            _ => {}
+        }
+        let right = self.expression(right)?;
+        let found = match (operation.operator, &left, &right) {
+            (Operator::Equals, _, _) => Some(Value::Boolean(left == right)),
+            (Operator::NotEquals, _, _) => Some(Value::Boolean(left != right)),
+            (operator, Value::Number(a), Value::Number(b)) => number(operator, *a, *b),
+            (operator, Value::String(a), Value::String(b)) => match operator {
+                Operator::Plus => Some(Value::String(format!("{}{}", a, b))),
+                Operator::Less => Some(Value::Boolean(a < b)),
+                Operator::Greater => Some(Value::Boolean(a > b)),
+                Operator::LessEquals => Some(Value::Boolean(a <= b)),
+                Operator::GreaterEquals => Some(Value::Boolean(a >= b)),
+                _ => None,
+            },
+            (operator, Value::Boolean(a), Value::Boolean(b)) => match operator {
+                Operator::And => Some(Value::Boolean(a & b)),
+                Operator::Or => Some(Value::Boolean(a | b)),
+                Operator::Xor => Some(Value::Boolean(a ^ b)),
+                _ => None,
+            },
+            _ => None,
+        };
+        return match found {
+            Some(found) => Ok(found),
+            None => self.error(EvalError::Operator(span, operation.operator, left, Some(right))),
+        };
+    }
+
+    // Whether a value is of a type written in the node `id`, `index` counts
+    // the types written in the node before it. Structs and traits are never
+    // known at compile time.
+    fn is(&self, value: &Value, id: NodeId, index: usize, data_type: &DataType) -> bool {
+        if data_type.nullable && *value == Value::Null {
+            return true;
+        }
+        return match self.resolution.type_at(id, index) {
+            Some(Declaration::Builtin(name)) => matches!(
+                (name.as_str(), value),
+                ("any", _)
+                    | ("str", Value::String(_))
+                    | ("num", Value::Number(_))
+                    | ("bool", Value::Boolean(_))
+                    | ("null", Value::Null)
+                    | ("ndef", Value::Undefined)
+            ),
+            Some(Declaration::Node(declaration)) => match self.source.get(declaration) {
+                AST::TypeAlias(alias) if alias.union || alias.types.len() == 1 => {
+                    alias.types.iter().enumerate().any(|(index, part)| self.is(value, declaration, index, part))
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+    }
+}
+
+// Arithmetic, comparisons and bitwise operators on numbers, the bitwise
+// ones only on whole numbers
+fn number(operator: Operator, a: f64, b: f64) -> Option<Value> {
+    let whole = |value: f64| (value.fract() == 0.0 && value.abs() < 2f64.powi(53)).then_some(value as i64);
+    return match operator {
+        Operator::Plus => Some(Value::Number(a + b)),
+        Operator::Minus => Some(Value::Number(a - b)),
+        Operator::Multiply => Some(Value::Number(a * b)),
+        Operator::Divide => Some(Value::Number(a / b)),
+        Operator::Modulo => Some(Value::Number(a % b)),
+        Operator::Less => Some(Value::Boolean(a < b)),
+        Operator::Greater => Some(Value::Boolean(a > b)),
+        Operator::LessEquals => Some(Value::Boolean(a <= b)),
+        Operator::GreaterEquals => Some(Value::Boolean(a >= b)),
+        Operator::And => Some(Value::Number((whole(a)? & whole(b)?) as f64)),
+        Operator::Or => Some(Value::Number((whole(a)? | whole(b)?) as f64)),
+        Operator::Xor => Some(Value::Number((whole(a)? ^ whole(b)?) as f64)),
+        _ => None,
+    };
+}
+
+/// Runs every constant initializer and every call of a `compile` function
+/// outside of compile functions, whose bodies only run when called. Each of
+/// them may take up to `limit` steps. Those that didn't type check are
+/// skipped, as are calls of functions that didn't. Errors are sorted by
+/// position.
+pub fn evaluate(source: &ASTSource, resolution: &Resolution, check: &TypeCheck, limit: usize) -> Evaluation {
+    let mut evaluator = Evaluator {
+        source,
+        resolution,
+        check,
+        limit,
+        steps: 0,
+        root: Span::new(0, 0),
+        frames: vec![Frame::default()],
+        constants: HashMap::new(),
+        evaluating: HashSet::new(),
+        errors: Vec::new(),
+    };
+    let mut evaluation = Evaluation::default();
+    // Outermost evaluated nodes, anything inside of them is replaced along
+    // with them
+    let mut pending: Vec<_> = source.roots().iter().rev().copied().collect();
+    while let Some(id) = pending.pop() {
+        let (root, constant) = match source.get(id) {
+            AST::Function(function) if function.stub.modifiers.contains(&Modifier::Compile) => continue,
+            AST::Variable {
+                variable,
+                value: Some(value),
+            } if variable.constant || variable.modifiers.contains(&Modifier::Compile) => {
+                (*value, compile_time(variable).then_some((id, variable.name)))
+            }
+            AST::Call { target, .. }
+                if resolution.value(*target).is_some_and(|declaration| match declaration {
+                    Declaration::Node(declaration) => {
+                        source.cast::<SpewFunction>(declaration).is_some_and(|function| evaluator.compile(function))
+                    }
+                    _ => false,
+                }) =>
+            {
+                (id, None)
+            }
+            node => {
+                pending.extend(node.children().into_iter().rev());
+                continue;
+            }
+        };
+        if !evaluator.typed(root) {
+            continue;
+        }
+        if let Some(found) = evaluator.root(root, constant) {
+            evaluation.values.insert(root, found);
+        }
+    }
+    evaluation.errors = evaluator.errors;
+    evaluation.errors.sort_by_key(|err| err.span().start);
+    evaluation.errors.dedup();
+    return evaluation;
+}
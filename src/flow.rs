@@ -0,0 +1,493 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
+
+use crate::ast::{ASTSource, DataType, Lambda, Literal, NodeId, Operation, Operator, Pattern, SpewFunction, AST};
+use crate::intern::Name;
+use crate::resolve::{Declaration, Resolution};
+use crate::source::Span;
+use crate::visit::{self, Visitor};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlowError {
+    // Return type of a body whose end can be reached
+    MissingReturn(Span, String),
+    // First statement that can't run, later ones aren't reported
+    Unreachable(Span),
+    // Variable read before any assignment
+    Uninitialized(Span, Name),
+    // Variable read when only some paths assigned it
+    MaybeUninitialized(Span, Name),
+    // `break` that isn't inside of a `while`
+    Break(Span),
+}
+
+impl FlowError {
+    pub fn span(&self) -> Span {
+        return match self {
+            FlowError::MissingReturn(span, _) => *span,
+            FlowError::Unreachable(span) => *span,
+            FlowError::Uninitialized(span, _) => *span,
+            FlowError::MaybeUninitialized(span, _) => *span,
+            FlowError::Break(span) => *span,
+        };
+    }
+}
+
+impl Display for FlowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlowError::MissingReturn(_, type_name) => {
+                write!(f, "missing `return`, the end of the body is reached without returning `{}`", type_name)
+            }
+            FlowError::Unreachable(_) => f.write_str("unreachable statement"),
+            FlowError::Uninitialized(_, name) => write!(f, "`{}` is used before it is assigned", name),
+            FlowError::MaybeUninitialized(_, name) => {
+                write!(f, "`{}` may be used before it is assigned, not every path assigns it", name)
+            }
+            FlowError::Break(_) => f.write_str("`break` outside of a loop"),
+        }
+    }
+}
+
+/// Straight line of nodes, control only enters at the top and leaves at the
+/// bottom
+#[derive(Debug, Clone, Default)]
+pub struct BasicBlock {
+    /// Nodes in the order they run, expressions after their operands.
+    /// Lambdas are a single node as their body runs when they are called.
+    pub nodes: Vec<NodeId>,
+    pub successors: Vec<usize>,
+}
+
+// Statement and the block it starts in, along with the block the statement
+// before it or around it started in
+#[derive(Debug, Clone, Copy)]
+struct Start {
+    id: NodeId,
+    block: usize,
+    previous: Option<usize>,
+}
+
+/// Control-flow graph of a function or lambda body. The first block is the
+/// entry and every `return` leads to `exit`.
+#[derive(Debug, Clone)]
+pub struct Graph {
+    pub blocks: Vec<BasicBlock>,
+    pub exit: usize,
+    /// Block control is in after the last statement, the body falls off its
+    /// end when it is reachable
+    pub end: usize,
+    starts: Vec<Start>,
+    // `break`s without a loop to leave
+    breaks: Vec<NodeId>,
+}
+
+impl Graph {
+    pub fn new(source: &ASTSource, body: &[NodeId]) -> Graph {
+        let mut builder = Builder {
+            source,
+            blocks: vec![BasicBlock::default(), BasicBlock::default()],
+            current: 0,
+            loops: Vec::new(),
+            starts: Vec::new(),
+            breaks: Vec::new(),
+        };
+        builder.statements(body, None);
+        return Graph {
+            end: builder.current,
+            blocks: builder.blocks,
+            exit: 1,
+            starts: builder.starts,
+            breaks: builder.breaks,
+        };
+    }
+
+    /// Per block whether it can be reached from the entry
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending = vec![0];
+        while let Some(block) = pending.pop() {
+            if !reachable[block] {
+                reachable[block] = true;
+                pending.extend(&self.blocks[block].successors);
+            }
+        }
+        return reachable;
+    }
+
+    /// Per block the blocks that lead to it
+    pub fn predecessors(&self) -> Vec<Vec<usize>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for (block, value) in self.blocks.iter().enumerate() {
+            for successor in &value.successors {
+                predecessors[*successor].push(block);
+            }
+        }
+        return predecessors;
+    }
+}
+
+struct Builder<'a> {
+    source: &'a ASTSource,
+    blocks: Vec<BasicBlock>,
+    current: usize,
+    // Blocks following the loops being built, innermost last
+    loops: Vec<usize>,
+    starts: Vec<Start>,
+    breaks: Vec<NodeId>,
+}
+
+impl<'a> Builder<'a> {
+    fn block(&mut self) -> usize {
+        self.blocks.push(BasicBlock::default());
+        return self.blocks.len() - 1;
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.blocks[from].successors.push(to);
+    }
+
+    fn push(&mut self, id: NodeId) {
+        let current = self.current;
+        self.blocks[current].nodes.push(id);
+    }
+
+    // Nothing follows a jump, statements after it go into a block nothing
+    // leads to
+    fn jump(&mut self, to: usize) {
+        self.edge(self.current, to);
+        self.current = self.block();
+    }
+
+    fn statements(&mut self, contents: &[NodeId], parent: Option<usize>) {
+        let mut previous = parent;
+        for statement in contents {
+            let start = self.current;
+            self.statement(*statement, previous);
+            previous = Some(start);
+        }
+    }
+
+    fn statement(&mut self, id: NodeId, previous: Option<usize>) {
+        let start = self.current;
+        self.starts.push(Start {
+            id,
+            block: start,
+            previous,
+        });
+        match self.source.get(id) {
+            AST::Variable { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(*value);
+                }
+                self.push(id);
+            }
+            AST::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(*value);
+                }
+                self.push(id);
+                self.jump(1);
+            }
+            AST::Break => {
+                self.push(id);
+                match self.loops.last() {
+                    Some(after) => self.jump(*after),
+                    None => self.breaks.push(id),
+                }
+            }
+            AST::ConditionBlock {
+                condition,
+                contents,
+                otherwise,
+            } => {
+                self.expression(*condition);
+                let branch = self.current;
+                self.current = self.block();
+                self.edge(branch, self.current);
+                self.statements(contents, Some(start));
+                let then_end = self.current;
+                self.current = self.block();
+                self.edge(branch, self.current);
+                if let Some(otherwise) = otherwise {
+                    self.statement(*otherwise, Some(start));
+                }
+                let join = self.block();
+                self.edge(then_end, join);
+                self.edge(self.current, join);
+                self.current = join;
+            }
+            AST::While { condition, contents } => {
+                let header = self.block();
+                self.edge(self.current, header);
+                self.current = header;
+                self.expression(*condition);
+                let test = self.current;
+                let after = self.block();
+                // `while true` only ends through a `break`
+                if !matches!(self.source.get(*condition), AST::Literal(Literal::Boolean(true))) {
+                    self.edge(test, after);
+                }
+                self.current = self.block();
+                self.edge(test, self.current);
+                self.loops.push(after);
+                self.statements(contents, Some(start));
+                self.loops.pop();
+                self.edge(self.current, header);
+                self.current = after;
+            }
+            AST::Block(contents) => self.statements(contents, Some(start)),
+            _ => self.expression(id),
+        }
+    }
+
+    fn expression(&mut self, id: NodeId) {
+        match self.source.get(id) {
+            // The body runs when the lambda is called and gets a graph of its own
+            AST::Lambda(_) => {}
+            AST::Operation(Operation { right: Some(_), .. }) => {
+                // Chains are followed in a loop as they can be arbitrarily long
+                let (first, operations) = self.source.chain(id);
+                self.expression(first);
+                for operation in operations {
+                    let AST::Operation(Operation { operator, right: Some(right), .. }) = self.source.get(operation) else {
+                        continue;
+                    };
+                    if !matches!(operator, Operator::AndAnd | Operator::OrOr | Operator::Coalesce) {
+                        self.expression(*right);
+                        continue;
+                    }
+                    // The right hand side only runs depending on the left
+                    let branch = self.current;
+                    self.current = self.block();
+                    self.edge(branch, self.current);
+                    self.expression(*right);
+                    let join = self.block();
+                    self.edge(branch, join);
+                    self.edge(self.current, join);
+                    self.current = join;
+                }
+            }
+            AST::Assign { target, value } => {
+                // A variable being assigned isn't read, the value a property
+                // is assigned on is
+                if !matches!(self.source.get(*target), AST::Identifier(_)) {
+                    self.expression(*target);
+                }
+                self.expression(*value);
+            }
+            AST::Match { value, arms } => {
+                self.expression(*value);
+                let branch = self.current;
+                let join = self.block();
+                for arm in arms {
+                    self.current = self.block();
+                    self.edge(branch, self.current);
+                    let body = self.source.get(*arm).body;
+                    match self.source.get(body) {
+                        AST::Block(contents) => self.statements(contents, Some(branch)),
+                        _ => self.expression(body),
+                    }
+                    self.edge(self.current, join);
+                }
+                // Values no arm matches go straight past the match
+                let catch_all = arms.iter()
+                    .any(|arm| matches!(self.source.get(*arm).pattern, Pattern::Wildcard | Pattern::Binding(_)));
+                if !catch_all {
+                    self.edge(branch, join);
+                }
+                self.current = join;
+            }
+            node => {
+                for child in node.children() {
+                    self.expression(child);
+                }
+            }
+        }
+        self.push(id);
+    }
+}
+
+// What is known to be assigned when a block is entered or left, `None` for
+// blocks nothing has flowed into yet
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Assigned {
+    // Assigned on every path
+    all: Option<HashSet<NodeId>>,
+    // Assigned on at least one path
+    any: HashSet<NodeId>,
+}
+
+struct Flow<'a> {
+    source: &'a ASTSource,
+    resolution: &'a Resolution,
+    errors: Vec<FlowError>,
+    // Assignments that give a variable declared without a value its first
+    // value, no path to them has assigned it yet
+    initializations: HashSet<NodeId>,
+}
+
+impl<'a> Flow<'a> {
+    // Body of the function or lambda `id`
+    fn body(&mut self, id: NodeId, body: &[NodeId], return_type: Option<&DataType>) {
+        let graph = Graph::new(self.source, body);
+        let reachable = graph.reachable();
+        for start in &graph.starts {
+            // Only the statement where reachable code stops is reported
+            if !reachable[start.block] && start.previous.is_none_or(|previous| reachable[previous]) {
+                self.errors.push(FlowError::Unreachable(self.source.span(start.id)));
+            }
+        }
+        for id in &graph.breaks {
+            self.errors.push(FlowError::Break(self.source.span(*id)));
+        }
+        if let Some(return_type) = return_type {
+            let void = self.resolution.type_of(id) == Some(Declaration::Builtin(Name::intern("void")));
+            if !(void && return_type.union.is_empty()) && reachable[graph.end] {
+                self.errors.push(FlowError::MissingReturn(return_type.full_span(), return_type.to_string()));
+            }
+        }
+        self.assignments(&graph, &reachable);
+    }
+
+    // Variable declared without a value in this body that a node reads or
+    // assigns, and whether it assigns it
+    fn access(&self, id: NodeId, declared: &HashSet<NodeId>) -> Option<(NodeId, bool)> {
+        let (target, assigns) = match self.source.get(id) {
+            AST::Identifier(_) => (id, false),
+            AST::Assign { target, .. } => (*target, true),
+            _ => return None,
+        };
+        return match self.resolution.value(target) {
+            Some(Declaration::Node(variable)) if declared.contains(&variable) => Some((variable, assigns)),
+            _ => None,
+        };
+    }
+
+    // Runs the nodes of a block over what is assigned when it is entered,
+    // reporting reads of what may not be assigned yet and recording
+    // initializations when `report` is set
+    fn transfer(&mut self, nodes: &[NodeId], declared: &HashSet<NodeId>, state: &mut Assigned, report: bool) {
+        for id in nodes {
+            if declared.contains(id) {
+                // Declared again by the next pass through a loop
+                if let Some(all) = &mut state.all {
+                    all.remove(id);
+                }
+                state.any.remove(id);
+                continue;
+            }
+            match self.access(*id, declared) {
+                Some((variable, true)) => {
+                    if report && !state.any.contains(&variable) {
+                        self.initializations.insert(*id);
+                    }
+                    if let Some(all) = &mut state.all {
+                        all.insert(variable);
+                    }
+                    state.any.insert(variable);
+                }
+                Some((variable, false)) if report => {
+                    let assigned = state.all.as_ref().is_some_and(|all| all.contains(&variable));
+                    let AST::Variable { variable: value, .. } = self.source.get(variable) else { continue };
+                    let span = self.source.span(*id);
+                    if !assigned && state.any.contains(&variable) {
+                        self.errors.push(FlowError::MaybeUninitialized(span, value.name));
+                    } else if !assigned {
+                        self.errors.push(FlowError::Uninitialized(span, value.name));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Reports reads of variables declared without a value that not every
+    // path to them assigns
+    fn assignments(&mut self, graph: &Graph, reachable: &[bool]) {
+        let declared: HashSet<NodeId> = graph.blocks.iter()
+            .flat_map(|block| &block.nodes)
+            .filter(|id| matches!(self.source.get(**id), AST::Variable { value: None, .. }))
+            .copied()
+            .collect();
+        if declared.is_empty() {
+            return;
+        }
+        let predecessors = graph.predecessors();
+        let mut entered = vec![Assigned::default(); graph.blocks.len()];
+        let mut left = vec![Assigned::default(); graph.blocks.len()];
+        entered[0].all = Some(HashSet::new());
+        // Blocks only ever gain what may be assigned and lose what is
+        // assigned for certain so this settles
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in (0..graph.blocks.len()).filter(|block| reachable[*block]) {
+                let mut state = entered[block].clone();
+                for predecessor in &predecessors[block] {
+                    let from = &left[*predecessor];
+                    state.any.extend(&from.any);
+                    state.all = match (state.all, &from.all) {
+                        (Some(all), Some(other)) => Some(all.intersection(other).copied().collect()),
+                        (all, None) => all,
+                        (None, Some(other)) => Some(other.clone()),
+                    };
+                }
+                entered[block] = state.clone();
+                self.transfer(&graph.blocks[block].nodes, &declared, &mut state, false);
+                if state != left[block] {
+                    left[block] = state;
+                    changed = true;
+                }
+            }
+        }
+        for block in (0..graph.blocks.len()).filter(|block| reachable[*block]) {
+            let mut state = entered[block].clone();
+            self.transfer(&graph.blocks[block].nodes, &declared, &mut state, true);
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Flow<'a> {
+    fn visit_function(&mut self, source: &'a ASTSource, id: NodeId<SpewFunction>, value: &'a SpewFunction) {
+        self.body(id.untyped(), &value.body, value.stub.return_type.as_ref());
+        visit::walk_function(self, source, id, value);
+    }
+
+    fn visit_lambda(&mut self, source: &'a ASTSource, id: NodeId<Lambda>, value: &'a Lambda) {
+        self.body(id.untyped(), &value.body, value.return_type.as_ref());
+        visit::walk_lambda(self, source, id, value);
+    }
+}
+
+/// Follows control through every function and lambda body, reporting bodies
+/// that can end without returning their value, statements that can never
+/// run, `break`s outside of loops and variables read before they are
+/// assigned. Errors are sorted by position.
+pub fn flow(source: &ASTSource, resolution: &Resolution) -> Vec<FlowError> {
+    let mut flow = Flow {
+        source,
+        resolution,
+        errors: Vec::new(),
+        initializations: HashSet::new(),
+    };
+    flow.visit_source(source);
+    let mut errors = flow.errors;
+    errors.sort_by_key(|err| err.span().start);
+    return errors;
+}
+
+/// Assignments to variables declared without a value in the same body that
+/// no path to them has assigned yet, the only ones that may write to a `let`
+/// that isn't `mut`
+pub fn initializations(source: &ASTSource, resolution: &Resolution) -> HashSet<NodeId> {
+    let mut flow = Flow {
+        source,
+        resolution,
+        errors: Vec::new(),
+        initializations: HashSet::new(),
+    };
+    flow.visit_source(source);
+    return flow.initializations;
+}
@@ -0,0 +1,554 @@
+use std::fmt;
+use std::fmt::Display;
+
+use crate::lexer::{KeywordType, LexResult, Lexer, Modifier, Symbol, Token, TokenKind};
+
+/// Default maximum width of a formatted line
+pub const DEFAULT_LINE_WIDTH: usize = 100;
+
+const INDENT: &str = "  ";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatConfig {
+    pub line_width: usize,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        return FormatConfig {
+            line_width: DEFAULT_LINE_WIDTH,
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ConfigError {
+    // Line that isn't a `key = value` pair, lines are one based
+    Syntax(usize),
+    UnknownKey(usize, String),
+    InvalidValue(usize, String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Syntax(line) => write!(f, "line {}: expected `key = value`", line),
+            ConfigError::UnknownKey(line, key) => write!(f, "line {}: unknown option `{}`", line, key),
+            ConfigError::InvalidValue(line, key) => write!(f, "line {}: invalid value for `{}`", line, key),
+        }
+    }
+}
+
+impl FormatConfig {
+    /// Name of the config file looked for in the working directory
+    pub const FILE_NAME: &'static str = ".spewfmt";
+
+    /// Reads a config made of `key = value` lines, `#` starts a comment.
+    /// Options that aren't mentioned keep their defaults.
+    pub fn parse(text: &str) -> Result<FormatConfig, ConfigError> {
+        let mut config = FormatConfig::default();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(ConfigError::Syntax(line_number)),
+            };
+            match key {
+                "line_width" => {
+                    config.line_width = match value.parse() {
+                        Ok(width) if width > 0 => width,
+                        _ => return Err(ConfigError::InvalidValue(line_number, key.to_string())),
+                    };
+                }
+                _ => return Err(ConfigError::UnknownKey(line_number, key.to_string())),
+            }
+        }
+        return Ok(config);
+    }
+}
+
+/// Kind of body a curly brace opens, decides where items get split onto
+/// their own lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Body {
+    Top,
+    Struct,
+    Trait,
+    Impl,
+    Block,
+    // Arms of a match, one per line
+    Match,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Piece<'a> {
+    token: Token<'a>,
+    // Source text of the token, comments and literals are kept verbatim
+    text: &'a str,
+    // Line breaks in the source between this and the previous token
+    newlines: usize,
+}
+
+impl Piece<'_> {
+    fn is(&self, symbol: Symbol) -> bool {
+        return matches!(self.token, Token::Symbol(value) if value == symbol);
+    }
+
+    fn is_keyword(&self, keyword: KeywordType) -> bool {
+        return matches!(self.token, Token::Keyword(value) if value == keyword);
+    }
+
+    fn is_line_comment(&self) -> bool {
+        return matches!(self.token, Token::Comment(_)) && self.text.starts_with("//");
+    }
+
+    fn is_comment(&self) -> bool {
+        return matches!(self.token, Token::Comment(_));
+    }
+
+    fn is_open(&self) -> bool {
+        return self.is(Symbol::OpenParen) || self.is(Symbol::OpenSquare);
+    }
+
+    fn is_close(&self) -> bool {
+        return self.is(Symbol::CloseParen) || self.is(Symbol::CloseSquare);
+    }
+
+    // Tokens that can end an expression or declaration
+    fn ends_value(&self) -> bool {
+        return matches!(self.token, Token::Ident(_) | Token::Literal(_))
+            || self.is(Symbol::CloseParen)
+            || self.is(Symbol::CloseSquare)
+            || self.is(Symbol::CloseCurly)
+            || self.is(Symbol::Question)
+            || self.is(Symbol::Underscore)
+            || self.is(Symbol::Semicolon);
+    }
+
+    fn is_modifier(&self) -> bool {
+        return matches!(self.token, Token::Keyword(KeywordType::Modifier(_)));
+    }
+
+    // Tokens that start a declaration in item bodies
+    fn starts_item(&self) -> bool {
+        return match self.token {
+            Token::Keyword(KeywordType::Modifier(modifier)) => modifier != Modifier::Mutable,
+            Token::Keyword(keyword) => matches!(
+                keyword,
+                KeywordType::Function
+                    | KeywordType::Let
+                    | KeywordType::Constant
+                    | KeywordType::Struct
+                    | KeywordType::Trait
+                    | KeywordType::Impl
+                    | KeywordType::Type
+                    | KeywordType::Import
+            ),
+            Token::Symbol(Symbol::At) => true,
+            _ => false,
+        };
+    }
+
+    // Keywords whose line the opening brace of their body stays on
+    fn is_header(&self) -> bool {
+        return self.is_modifier()
+            || self.is(Symbol::At)
+            || self.is(Symbol::CloseCurly)
+            || matches!(
+                self.token,
+                Token::Keyword(
+                    KeywordType::Function
+                        | KeywordType::Struct
+                        | KeywordType::Trait
+                        | KeywordType::Impl
+                        | KeywordType::If
+                        | KeywordType::Else
+                        | KeywordType::While
+                )
+            );
+    }
+
+    fn is_binary_operator(&self) -> bool {
+        return matches!(
+            self.token,
+            Token::Symbol(
+                Symbol::Plus
+                    | Symbol::Minus
+                    | Symbol::Multiply
+                    | Symbol::Divide
+                    | Symbol::Percent
+                    | Symbol::Left
+                    | Symbol::Right
+                    | Symbol::LeftEquals
+                    | Symbol::RightEquals
+                    | Symbol::EqualsEquals
+                    | Symbol::NotEquals
+                    | Symbol::And
+                    | Symbol::AndAnd
+                    | Symbol::Pipe
+                    | Symbol::OrOr
+                    | Symbol::Caret
+                    | Symbol::Equals
+                    | Symbol::Arrow
+                    | Symbol::QuestionQuestion
+                    | Symbol::FatArrow
+            )
+        );
+    }
+}
+
+#[derive(Debug)]
+struct Line<'a> {
+    indent: usize,
+    blank_before: bool,
+    pieces: Vec<Piece<'a>>,
+}
+
+/// Formats spew source. Only whitespace and trailing commas change so the
+/// result parses to the same tree as the input, and formatting the result
+/// again gives back the same text.
+pub fn format(text: &str, config: &FormatConfig) -> LexResult<String> {
+    let pieces = pieces(text)?;
+    let mut out = String::new();
+    for line in lines(&pieces) {
+        if line.blank_before && !out.is_empty() {
+            out.push('\n');
+        }
+        render(&line.pieces, line.indent, config.line_width, &mut out);
+    }
+    return Ok(out);
+}
+
+fn pieces(text: &str) -> LexResult<Vec<Piece<'_>>> {
+    let mut pieces = Vec::new();
+    let mut end = 0;
+    for token in Lexer::new(text) {
+        let token = token?;
+        let newlines = text[end..token.span.start].matches('\n').count();
+        let source = &text[token.span.start..token.span.end];
+        pieces.push(Piece {
+            token: token.value,
+            text: if token.value.is(TokenKind::Comment) {
+                source.trim_end()
+            } else {
+                source
+            },
+            newlines,
+        });
+        end = token.span.end;
+    }
+    return Ok(pieces);
+}
+
+// Splits the tokens into lines and works out the indentation of each
+fn lines<'a>(pieces: &[Piece<'a>]) -> Vec<Line<'a>> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut current = Line {
+        indent: 0,
+        blank_before: false,
+        pieces: Vec::new(),
+    };
+    let mut bodies = vec![Body::Top];
+    // Parentheses that are open, line breaks inside of them are dropped
+    let mut groups = 0;
+    // Parentheses that were open around each body, bodies such as lambdas
+    // passed as arguments are laid out as if they weren't inside of them
+    let mut outer_groups = Vec::new();
+    for (index, piece) in pieces.iter().enumerate() {
+        let body = *bodies.last().unwrap();
+        let mut following = pieces[index + 1..].iter().filter(|piece| !piece.is_comment());
+        let next = following.next();
+        let after_next = following.next();
+        // Block comments are looked through so code following `{ /* note */`
+        // still goes on its own line
+        let previous = match current.pieces.last() {
+            Some(last) if last.is_comment() && !last.is_line_comment() => {
+                current.pieces.iter().rev().find(|piece| !piece.is_comment()).or(Some(last))
+            }
+            last => last,
+        };
+        if let Some(previous) = previous {
+            if breaks_before(previous, piece, [next, after_next], body, groups, &current) {
+                // Source blank lines are kept, and items following a body at
+                // the top level or in an impl are always separated by one
+                let separated = matches!(body, Body::Top | Body::Impl)
+                    && previous.is(Symbol::CloseCurly)
+                    && piece.starts_item();
+                let blank = separated
+                    || (piece.newlines > 1 && !previous.is(Symbol::OpenCurly) && !piece.is(Symbol::CloseCurly));
+                let finished = std::mem::replace(
+                    &mut current,
+                    Line {
+                        indent: 0,
+                        blank_before: blank,
+                        pieces: Vec::new(),
+                    },
+                );
+                lines.push(finished);
+            }
+        }
+
+        // The braces of an import list its names rather than opening a body
+        let import = current.pieces.first().is_some_and(|first| first.is_keyword(KeywordType::Import));
+        if import && (piece.is(Symbol::OpenCurly) || piece.is(Symbol::CloseCurly)) {
+            current.pieces.push(*piece);
+            continue;
+        }
+        if piece.is(Symbol::CloseCurly) && bodies.len() > 1 {
+            bodies.pop();
+        }
+        if current.pieces.is_empty() {
+            current.indent = bodies.len() - 1 + groups;
+            if continues(lines.last(), piece, groups) {
+                current.indent += 1;
+            }
+        }
+        if piece.is(Symbol::OpenCurly) {
+            // The last keyword decides as matches can be nested on one line,
+            // the block of an arm is opened right after its `=>`
+            let keyword = current.pieces.iter().rev().find_map(|piece| match piece.token {
+                Token::Keyword(KeywordType::Struct) => Some(Body::Struct),
+                Token::Keyword(KeywordType::Trait) => Some(Body::Trait),
+                Token::Keyword(KeywordType::Impl) => Some(Body::Impl),
+                Token::Keyword(KeywordType::Match) => Some(Body::Match),
+                _ => None,
+            });
+            let arm = current.pieces.last().is_some_and(|last| last.is(Symbol::FatArrow));
+            bodies.push(keyword.filter(|_| !arm).unwrap_or(Body::Block));
+            outer_groups.push(std::mem::take(&mut groups));
+        } else if piece.is(Symbol::CloseCurly) && !outer_groups.is_empty() {
+            groups = outer_groups.pop().unwrap();
+        } else if piece.is_open() {
+            groups += 1;
+        } else if piece.is_close() && groups > 0 {
+            groups -= 1;
+        }
+        current.pieces.push(*piece);
+    }
+    if !current.pieces.is_empty() {
+        lines.push(current);
+    }
+    return lines;
+}
+
+fn breaks_before(
+    previous: &Piece,
+    piece: &Piece,
+    // The two tokens after the piece skipping comments
+    next: [Option<&Piece>; 2],
+    body: Body,
+    groups: usize,
+    line: &Line,
+) -> bool {
+    if previous.is_line_comment() {
+        return true;
+    }
+    if piece.is_comment() {
+        // Comments stay where they were relative to the code
+        return piece.newlines > 0;
+    }
+    if line.pieces.first().is_some_and(|first| first.is_keyword(KeywordType::Import)) {
+        // Imports take one line ending with their path
+        return matches!(previous.token, Token::Literal(_));
+    }
+    if previous.is(Symbol::OpenCurly) {
+        return !piece.is(Symbol::CloseCurly);
+    }
+    if piece.is(Symbol::CloseCurly) {
+        return true;
+    }
+    if piece.is_keyword(KeywordType::Else) && previous.is(Symbol::CloseCurly) {
+        return false;
+    }
+    if piece.is(Symbol::OpenCurly) {
+        // Braces go on the line of the declaration or statement they belong to
+        let header = line.pieces.first().is_some_and(|first| first.is_header());
+        return piece.newlines > 0 && !(header && (previous.ends_value() || previous.is_keyword(KeywordType::Else)));
+    }
+    if previous.is(Symbol::CloseCurly) {
+        return !(piece.is_close() || piece.is(Symbol::Comma) || piece.is(Symbol::Semicolon) || piece.is(Symbol::Period));
+    }
+    if groups > 0 {
+        return false;
+    }
+    if previous.is(Symbol::Semicolon) || (body == Body::Match && previous.is(Symbol::Comma)) {
+        return true;
+    }
+    let item = match body {
+        // One property per line with their annotations above them
+        Body::Struct => {
+            let property = match next {
+                [Some(next), _] if next.is(Symbol::Colon) => true,
+                [Some(next), Some(after)] => next.is(Symbol::Question) && after.is(Symbol::Colon),
+                _ => false,
+            };
+            piece.is(Symbol::At) || piece.is_modifier() || (matches!(piece.token, Token::Ident(_)) && property)
+        }
+        Body::Top | Body::Trait | Body::Impl => piece.starts_item(),
+        Body::Block | Body::Match => false,
+    };
+    if item && previous.ends_value() {
+        return true;
+    }
+    return piece.newlines > 0;
+}
+
+// Lines that carry on the expression from the line before are indented once more
+fn continues(previous: Option<&Line>, piece: &Piece, groups: usize) -> bool {
+    if groups > 0 {
+        return false;
+    }
+    if piece.is(Symbol::Period) || (piece.is_binary_operator() && !piece.is(Symbol::Minus)) {
+        return true;
+    }
+    return previous
+        .and_then(|line| line.pieces.last())
+        .is_some_and(|last| last.is_binary_operator() || last.is(Symbol::Period) || last.is(Symbol::Colon));
+}
+
+fn spaced(previous: &Piece, piece: &Piece, unary: bool) -> bool {
+    if piece.is_comment() || previous.is_comment() {
+        return true;
+    }
+    if unary {
+        return false;
+    }
+    if piece.is_close()
+        || (piece.is(Symbol::CloseCurly) && previous.is(Symbol::OpenCurly))
+        || piece.is(Symbol::Comma)
+        || piece.is(Symbol::Semicolon)
+        || piece.is(Symbol::Period)
+        || piece.is(Symbol::Colon)
+        || piece.is(Symbol::ColonColon)
+        || piece.is(Symbol::Question)
+    {
+        return false;
+    }
+    if previous.is_open() || previous.is(Symbol::Period) || previous.is(Symbol::ColonColon) || previous.is(Symbol::At) {
+        return false;
+    }
+    if piece.is_open() {
+        // Calls and indexing hug their target
+        return !(matches!(previous.token, Token::Ident(_) | Token::Literal(_)) || previous.is_close());
+    }
+    return true;
+}
+
+// Minus after something that can't end a value negates rather than subtracts
+fn is_unary(previous: Option<&Piece>, piece: &Piece) -> bool {
+    if piece.is(Symbol::Exclamation) {
+        return true;
+    }
+    if !piece.is(Symbol::Minus) {
+        return false;
+    }
+    return !previous.is_some_and(|previous| previous.ends_value() && !previous.is(Symbol::Semicolon));
+}
+
+fn render_flat(pieces: &[Piece]) -> String {
+    let mut out = String::new();
+    let mut unary = false;
+    let mut previous: Option<&Piece> = None;
+    for (index, piece) in pieces.iter().enumerate() {
+        // Trailing commas are only kept when a list is wrapped
+        if piece.is(Symbol::Comma) && pieces.get(index + 1).is_some_and(|next| next.is_close()) {
+            continue;
+        }
+        if let Some(previous) = previous {
+            if spaced(previous, piece, unary) {
+                out.push(' ');
+            } else if unary && previous.is(Symbol::Minus) && piece.is(Symbol::Minus) {
+                // `--x` reads like a decrement
+                out.push(' ');
+            }
+        }
+        out.push_str(piece.text);
+        unary = is_unary(previous, piece);
+        previous = Some(piece);
+    }
+    return out;
+}
+
+fn width(indent: usize, text: &str) -> usize {
+    let first = text.lines().next().unwrap_or("");
+    return indent * INDENT.len() + first.chars().count();
+}
+
+// Finds the first argument list on the line that can be wrapped. Only
+// lists following a name or a call are taken, parentheses used for
+// grouping can't have a trailing comma.
+fn wrappable(pieces: &[Piece]) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let mut open = None;
+    for (index, piece) in pieces.iter().enumerate() {
+        if piece.is_open() {
+            if depth == 0 {
+                let list = index > 0
+                    && (matches!(pieces[index - 1].token, Token::Ident(_)) || pieces[index - 1].is_close());
+                open = if list { Some(index) } else { None };
+            }
+            depth += 1;
+        } else if piece.is_close() && depth > 0 {
+            depth -= 1;
+            if depth == 0 {
+                if let Some(open) = open.filter(|open| index > open + 1) {
+                    return Some((open, index));
+                }
+            }
+        }
+    }
+    return None;
+}
+
+/// Writes a line indented, lines that are too long have their first
+/// parenthesised list put one element per line and the rest of the line
+/// wrapped the same way
+fn render(pieces: &[Piece], indent: usize, line_width: usize, out: &mut String) {
+    let flat = render_flat(pieces);
+    let wrap = if width(indent, &flat) > line_width {
+        wrappable(pieces)
+    } else {
+        None
+    };
+    let (open, close) = match wrap {
+        Some(wrap) => wrap,
+        None => {
+            for _ in 0..indent {
+                out.push_str(INDENT);
+            }
+            out.push_str(&flat);
+            out.push('\n');
+            return;
+        }
+    };
+
+    render(&pieces[..=open], indent, line_width, out);
+    let mut depth = 0;
+    let mut element: Vec<Piece> = Vec::new();
+    for piece in &pieces[open + 1..close] {
+        if piece.is_open() {
+            depth += 1;
+        } else if piece.is_close() {
+            depth -= 1;
+        }
+        element.push(*piece);
+        if depth == 0 && piece.is(Symbol::Comma) {
+            render(&element, indent + 1, line_width, out);
+            element.clear();
+        }
+    }
+    if !element.is_empty() {
+        let comma = Piece {
+            token: Token::Symbol(Symbol::Comma),
+            text: ",",
+            newlines: 0,
+        };
+        element.push(comma);
+        render(&element, indent + 1, line_width, out);
+    }
+    render(&pieces[close..], indent, line_width, out);
+}
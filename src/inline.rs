@@ -0,0 +1,381 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{ASTSource, NodeId, Pattern, SpewFunction, Variable, AST};
+use crate::intern::Name;
+use crate::lexer::Modifier;
+use crate::resolve::{Declaration, Resolution};
+
+/// Functions whose body is a single `return` of at most this many nodes are
+/// inlined without being marked `inline`
+pub const SMALL_FUNCTION: usize = 8;
+
+// How the body of a function replaces a call
+enum Shape {
+    // Body is a single `return` of the expression, calls anywhere become it
+    Expression(NodeId),
+    // Body only returns at its end, calls used as a statement become a
+    // block of its statements
+    Statements,
+}
+
+// What a parameter is replaced with in the copied body
+enum Argument {
+    // The argument itself, a literal or a name whose value can't change
+    Copy(NodeId),
+    // Local the argument was evaluated into before the call
+    Local(Name),
+}
+
+// Lists of statements a node holds directly
+fn statements(value: &AST) -> Option<&Vec<NodeId>> {
+    return match value {
+        AST::Function(function) => Some(&function.body),
+        AST::Lambda(lambda) => Some(&lambda.body),
+        AST::ConditionBlock { contents, .. } | AST::While { contents, .. } | AST::Block(contents) => Some(contents),
+        _ => None,
+    };
+}
+
+fn statements_mut(value: &mut AST) -> Option<&mut Vec<NodeId>> {
+    return match value {
+        AST::Function(function) => Some(&mut function.body),
+        AST::Lambda(lambda) => Some(&mut lambda.body),
+        AST::ConditionBlock { contents, .. } | AST::While { contents, .. } | AST::Block(contents) => Some(contents),
+        _ => None,
+    };
+}
+
+// Every node below the given ones, lambdas included unless `lambdas` is off
+fn descendants(source: &ASTSource, ids: &[NodeId], lambdas: bool) -> Vec<NodeId> {
+    let mut found = Vec::new();
+    let mut pending = ids.to_vec();
+    while let Some(id) = pending.pop() {
+        found.push(id);
+        let node = source.get(id);
+        if lambdas || !matches!(node, AST::Lambda(_)) {
+            pending.extend(node.children());
+        }
+    }
+    return found;
+}
+
+// Names the nodes declare as variables, arguments or match bindings
+fn declared(source: &ASTSource, ids: &[NodeId]) -> HashSet<Name> {
+    return descendants(source, ids, true).into_iter()
+        .filter_map(|id| match source.get(id) {
+            AST::Variable { variable, .. } => Some(variable.name),
+            AST::Argument(argument) => Some(argument.name),
+            AST::Arm(arm) => match arm.pattern {
+                Pattern::Binding(name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+}
+
+// Statement a call is the first thing to run in, either the call on its own,
+// or the whole value of a `let`, `return` or assignment to a name. The
+// arguments of such calls can be evaluated into locals right before it.
+fn head(source: &ASTSource, call: NodeId) -> Option<NodeId> {
+    let parent = source.parent(call)?;
+    let statement = match source.get(parent) {
+        AST::Variable { value: Some(value), .. } | AST::Return(Some(value)) if *value == call => parent,
+        AST::Assign { target, value } if *value == call && matches!(source.get(*target), AST::Identifier(_)) => parent,
+        _ => call,
+    };
+    let holder = source.parent(statement)?;
+    return statements(source.get(holder))
+        .is_some_and(|contents| contents.contains(&statement))
+        .then_some(statement);
+}
+
+struct Inliner<'a> {
+    resolution: &'a Resolution,
+    // Functions that may be inlined
+    functions: HashSet<NodeId<SpewFunction>>,
+    // Copies mapped to the node they were copied from, as the resolution
+    // only knows the original nodes
+    origins: HashMap<NodeId, NodeId>,
+    // Every name in the source so the locals made for arguments are new
+    names: HashSet<Name>,
+    // Calls left to look at, copies of bodies add the calls they contain
+    pending: Vec<NodeId>,
+}
+
+impl<'a> Inliner<'a> {
+    fn origin(&self, id: NodeId) -> NodeId {
+        return self.origins.get(&id).copied().unwrap_or(id);
+    }
+
+    fn declaration(&self, id: NodeId) -> Option<Declaration> {
+        return self.resolution.value(self.origin(id));
+    }
+
+    fn callee(&self, source: &ASTSource, call: NodeId) -> Option<NodeId<SpewFunction>> {
+        let AST::Call { target, .. } = source.get(call) else { return None };
+        let Some(Declaration::Node(declaration)) = self.declaration(*target) else { return None };
+        // The body of a function of another file may use names that aren't
+        // visible where it is called
+        let file = source.file_of(source.span(call));
+        return source.cast::<SpewFunction>(declaration)
+            .filter(|function| self.functions.contains(function))
+            .filter(|function| source.file_of(source.span(function.untyped())) == file);
+    }
+
+    // How the body of a function can replace calls of it. Checked on every
+    // call as inlining into the body may have changed it.
+    fn shape(&self, source: &ASTSource, function: NodeId<SpewFunction>) -> Option<Shape> {
+        let value = source.get(function);
+        if let [statement] = value.body.as_slice() {
+            if let AST::Return(Some(value)) = source.get(*statement) {
+                return Some(Shape::Expression(*value));
+            }
+        }
+        // Statements can't leave early, lambdas return from themselves
+        let (last, rest) = value.body.split_last()?;
+        let last = match source.get(*last) {
+            AST::Return(value) => value.iter().copied().collect(),
+            _ => vec![*last],
+        };
+        let returns = descendants(source, rest, false).into_iter()
+            .chain(descendants(source, &last, false))
+            .any(|id| matches!(source.get(id), AST::Return(_)));
+        let inline = value.stub.modifiers.contains(&Modifier::Inline);
+        return (inline && !returns).then_some(Shape::Statements);
+    }
+
+    // Whether an argument can be copied into the body in place of its
+    // parameter, wherever and however often the body uses it
+    fn constant(&self, source: &ASTSource, id: NodeId) -> bool {
+        return match source.get(id) {
+            AST::Literal(_) => true,
+            AST::Identifier(_) => match self.declaration(id) {
+                Some(Declaration::Node(declaration)) => match source.get(declaration) {
+                    AST::Variable {
+                        variable,
+                        value: Some(_),
+                    } => !variable.modifiers.contains(&Modifier::Mutable),
+                    AST::Argument(_) | AST::Arm(_) | AST::Function(_) => true,
+                    _ => false,
+                },
+                Some(Declaration::Builtin(_)) => true,
+                _ => false,
+            },
+            _ => false,
+        };
+    }
+
+    // Whether the caller declares a name the body uses for something outside
+    // of it, which the copy would then refer to instead
+    fn shadowed(&self, source: &ASTSource, call: NodeId, body: &[NodeId]) -> bool {
+        let root = source.ancestors(call).last().unwrap_or(call);
+        let locals = declared(source, &source.get(root).children());
+        return descendants(source, body, true).into_iter().any(|id| match source.get(id) {
+            AST::Identifier(name) => {
+                let outside = match self.declaration(id) {
+                    Some(Declaration::Node(declaration)) => source.parent(declaration).is_none(),
+                    Some(_) => true,
+                    None => false,
+                };
+                outside && locals.contains(name)
+            }
+            _ => false,
+        });
+    }
+
+    fn fresh(&mut self, name: Name) -> Name {
+        let mut index = 0;
+        loop {
+            let candidate = Name::intern(&format!("{}_{}", name, index));
+            if self.names.insert(candidate) {
+                return candidate;
+            }
+            index += 1;
+        }
+    }
+
+    // Copies a subtree with parameters replaced by their arguments, returns
+    // the id of the copy
+    fn copy(&mut self, source: &mut ASTSource, id: NodeId, arguments: &HashMap<NodeId, Argument>) -> NodeId {
+        let parameter = match (source.get(id), self.declaration(id)) {
+            (AST::Identifier(_), Some(Declaration::Node(declaration))) => arguments.get(&declaration),
+            _ => None,
+        };
+        let value = match parameter {
+            Some(Argument::Copy(argument)) => return self.copy(source, *argument, &HashMap::new()),
+            Some(Argument::Local(name)) => AST::Identifier(*name),
+            None => {
+                let mut value = source.get(id).clone();
+                value.map_children(|child| self.copy(source, child, arguments));
+                value
+            }
+        };
+        let copy = source.alloc(value, source.span(id));
+        self.origins.insert(copy, self.origin(id));
+        if let AST::Call { .. } = source.get(copy) {
+            self.pending.push(copy);
+        }
+        return copy;
+    }
+
+    fn call(&mut self, source: &mut ASTSource, call: NodeId) {
+        let Some(function) = self.callee(source, call) else { return };
+        let Some(shape) = self.shape(source, function) else { return };
+        let AST::Call { arguments, .. } = source.get(call) else { return };
+        let arguments = arguments.clone();
+        let parameters = source.get(function).stub.arguments.clone();
+        let body = source.get(function).body.clone();
+        let head = head(source, call);
+        if parameters.len() != arguments.len()
+            || (matches!(shape, Shape::Statements) && head != Some(call))
+            || self.shadowed(source, call, &body)
+        {
+            return;
+        }
+
+        // Arguments keep running once and in order, those that could change
+        // or have effects are evaluated into locals unless the call is
+        // nested in an expression where they can't go
+        let inside = declared(source, &body);
+        let mut replacements = HashMap::new();
+        let mut locals = Vec::new();
+        for (parameter, argument) in parameters.iter().zip(&arguments) {
+            let captured = match source.get(*argument) {
+                AST::Identifier(name) => inside.contains(name),
+                _ => false,
+            };
+            if self.constant(source, *argument) && !captured {
+                replacements.insert(parameter.untyped(), Argument::Copy(*argument));
+                continue;
+            }
+            if head.is_none() {
+                return;
+            }
+            let name = self.fresh(source.get(*parameter).name);
+            locals.push((name, *argument));
+            replacements.insert(parameter.untyped(), Argument::Local(name));
+        }
+        // Calls moved into locals head their statement now
+        let calls = locals.iter()
+            .map(|(_, argument)| *argument)
+            .filter(|argument| matches!(source.get(*argument), AST::Call { .. }));
+        self.pending.extend(calls);
+        let locals: Vec<NodeId> = locals.into_iter()
+            .map(|(name, argument)| {
+                let variable = Variable {
+                    constant: false,
+                    name,
+                    modifiers: Vec::new(),
+                    type_of: None,
+                };
+                let span = source.span(argument);
+                source.alloc(AST::Variable { variable, value: Some(argument) }, span)
+            })
+            .collect();
+
+        match shape {
+            Shape::Expression(value) => {
+                let copy = self.copy(source, value, &replacements);
+                let value = source.get(copy).clone();
+                source.replace(call, value);
+                self.origins.insert(call, self.origin(copy));
+                if let (Some(statement), false) = (head, locals.is_empty()) {
+                    let holder = source.parent(statement).unwrap();
+                    let mut value = source.get(holder).clone();
+                    let contents = statements_mut(&mut value).unwrap();
+                    let index = contents.iter().position(|id| *id == statement).unwrap();
+                    contents.splice(index..index, locals);
+                    source.replace(holder, value);
+                }
+                if let AST::Call { .. } = source.get(call) {
+                    self.pending.push(call);
+                }
+            }
+            Shape::Statements => {
+                let mut contents = locals;
+                for statement in &body {
+                    match source.get(*statement) {
+                        AST::Return(Some(value)) => contents.push(self.copy(source, *value, &replacements)),
+                        AST::Return(None) => {}
+                        _ => contents.push(self.copy(source, *statement, &replacements)),
+                    }
+                }
+                source.replace(call, AST::Block(contents));
+            }
+        }
+    }
+}
+
+/// Replaces calls of `inline` functions with their bodies, and calls of
+/// functions that only return a small expression. Calls anywhere can be
+/// replaced when the body is a single `return`, other `inline` functions only
+/// when the call is a statement of its own. Functions that can reach
+/// themselves through calls are never inlined, nor are calls from another
+/// file than the function's, and the resolution has to be of the tree as it
+/// is passed in.
+pub fn inline(source: &mut ASTSource, resolution: &Resolution) {
+    let mut inliner = Inliner {
+        resolution,
+        functions: HashSet::new(),
+        origins: HashMap::new(),
+        names: HashSet::new(),
+        pending: Vec::new(),
+    };
+    for id in source.ids() {
+        let name = match source.get(id) {
+            AST::Identifier(name) => *name,
+            AST::Variable { variable, .. } => variable.name,
+            AST::Argument(argument) => argument.name,
+            _ => continue,
+        };
+        inliner.names.insert(name);
+    }
+
+    let mut candidates = Vec::new();
+    for root in source.roots() {
+        let Some(function) = source.cast::<SpewFunction>(*root) else { continue };
+        let value = source.get(function);
+        // Compile functions have already been evaluated
+        if value.stub.modifiers.contains(&Modifier::Compile) {
+            continue;
+        }
+        let small = match inliner.shape(source, function) {
+            Some(Shape::Expression(value)) => descendants(source, &[value], true).len() <= SMALL_FUNCTION,
+            _ => false,
+        };
+        if small || value.stub.modifiers.contains(&Modifier::Inline) {
+            candidates.push(function);
+        }
+    }
+    inliner.functions = candidates.iter().copied().collect();
+    // Candidates each candidate calls
+    let calls: HashMap<_, Vec<_>> = candidates.iter()
+        .map(|function| {
+            let body = &source.get(*function).body;
+            let called = descendants(source, body, true).into_iter()
+                .filter_map(|id| inliner.callee(source, id))
+                .collect();
+            (*function, called)
+        })
+        .collect();
+    for function in &candidates {
+        let mut seen = HashSet::new();
+        let mut pending = calls[function].clone();
+        while let Some(next) = pending.pop() {
+            if next == *function {
+                inliner.functions.remove(function);
+                break;
+            }
+            if seen.insert(next) {
+                pending.extend(&calls[&next]);
+            }
+        }
+    }
+
+    inliner.pending = source.ids().filter(|id| matches!(source.get(*id), AST::Call { .. })).collect();
+    inliner.pending.reverse();
+    while let Some(call) = inliner.pending.pop() {
+        inliner.call(source, call);
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Display};
+use std::hash::{BuildHasherDefault, Hasher};
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref INTERNER: RwLock<Interner> = RwLock::new(Interner::default());
+}
+
+/// Interned identifier, cheap to copy and compare. Every occurrence of the
+/// same text maps to the same name for the lifetime of the process.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Name(u32);
+
+// Identifiers are short so the default SipHash dominates lookups, this is
+// the multiply-rotate hash used by rustc (FxHash)
+#[derive(Default)]
+struct NameHasher(u64);
+
+impl Hasher for NameHasher {
+    fn finish(&self) -> u64 {
+        return self.0;
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.write_u64(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for byte in chunks.remainder() {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+}
+
+#[derive(Default)]
+struct Interner {
+    names: HashMap<&'static str, Name, BuildHasherDefault<NameHasher>>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn intern(&mut self, text: &str) -> Name {
+        if let Some(name) = self.names.get(text) {
+            return *name;
+        }
+        // Interned strings live until the process exits so they can be
+        // handed out as 'static without holding the lock
+        let text: &'static str = Box::leak(Box::from(text));
+        let name = Name(self.strings.len() as u32);
+        self.strings.push(text);
+        self.names.insert(text, name);
+        return name;
+    }
+}
+
+impl Name {
+    pub fn intern(text: &str) -> Name {
+        if let Some(name) = INTERNER.read().unwrap().names.get(text) {
+            return *name;
+        }
+        return INTERNER.write().unwrap().intern(text);
+    }
+
+    /// Resolves the name back to its text
+    pub fn as_str(&self) -> &'static str {
+        return INTERNER.read().unwrap().strings[self.0 as usize];
+    }
+
+    /// Index of the name in the interner, stable for the process lifetime
+    pub fn index(&self) -> u32 {
+        return self.0;
+    }
+}
+
+impl From<&str> for Name {
+    fn from(value: &str) -> Name {
+        return Name::intern(value);
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        return self.as_str() == other;
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        return self.as_str() == *other;
+    }
+}
+
+impl Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,572 @@
+use std::fmt::Write;
+
+use crate::ast::{ASTSource, Annotation, DataType, FunctionStub, ImportNames, Literal, NodeId, Pattern, AST};
+use crate::lexer::{LexResult, Lexer, Modifier, Token};
+use crate::source::Span;
+
+/// Version of the JSON format, bumped whenever a change could break readers.
+/// The format is described by `schema/spew.schema.json`.
+pub const JSON_VERSION: u32 = 12;
+
+/// Minimal JSON writer that keeps track of where commas are needed
+struct JsonWriter {
+    out: String,
+    // Per open object or array whether an entry has been written yet
+    filled: Vec<bool>,
+    // A key was just written so the next value belongs to it
+    after_key: bool,
+}
+
+impl JsonWriter {
+    fn new() -> JsonWriter {
+        return JsonWriter {
+            out: String::new(),
+            filled: Vec::new(),
+            after_key: false,
+        };
+    }
+
+    fn separate(&mut self) {
+        if self.after_key {
+            self.after_key = false;
+            return;
+        }
+        if let Some(filled) = self.filled.last_mut() {
+            if *filled {
+                self.out.push(',');
+            }
+            *filled = true;
+        }
+    }
+
+    fn begin_object(&mut self) {
+        self.separate();
+        self.out.push('{');
+        self.filled.push(false);
+    }
+
+    fn end_object(&mut self) {
+        self.filled.pop();
+        self.out.push('}');
+    }
+
+    fn begin_array(&mut self) {
+        self.separate();
+        self.out.push('[');
+        self.filled.push(false);
+    }
+
+    fn end_array(&mut self) {
+        self.filled.pop();
+        self.out.push(']');
+    }
+
+    fn key(&mut self, key: &str) {
+        self.string(key);
+        self.out.push(':');
+        self.after_key = true;
+    }
+
+    fn string(&mut self, value: &str) {
+        self.separate();
+        self.out.push('"');
+        for char in value.chars() {
+            match char {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                char if (char as u32) < 0x20 => {
+                    let _ = write!(self.out, "\\u{:04x}", char as u32);
+                }
+                char => self.out.push(char),
+            }
+        }
+        self.out.push('"');
+    }
+
+    fn number(&mut self, value: usize) {
+        self.separate();
+        let _ = write!(self.out, "{}", value);
+    }
+
+    fn boolean(&mut self, value: bool) {
+        self.separate();
+        self.out.push_str(if value { "true" } else { "false" });
+    }
+
+    fn null(&mut self) {
+        self.separate();
+        self.out.push_str("null");
+    }
+
+    fn span(&mut self, span: Span) {
+        self.begin_object();
+        self.key("start");
+        self.number(span.start);
+        self.key("end");
+        self.number(span.end);
+        self.end_object();
+    }
+
+    fn id<T>(&mut self, id: NodeId<T>) {
+        self.number(id.index());
+    }
+
+    fn optional_id<T>(&mut self, id: Option<NodeId<T>>) {
+        match id {
+            Some(id) => self.id(id),
+            None => self.null(),
+        }
+    }
+
+    fn ids<T>(&mut self, ids: &[NodeId<T>]) {
+        self.begin_array();
+        for id in ids {
+            self.id(*id);
+        }
+        self.end_array();
+    }
+
+    fn modifiers(&mut self, modifiers: &[Modifier]) {
+        self.begin_array();
+        for modifier in modifiers {
+            self.string(modifier.as_str());
+        }
+        self.end_array();
+    }
+
+    // Literal values are kept as written, strings have their escapes and
+    // numbers their source text so no precision is lost
+    fn literal(&mut self, value: &Literal) {
+        self.begin_object();
+        self.key("type");
+        match value {
+            Literal::String(value) => {
+                self.string("string");
+                self.key("value");
+                self.string(value);
+            }
+            Literal::Number(value) => {
+                self.string("number");
+                self.key("value");
+                self.string(value);
+            }
+            Literal::Boolean(value) => {
+                self.string("boolean");
+                self.key("value");
+                self.boolean(*value);
+            }
+            Literal::Null => self.string("null"),
+            Literal::Undefined => self.string("ndef"),
+        }
+        self.end_object();
+    }
+
+    fn data_type(&mut self, value: &DataType) {
+        self.begin_object();
+        self.key("name");
+        self.string(value.name.as_str());
+        self.key("nullable");
+        self.boolean(value.nullable);
+        self.key("span");
+        self.span(value.span);
+        self.key("union");
+        self.begin_array();
+        for member in &value.union {
+            self.data_type(member);
+        }
+        self.end_array();
+        self.end_object();
+    }
+
+    fn optional_data_type(&mut self, value: &Option<DataType>) {
+        match value {
+            Some(value) => self.data_type(value),
+            None => self.null(),
+        }
+    }
+
+    fn annotations(&mut self, annotations: &[Annotation]) {
+        self.begin_array();
+        for annotation in annotations {
+            self.begin_object();
+            self.key("name");
+            self.string(annotation.name.as_str());
+            self.key("arguments");
+            self.begin_array();
+            for argument in &annotation.arguments {
+                self.begin_object();
+                self.key("name");
+                match argument.name {
+                    Some(name) => self.string(name.as_str()),
+                    None => self.null(),
+                }
+                self.key("value");
+                self.literal(&argument.value);
+                self.key("span");
+                self.span(argument.span);
+                self.end_object();
+            }
+            self.end_array();
+            self.key("span");
+            self.span(annotation.span);
+            self.end_object();
+        }
+        self.end_array();
+    }
+
+    // Fields shared by trait stubs and functions
+    fn signature(&mut self, stub: &FunctionStub) {
+        self.key("annotations");
+        self.annotations(&stub.annotations);
+        self.key("name");
+        self.string(stub.name.as_str());
+        self.key("modifiers");
+        self.modifiers(&stub.modifiers);
+        self.key("arguments");
+        self.ids(&stub.arguments);
+        self.key("return_type");
+        self.optional_data_type(&stub.return_type);
+    }
+
+    fn node(&mut self, source: &ASTSource, id: NodeId) {
+        self.begin_object();
+        self.key("id");
+        self.id(id);
+        self.key("kind");
+        self.string(node_kind(source.get(id)));
+        self.key("span");
+        self.span(source.span(id));
+        self.key("parent");
+        self.optional_id(source.parent(id));
+        match source.get(id) {
+            AST::Variable { variable, value } => {
+                self.key("constant");
+                self.boolean(variable.constant);
+                self.key("name");
+                self.string(variable.name.as_str());
+                self.key("modifiers");
+                self.modifiers(&variable.modifiers);
+                self.key("type");
+                self.optional_data_type(&variable.type_of);
+                self.key("value");
+                self.optional_id(*value);
+            }
+            AST::Literal(value) => {
+                self.key("literal");
+                self.literal(value);
+            }
+            AST::Identifier(name) => {
+                self.key("name");
+                self.string(name.as_str());
+            }
+            AST::Operation(operation) => {
+                // Unary operations have a null right hand side
+                self.key("operator");
+                self.string(operation.operator.as_str());
+                self.key("left");
+                self.id(operation.left);
+                self.key("right");
+                self.optional_id(operation.right);
+            }
+            AST::Call { target, arguments } => {
+                self.key("target");
+                self.id(*target);
+                self.key("arguments");
+                self.ids(arguments);
+            }
+            AST::Member { target, name } => {
+                self.key("target");
+                self.id(*target);
+                self.key("name");
+                self.string(name.as_str());
+            }
+            AST::Path { data_type, name } => {
+                self.key("type");
+                self.data_type(data_type);
+                self.key("name");
+                self.string(name.as_str());
+            }
+            AST::Is { value, data_type } => {
+                self.key("value");
+                self.id(*value);
+                self.key("type");
+                self.data_type(data_type);
+            }
+            AST::Assign { target, value } => {
+                self.key("target");
+                self.id(*target);
+                self.key("value");
+                self.id(*value);
+            }
+            AST::ConditionBlock {
+                condition,
+                contents,
+                otherwise,
+            } => {
+                self.key("condition");
+                self.id(*condition);
+                self.key("contents");
+                self.ids(contents);
+                self.key("otherwise");
+                self.optional_id(*otherwise);
+            }
+            AST::While { condition, contents } => {
+                self.key("condition");
+                self.id(*condition);
+                self.key("contents");
+                self.ids(contents);
+            }
+            AST::Break => {}
+            AST::Return(value) => {
+                self.key("value");
+                self.optional_id(*value);
+            }
+            AST::Block(contents) => {
+                self.key("contents");
+                self.ids(contents);
+            }
+            AST::Match { value, arms } => {
+                self.key("value");
+                self.id(*value);
+                self.key("arms");
+                self.ids(arms);
+            }
+            AST::Arm(value) => {
+                self.key("pattern");
+                self.begin_object();
+                self.key("type");
+                match &value.pattern {
+                    Pattern::Literal(literal) => {
+                        self.string("literal");
+                        self.key("literal");
+                        self.literal(literal);
+                    }
+                    Pattern::Wildcard => self.string("wildcard"),
+                    Pattern::Binding(name) => {
+                        self.string("binding");
+                        self.key("name");
+                        self.string(name.as_str());
+                    }
+                    Pattern::Type(data_type) => {
+                        self.string("is");
+                        self.key("data_type");
+                        self.data_type(data_type);
+                    }
+                }
+                self.end_object();
+                self.key("body");
+                self.id(value.body);
+            }
+            AST::Lambda(value) => {
+                self.key("arguments");
+                self.ids(&value.arguments);
+                self.key("return_type");
+                self.optional_data_type(&value.return_type);
+                self.key("body");
+                self.ids(&value.body);
+            }
+            AST::Struct(value) => {
+                self.key("annotations");
+                self.annotations(&value.annotations);
+                self.key("public");
+                self.boolean(value.public);
+                self.key("name");
+                self.string(value.name.as_str());
+                self.key("properties");
+                self.ids(&value.properties);
+            }
+            AST::Property(value) => {
+                self.key("annotations");
+                self.annotations(&value.annotations);
+                self.key("public");
+                self.boolean(value.public);
+                self.key("name");
+                self.string(value.name.as_str());
+                self.key("optional");
+                self.boolean(value.optional);
+                self.key("type");
+                self.data_type(&value.type_of);
+            }
+            AST::Trait(value) => {
+                self.key("annotations");
+                self.annotations(&value.annotations);
+                self.key("public");
+                self.boolean(value.public);
+                self.key("name");
+                self.string(value.name.as_str());
+                self.key("supertraits");
+                self.begin_array();
+                for supertrait in &value.supertraits {
+                    self.string(supertrait.as_str());
+                }
+                self.end_array();
+                self.key("functions");
+                self.ids(&value.functions);
+            }
+            AST::FunctionStub(value) => self.signature(value),
+            AST::Impl(value) => {
+                self.key("trait");
+                match value.trait_name {
+                    Some(name) => self.string(name.as_str()),
+                    None => self.null(),
+                }
+                self.key("name");
+                self.string(value.name.as_str());
+                self.key("constants");
+                self.ids(&value.constants);
+                self.key("functions");
+                self.ids(&value.functions);
+            }
+            AST::Function(value) => {
+                self.signature(&value.stub);
+                self.key("body");
+                self.ids(&value.body);
+            }
+            AST::Argument(value) => {
+                self.key("name");
+                self.string(value.name.as_str());
+                self.key("type");
+                self.optional_data_type(&value.data_type);
+            }
+            AST::TypeAlias(value) => {
+                self.key("public");
+                self.boolean(value.public);
+                self.key("name");
+                self.string(value.name.as_str());
+                self.key("types");
+                self.begin_array();
+                for data_type in &value.types {
+                    self.data_type(data_type);
+                }
+                self.end_array();
+                self.key("union");
+                self.boolean(value.union);
+            }
+            AST::Import(value) => {
+                // Null when every item is imported, a string for a namespace
+                // and otherwise the listed names
+                self.key("names");
+                match &value.names {
+                    ImportNames::All => self.null(),
+                    ImportNames::Namespace(name) => self.string(name.as_str()),
+                    ImportNames::Listed(names) => {
+                        self.begin_array();
+                        for name in names {
+                            self.begin_object();
+                            self.key("name");
+                            self.string(name.name.as_str());
+                            self.key("alias");
+                            match name.alias {
+                                Some(alias) => self.string(alias.as_str()),
+                                None => self.null(),
+                            }
+                            self.key("span");
+                            self.span(name.span);
+                            self.end_object();
+                        }
+                        self.end_array();
+                    }
+                }
+                self.key("path");
+                self.string(&value.path);
+            }
+        }
+        self.end_object();
+    }
+}
+
+/// Name used for the kind of a node in the JSON output
+pub fn node_kind(value: &AST) -> &'static str {
+    return match value {
+        AST::Variable { .. } => "variable",
+        AST::Literal(_) => "literal",
+        AST::Identifier(_) => "identifier",
+        AST::Operation(_) => "operation",
+        AST::Call { .. } => "call",
+        AST::Member { .. } => "member",
+        AST::Path { .. } => "path",
+        AST::Is { .. } => "is",
+        AST::Assign { .. } => "assign",
+        AST::ConditionBlock { .. } => "condition",
+        AST::While { .. } => "while",
+        AST::Break => "break",
+        AST::Return(_) => "return",
+        AST::Block(_) => "block",
+        AST::Match { .. } => "match",
+        AST::Arm(_) => "arm",
+        AST::Lambda(_) => "lambda",
+        AST::Struct(_) => "struct",
+        AST::Property(_) => "property",
+        AST::Trait(_) => "trait",
+        AST::FunctionStub(_) => "function_stub",
+        AST::Impl(_) => "impl",
+        AST::Function(_) => "function",
+        AST::Argument(_) => "argument",
+        AST::TypeAlias(_) => "type_alias",
+        AST::Import(_) => "import",
+    };
+}
+
+/// Serialises the tree as a flat list of nodes indexed by id, children are
+/// referenced by their id
+pub fn ast_to_json(source: &ASTSource) -> String {
+    let mut writer = JsonWriter::new();
+    writer.begin_object();
+    writer.key("version");
+    writer.number(JSON_VERSION as usize);
+    writer.key("roots");
+    writer.ids(source.roots());
+    writer.key("nodes");
+    writer.begin_array();
+    for id in source.ids() {
+        writer.node(source, id);
+    }
+    writer.end_array();
+    writer.end_object();
+    return writer.out;
+}
+
+/// Serialises every token of the text including comments
+pub fn tokens_to_json(text: &str) -> LexResult<String> {
+    let mut writer = JsonWriter::new();
+    writer.begin_object();
+    writer.key("version");
+    writer.number(JSON_VERSION as usize);
+    writer.key("tokens");
+    writer.begin_array();
+    for token in Lexer::new(text) {
+        let token = token?;
+        writer.begin_object();
+        writer.key("kind");
+        writer.string(match token.value {
+            Token::Comment(_) => "comment",
+            Token::Keyword(_) => "keyword",
+            Token::Ident(_) => "identifier",
+            Token::Symbol(_) => "symbol",
+            Token::Literal(_) => "literal",
+        });
+        writer.key("text");
+        writer.string(&text[token.span.start..token.span.end]);
+        match token.value {
+            // Identifiers are NFC normalised so may differ from the text
+            Token::Ident(name) => {
+                writer.key("name");
+                writer.string(name.as_str());
+            }
+            Token::Literal(literal) => {
+                writer.key("literal");
+                writer.literal(&Literal::from(&literal));
+            }
+            _ => {}
+        }
+        writer.key("span");
+        writer.span(token.span);
+        writer.end_object();
+    }
+    writer.end_array();
+    writer.end_object();
+    return Ok(writer.out);
+}
@@ -0,0 +1,642 @@
+use std::fmt;
+use std::fmt::{Debug, Display};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+use unicode_xid::UnicodeXID;
+use crate::intern::Name;
+use crate::source::{Span, Spanned};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeywordType {
+    Constant,
+    Let,
+    Function,
+    Struct,
+    Trait,
+    Impl,
+    For,
+    Return,
+    If,
+    Else,
+    Match,
+    Is,
+    Type,
+    While,
+    Break,
+    Import,
+    From,
+    As,
+    Modifier(Modifier),
+}
+
+impl KeywordType {
+    fn from(value: &str) -> Option<KeywordType> {
+        return match value {
+            "const" => Some(KeywordType::Constant),
+            "fun" => Some(KeywordType::Function),
+            "let" => Some(KeywordType::Let),
+            "trait" => Some(KeywordType::Trait),
+            "impl" => Some(KeywordType::Impl),
+            "struct" => Some(KeywordType::Struct),
+            "for" => Some(KeywordType::For),
+            "return" => Some(KeywordType::Return),
+            "if" => Some(KeywordType::If),
+            "else" => Some(KeywordType::Else),
+            "match" => Some(KeywordType::Match),
+            "is" => Some(KeywordType::Is),
+            "type" => Some(KeywordType::Type),
+            "while" => Some(KeywordType::While),
+            "break" => Some(KeywordType::Break),
+            "import" => Some(KeywordType::Import),
+            "from" => Some(KeywordType::From),
+            "as" => Some(KeywordType::As),
+            v => Modifier::from(v).map(KeywordType::Modifier),
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            KeywordType::Constant => "const",
+            KeywordType::Let => "let",
+            KeywordType::Function => "fun",
+            KeywordType::Struct => "struct",
+            KeywordType::Trait => "trait",
+            KeywordType::Impl => "impl",
+            KeywordType::For => "for",
+            KeywordType::Return => "return",
+            KeywordType::If => "if",
+            KeywordType::Else => "else",
+            KeywordType::Match => "match",
+            KeywordType::Is => "is",
+            KeywordType::Type => "type",
+            KeywordType::While => "while",
+            KeywordType::Break => "break",
+            KeywordType::Import => "import",
+            KeywordType::From => "from",
+            KeywordType::As => "as",
+            KeywordType::Modifier(modifier) => modifier.as_str(),
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Modifier {
+    Public,
+    Static,
+    Inline,
+    Compile,
+    Mutable,
+}
+
+impl Modifier {
+    fn from(value: &str) -> Option<Modifier> {
+        return match value {
+            "pub" => Some(Modifier::Public),
+            "mut" => Some(Modifier::Mutable),
+            "static" => Some(Modifier::Static),
+            "inline" => Some(Modifier::Inline),
+            "compile" => Some(Modifier::Compile),
+            _ => None,
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Modifier::Public => "pub",
+            Modifier::Mutable => "mut",
+            Modifier::Static => "static",
+            Modifier::Inline => "inline",
+            Modifier::Compile => "compile",
+        };
+    }
+}
+
+macro_rules! symbols {
+    (
+      $($name:ident: $value:literal),* $(,)?
+      ;
+      $($pair_name:ident: $first:literal $second:literal),* $(,)?
+    ) => {
+        #[derive(PartialEq, Clone, Copy)]
+        pub enum Symbol {
+          $($name,)*
+          $($pair_name,)*
+        }
+
+        impl Symbol {
+          fn from(value: char) -> Option<Symbol> {
+            return match value {
+              $(
+                $value => Some(Symbol::$name),
+              )*
+              _ => None,
+            }
+          }
+
+          // Symbols made of two chars, these take priority over the single
+          // char symbols they start with
+          fn from_pair(first: char, second: char) -> Option<Symbol> {
+            return match (first, second) {
+              $(
+                ($first, $second) => Some(Symbol::$pair_name),
+              )*
+              _ => None,
+            }
+          }
+        }
+
+        impl Debug for Symbol {
+          fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+              match self {
+                $(
+                  Symbol::$name => {
+                    write!(f, "{}",$value)?;
+                  },
+                )*
+                $(
+                  Symbol::$pair_name => {
+                    write!(f, "{}{}", $first, $second)?;
+                  },
+                )*
+              }
+              Ok(())
+          }
+        }
+    };
+}
+
+symbols! {
+  OpenCurly: '{',
+  CloseCurly: '}',
+  OpenParen: '(',
+  CloseParen: ')',
+  OpenSquare: '[',
+  CloseSquare: ']',
+  Plus: '+',
+  Minus: '-',
+  Left: '<',
+  Right: '>',
+  Underscore: '_',
+  Exclamation: '!',
+  Equals: '=',
+  And: '&',
+  Pipe: '|',
+  Caret: '^',
+  Period: '.',
+  Multiply: '*',
+  Percent: '%',
+  Divide: '/',
+  Colon: ':',
+  Semicolon: ';',
+  Comma: ',',
+  Question: '?',
+  At: '@',
+  ;
+  Arrow: '-' '>',
+  EqualsEquals: '=' '=',
+  NotEquals: '!' '=',
+  LeftEquals: '<' '=',
+  RightEquals: '>' '=',
+  AndAnd: '&' '&',
+  OrOr: '|' '|',
+  QuestionQuestion: '?' '?',
+  FatArrow: '=' '>',
+  ColonColon: ':' ':',
+}
+
+// String and number literals borrow their raw text from the source, string
+// escapes are left as written and the quote they were written with is kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Literal<'a> {
+    String(&'a str, char),
+    Number(&'a str),
+    Boolean(bool),
+    Null,
+    Undefined,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Token<'a> {
+    Comment(&'a str),
+    Keyword(KeywordType),
+    Ident(Name),
+    Symbol(Symbol),
+    Literal(Literal<'a>),
+}
+
+impl Debug for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Comment(comment) => {
+                write!(f, "Comment({})", comment)?;
+            }
+            Token::Keyword(keyword) => {
+                write!(f, "Keyword({:?})", keyword)?;
+            }
+            Token::Ident(ident) => {
+                write!(f, "Ident({})", ident)?;
+            }
+            Token::Symbol(symbol) => {
+                write!(f, "Symbol({:?})", symbol)?;
+            }
+            Token::Literal(literal) => {
+                write!(f, "Literal({:?})", literal)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Literal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::String(value, quote) => write!(f, "{}{}{}", quote, value, quote),
+            Literal::Number(value) => f.write_str(value),
+            Literal::Boolean(value) => write!(f, "{}", value),
+            Literal::Null => f.write_str("null"),
+            Literal::Undefined => f.write_str("ndef"),
+        }
+    }
+}
+
+// Source-like form of the token used in diagnostics
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Comment(_) => f.write_str("comment"),
+            Token::Keyword(keyword) => write!(f, "`{}`", keyword.as_str()),
+            Token::Ident(ident) => write!(f, "identifier `{}`", ident),
+            Token::Symbol(symbol) => write!(f, "`{:?}`", symbol),
+            Token::Literal(literal) => write!(f, "`{}`", literal),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub enum LexErrorKind {
+    UnexpectedToken(char, String),
+    Expected(String),
+    IncompleteLiteral(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct LexError {
+    // Byte offset the error occurred at
+    pub position: usize,
+    pub kind: LexErrorKind,
+}
+
+pub type LexResult<T> = Result<T, LexError>;
+
+/// Streaming lexer over the source text. Tokens are produced on demand and
+/// borrow their text from the source instead of copying it.
+#[derive(Debug, Clone)]
+pub struct Lexer<'a> {
+    text: &'a str,
+    // Byte offset of the next char to be read
+    position: usize,
+    // Added to every offset reported, files parsed into one arena each
+    // start where the one before them ended
+    offset: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(text: &'a str) -> Lexer<'a> {
+        return Lexer::with_offset(text, 0);
+    }
+
+    /// Lexer whose spans and errors start at `offset` instead of 0
+    pub fn with_offset(text: &'a str, offset: usize) -> Lexer<'a> {
+        return Lexer { text, position: 0, offset };
+    }
+
+    fn error(&self, kind: LexErrorKind) -> LexError {
+        LexError {
+            position: self.offset + self.position,
+            kind,
+        }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        return self.text[self.position..].chars().next();
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let char = self.peek_char()?;
+        self.position += char.len_utf8();
+        return Some(char);
+    }
+
+    fn take_while<P>(&mut self, mut predicate: P) -> &'a str
+        where
+            P: FnMut(char) -> bool,
+    {
+        let start = self.position;
+        while let Some(char) = self.peek_char() {
+            if !predicate(char) {
+                break;
+            }
+            self.position += char.len_utf8();
+        }
+        return &self.text[start..self.position];
+    }
+
+    fn consume_comment(&mut self) -> LexResult<Token<'a>> {
+        return match self.peek_char() {
+            Some('/') => {
+                // Full line comment
+                self.position += 1;
+                let comment_text = self.take_while(|char| char != '\n');
+                Ok(Token::Comment(comment_text.strip_suffix('\r').unwrap_or(comment_text)))
+            }
+            Some('*') => {
+                // Multi-line comment
+                self.position += 1;
+                let start = self.position;
+                match self.text[start..].find("*/") {
+                    Some(length) => {
+                        self.position = start + length + 2;
+                        Ok(Token::Comment(&self.text[start..start + length]))
+                    }
+                    None => {
+                        self.position = self.text.len();
+                        Err(self.error(LexErrorKind::IncompleteLiteral(String::from("*/ to close comment"))))
+                    }
+                }
+            }
+            // Not a comment just a plain division
+            _ => Ok(Token::Symbol(Symbol::Divide)),
+        };
+    }
+
+    fn consume_ident(&mut self, start: usize) -> Token<'a> {
+        self.take_while(|char| char.is_xid_continue());
+        let ident = &self.text[start..self.position];
+        if let Some(keyword) = KeywordType::from(ident) {
+            return Token::Keyword(keyword);
+        }
+        return match ident {
+            // A lone underscore is the wildcard symbol not an identifier
+            "_" => Token::Symbol(Symbol::Underscore),
+            "true" => Token::Literal(Literal::Boolean(true)),
+            "false" => Token::Literal(Literal::Boolean(false)),
+            "null" => Token::Literal(Literal::Null),
+            "ndef" => Token::Literal(Literal::Undefined),
+            // Identifiers are compared in NFC so that different encodings of the
+            // same visible name (e.g. precomposed vs combining accents) are equal
+            ident if is_nfc(ident) => Token::Ident(Name::intern(ident)),
+            ident => Token::Ident(Name::intern(&ident.nfc().collect::<String>())),
+        };
+    }
+
+    fn consume_number_literal(&mut self, start: usize) -> Token<'a> {
+        self.take_while(|char| char.is_ascii_digit());
+        // Only treat the period as a decimal point when a digit follows it
+        // so member access on a number still lexes as a period
+        let rest = &self.text.as_bytes()[self.position..];
+        if rest.len() > 1 && rest[0] == b'.' && rest[1].is_ascii_digit() {
+            self.position += 1;
+            self.take_while(|char| char.is_ascii_digit());
+        }
+        return Token::Literal(Literal::Number(&self.text[start..self.position]));
+    }
+
+    fn consume_string_literal(&mut self, open_char: char) -> LexResult<Token<'a>> {
+        let start = self.position;
+        while let Some(char) = self.next_char() {
+            if char == open_char {
+                let end = self.position - open_char.len_utf8();
+                return Ok(Token::Literal(Literal::String(&self.text[start..end], open_char)));
+            } else if char == '\\' {
+                // Skip whatever is escaped so an escaped quote doesn't end the string
+                self.next_char();
+            }
+        }
+        return Err(self.error(LexErrorKind::IncompleteLiteral(format!(
+            "{} to close string",
+            open_char
+        ))));
+    }
+
+    /// Produces the next token including comments, None once the end of the
+    /// text has been reached
+    pub fn next_token(&mut self) -> Option<LexResult<Spanned<Token<'a>>>> {
+        loop {
+            let start = self.position;
+            let next_char = self.next_char()?;
+            let token = if next_char.is_whitespace() {
+                // Consuming whitespace
+                self.take_while(|char| char.is_whitespace());
+                continue;
+            } else if next_char == '/' {
+                // Consume comments
+                self.consume_comment()
+            } else if next_char.is_xid_start() || next_char == '_' {
+                // Consume idents (UAX #31 with a leading underscore allowed)
+                Ok(self.consume_ident(start))
+            } else if next_char == '"' || next_char == '\'' {
+                self.consume_string_literal(next_char)
+            } else if next_char.is_ascii_digit() {
+                Ok(self.consume_number_literal(start))
+            } else if let Some(symbol) = Symbol::from(next_char) {
+                match self.peek_char().and_then(|second| Symbol::from_pair(next_char, second)) {
+                    Some(pair) => {
+                        self.position += 1;
+                        Ok(Token::Symbol(pair))
+                    }
+                    None => Ok(Token::Symbol(symbol)),
+                }
+            } else {
+                // Skipping it would lose source text, e.g. when formatting
+                return Some(Err(LexError {
+                    position: self.offset + start,
+                    kind: LexErrorKind::UnexpectedToken(next_char, String::from("not part of any token")),
+                }));
+            };
+            return Some(token.map(|token| Spanned {
+                value: token,
+                span: Span::new(self.offset + start, self.offset + self.position),
+            }));
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = LexResult<Spanned<Token<'a>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.next_token();
+    }
+}
+
+/// Kind of a token without its contents, used to ask the token set for
+/// a specific token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Comment,
+    Keyword(KeywordType),
+    Ident,
+    Symbol(Symbol),
+    Literal,
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Comment => f.write_str("comment"),
+            TokenKind::Keyword(keyword) => write!(f, "`{}`", keyword.as_str()),
+            TokenKind::Ident => f.write_str("identifier"),
+            TokenKind::Symbol(symbol) => write!(f, "`{:?}`", symbol),
+            TokenKind::Literal => f.write_str("literal"),
+        }
+    }
+}
+
+impl Token<'_> {
+    pub fn kind(&self) -> TokenKind {
+        return match self {
+            Token::Comment(_) => TokenKind::Comment,
+            Token::Keyword(keyword) => TokenKind::Keyword(*keyword),
+            Token::Ident(_) => TokenKind::Ident,
+            Token::Symbol(symbol) => TokenKind::Symbol(*symbol),
+            Token::Literal(_) => TokenKind::Literal,
+        };
+    }
+
+    pub fn is(&self, kind: TokenKind) -> bool {
+        return self.kind() == kind;
+    }
+}
+
+/// Position in a token set that can be returned to with TokenSet::rewind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Checkpoint(usize);
+
+/// Peekable stream of tokens pulled lazily from a lexer. Only significant
+/// tokens are visible through the cursor, comments are collected on the
+/// side so lookahead and rewinding never land on them.
+#[derive(Debug, Clone)]
+pub struct TokenSet<'a> {
+    lexer: Lexer<'a>,
+    tokens: Vec<Spanned<Token<'a>>>,
+    comments: Vec<Spanned<&'a str>>,
+    cursor: usize,
+    error: Option<LexError>,
+}
+
+impl<'a> TokenSet<'a> {
+    pub fn new(text: &'a str) -> TokenSet<'a> {
+        return TokenSet::with_offset(text, 0);
+    }
+
+    /// Token set whose spans start at `offset` instead of 0
+    pub fn with_offset(text: &'a str, offset: usize) -> TokenSet<'a> {
+        return TokenSet {
+            lexer: Lexer::with_offset(text, offset),
+            tokens: Vec::new(),
+            comments: Vec::new(),
+            cursor: 0,
+            error: None,
+        };
+    }
+
+    /// The first error produced by the lexer, the token stream ends
+    /// at the point the error occurred
+    pub fn error(&self) -> Option<&LexError> {
+        return self.error.as_ref();
+    }
+
+    /// Comments that have been lexed so far
+    pub fn comments(&self) -> &[Spanned<&'a str>] {
+        return &self.comments;
+    }
+
+    // Ensures the token at the index has been lexed returning whether it exists
+    fn fill(&mut self, index: usize) -> bool {
+        while self.tokens.len() <= index {
+            if self.error.is_some() {
+                return false;
+            }
+            match self.lexer.next_token() {
+                Some(Ok(Spanned { value: Token::Comment(comment), span })) => {
+                    self.comments.push(Spanned { value: comment, span });
+                }
+                Some(Ok(token)) => self.tokens.push(token),
+                Some(Err(err)) => {
+                    self.error = Some(err);
+                    return false;
+                }
+                None => return false,
+            }
+        }
+        return true;
+    }
+
+    /// Looks at the token `amount` tokens ahead of the cursor without
+    /// consuming anything, peek(0) is the token next_token would return
+    pub fn peek(&mut self, amount: usize) -> Option<Token<'a>> {
+        let index = self.cursor + amount;
+        if self.fill(index) {
+            return Some(self.tokens[index].value);
+        }
+        return None;
+    }
+
+    /// The span of the token `amount` tokens ahead, or an empty span at the
+    /// end of the input when there is no such token
+    pub fn peek_span(&mut self, amount: usize) -> Span {
+        let index = self.cursor + amount;
+        if self.fill(index) {
+            return self.tokens[index].span;
+        }
+        return self.end_span();
+    }
+
+    fn end_span(&self) -> Span {
+        let end = self.lexer.offset + self.lexer.position;
+        return Span::new(end, end);
+    }
+
+    /// The span of the token most recently returned by next_token
+    pub fn span(&self) -> Span {
+        return match self.cursor.checked_sub(1) {
+            Some(index) => self.tokens[index].span,
+            None => Span::new(self.lexer.offset, self.lexer.offset),
+        };
+    }
+
+    /// Whether a line ends between the token most recently returned by
+    /// next_token and the next one, the end of the input counts as one
+    pub fn line_break(&mut self) -> bool {
+        let end = self.span().end - self.lexer.offset;
+        let next = self.peek_span(0).start - self.lexer.offset;
+        return next == self.lexer.text.len() || self.lexer.text[end..next].contains('\n');
+    }
+
+    pub fn next_token(&mut self) -> Option<Token<'a>> {
+        let token = self.peek(0)?;
+        self.cursor += 1;
+        return Some(token);
+    }
+
+    /// Consumes the next token only if it is of the provided kind. When it
+    /// isn't the token is left in place and returned as the error, None
+    /// meaning the input ended.
+    pub fn expect(&mut self, kind: TokenKind) -> Result<Token<'a>, Option<Token<'a>>> {
+        return match self.peek(0) {
+            Some(token) if token.is(kind) => {
+                self.cursor += 1;
+                Ok(token)
+            }
+            token => Err(token),
+        };
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        return Checkpoint(self.cursor);
+    }
+
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.cursor = checkpoint.0;
+    }
+}
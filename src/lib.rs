@@ -0,0 +1,21 @@
+#![allow(clippy::needless_return, clippy::upper_case_acronyms)]
+
+pub mod annotation;
+pub mod ast;
+pub mod check;
+pub mod conform;
+pub mod eval;
+pub mod flow;
+pub mod format;
+pub mod inline;
+pub mod intern;
+pub mod json;
+pub mod lexer;
+pub mod load;
+pub mod mutability;
+pub mod printer;
+pub mod resolve;
+pub mod source;
+pub mod types;
+pub mod visibility;
+pub mod visit;
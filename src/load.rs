@@ -0,0 +1,131 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fmt::Display;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::ast::{ASTError, ASTSource, Import, ParseOptions};
+use crate::source::{Encoding, LineIndex, Position, Span};
+
+/// Extension of the file an import refers to
+pub const EXTENSION: &str = "spew";
+
+#[derive(Debug, Clone)]
+pub enum LoadError {
+    // Import followed by the path of the file it refers to and why it
+    // couldn't be read
+    Read(Span, String, String),
+    Parse(ASTError),
+}
+
+impl LoadError {
+    pub fn span(&self) -> Span {
+        return match self {
+            LoadError::Read(span, _, _) => *span,
+            LoadError::Parse(err) => err.span(),
+        };
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Read(_, path, err) => write!(f, "cannot import `{}`: {}", path, err),
+            LoadError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// A file and every file it imports, directly or not, parsed into one tree
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub source: ASTSource,
+    // Text of every file of the tree in the same order
+    pub texts: Vec<String>,
+    pub errors: Vec<LoadError>,
+}
+
+impl Program {
+    /// Path of the file a span is in and where it starts in that file,
+    /// lines and columns start at 0
+    pub fn locate(&self, span: Span) -> (&str, Position) {
+        let file = self.source.file_of(span);
+        let value = &self.source.files()[file];
+        let index = LineIndex::new(&self.texts[file]);
+        let position = index.position(span.start.saturating_sub(value.offset), Encoding::Char);
+        return (&value.path, position);
+    }
+}
+
+// Drops the `.` and `name/..` components so a file reached through
+// different imports is only loaded once
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    return normalized;
+}
+
+/// Parses a file then the files it imports, relative to the directory of
+/// the importing file, until every import is linked to its file. `path`
+/// is only used to find the imports and in messages, the other files are
+/// read with `read`. Errors are sorted by position.
+pub fn load<F>(path: &str, text: String, mut read: F) -> Program
+    where
+        F: FnMut(&Path) -> io::Result<String>,
+{
+    let mut program = Program {
+        source: ASTSource::new(),
+        texts: Vec::new(),
+        errors: Vec::new(),
+    };
+    let mut loaded = HashMap::new();
+    let mut pending = vec![(normalize(Path::new(path)), path.to_string(), text)];
+    // Imports in the order they were found
+    let mut imports = VecDeque::new();
+    loop {
+        for (key, path, text) in pending.drain(..) {
+            let parsed = program.source.parse_file(&path, &text, ParseOptions::default());
+            program.texts.push(text);
+            match parsed {
+                Ok(file) => {
+                    loaded.insert(key.clone(), file);
+                    let roots = program.source.file_roots(file);
+                    let found = roots.iter().filter_map(|id| program.source.cast::<Import>(*id));
+                    imports.extend(found.map(|id| (key.clone(), id)));
+                }
+                Err(err) => program.errors.push(LoadError::Parse(err)),
+            }
+        }
+        let Some((importer, id)) = imports.pop_front() else { break };
+        let file = format!("{}.{}", program.source.get(id).file(), EXTENSION);
+        let key = normalize(&importer.parent().unwrap_or(Path::new("")).join(file));
+        if let Some(file) = loaded.get(&key) {
+            program.source.link(id, *file);
+            continue;
+        }
+        let path = key.to_string_lossy().to_string();
+        match read(&key) {
+            Ok(text) => {
+                // Files are parsed in the order they are found so this is
+                // the index the file gets
+                program.source.link(id, program.texts.len());
+                loaded.insert(key.clone(), program.texts.len());
+                pending.push((key, path, text));
+            }
+            Err(err) => {
+                let span = program.source.span(id.untyped());
+                program.errors.push(LoadError::Read(span, path, err.to_string()));
+            }
+        }
+    }
+    program.errors.sort_by_key(|err| err.span().start);
+    return program;
+}
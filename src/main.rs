@@ -1,437 +1,413 @@
-use std::fmt;
-use std::fmt::Debug;
-use crate::ast::ASTSource;
+#![allow(clippy::needless_return)]
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::ExitCode;
+
+use spew::annotation::AnnotationRegistry;
+use spew::ast::{ASTSource, AST};
+use spew::check;
+use spew::conform;
+use spew::eval::{self, Evaluation};
+use spew::flow;
+use spew::format::{self, FormatConfig};
+use spew::inline;
+use spew::json;
+use spew::lexer::{LexError, Lexer, TokenSet};
+use spew::load::{self, Program};
+use spew::mutability;
+use spew::printer;
+use spew::resolve;
+use spew::source::{Encoding, LineIndex, Position, Span};
+use spew::visibility;
 
-mod ast;
-
-struct Parser;
-
-struct ParserContext<'a> {
-    chars: &'a mut Vec<char>,
-    tokens: &'a mut Vec<Token>,
-
-    line: usize,
-    line_offset: usize,
-    offset: usize,
-}
+const SOURCE: &str = include_str!("../example.spew");
 
-impl ParserContext<'_> {
-    fn new<'a>(chars: &'a mut Vec<char>, tokens: &'a mut Vec<Token>) -> ParserContext<'a> {
-        ParserContext {
-            chars,
-            tokens,
-            line: 0,
-            offset: 0,
-            line_offset: 0,
+const USAGE: &str = "usage: spew check [file]
+       spew compile [--steps <limit>] [--no-inline] [file]
+       spew hover <line>:<column> [file]
+       spew fmt [--check] [--config <file>] [files...]
+       spew json <tokens|ast> [file]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    return match args.first().map(String::as_str) {
+        None => {
+            dump();
+            ExitCode::SUCCESS
         }
-    }
-
-    fn push_token(&mut self, token: Token) {
-        self.tokens.push(token)
-    }
-
-    fn step_back(&mut self) {
-        self.offset -= 1;
-    }
-
-    fn next_char(&mut self) -> Option<char> {
-        if self.offset < self.chars.len() {
-            let char = *self.chars.get(self.offset)?;
-            self.offset += 1;
-            if char == '\n' {
-                self.line += 1;
-                self.line_offset = 0
-            } else if char == '\r' {
-                self.line_offset = 0;
-                return self.next_char();
-            } else {
-                self.line_offset += 1;
-            }
-            return Some(char);
+        Some("check") => check(&args[1..]),
+        Some("compile") => compile(&args[1..]),
+        Some("hover") => hover(&args[1..]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("json") => json(&args[1..]),
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
         }
-        return None;
-    }
+    };
+}
 
-    fn take_while<P>(&mut self, mut predicate: P) -> String
-        where
-            Self: Sized,
-            P: FnMut(&char) -> bool,
-    {
-        let mut out = String::new();
-        while let Some(char) = self.next_char() {
-            let result = predicate(&char);
-            if !result {
-                self.offset -= 1;
-                break;
-            }
-            out.push(char);
+// Prints the tokens and tree of the example source
+fn dump() {
+    let index = LineIndex::new(SOURCE);
+    let tokens: Result<Vec<_>, _> = Lexer::new(SOURCE).collect();
+    match tokens {
+        Ok(tokens) => println!("{:#?}", tokens),
+        Err(err) => {
+            let position = index.position(err.position, Encoding::Char);
+            eprintln!("{}:{}: {:?}", position.line + 1, position.column + 1, err.kind);
+            return;
         }
-        return out;
     }
-
-    fn skip_while<P>(&mut self, mut predicate: P)
-        where
-            Self: Sized,
-            P: FnMut(&char) -> bool,
-    {
-        while let Some(char) = self.next_char() {
-            let result = predicate(&char);
-            if !result {
-                self.offset -= 1;
-                break;
+    let values = ASTSource::parse_ast(TokenSet::new(SOURCE));
+    match values {
+        Ok(ast) => {
+            println!("{:#?}", ast);
+            for err in AnnotationRegistry::default().validate(&ast) {
+                let position = index.position(err.span().start, Encoding::Char);
+                eprintln!("{}:{}: {}", position.line + 1, position.column + 1, err)
             }
         }
+        Err(err) => {
+            let position = index.position(err.span().start, Encoding::Char);
+            eprintln!("{}:{}: {}", position.line + 1, position.column + 1, err)
+        }
     }
 }
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub enum KeywordType {
-    Constant,
-    Let,
-    Function,
-    Unknown,
-    Static,
-    Struct,
-    Trait,
-    Impl,
-    For,
-    Modifier(Modifier),
+fn print_lex_error(path: &str, text: &str, err: &LexError) {
+    let position = LineIndex::new(text).position(err.position, Encoding::Char);
+    eprintln!("{}:{}:{}: {:?}", path, position.line + 1, position.column + 1, err.kind);
 }
 
-impl KeywordType {
-    fn from(value: &String) -> Option<KeywordType> {
-        return match value.as_ref() {
-            "const" => Some(KeywordType::Constant),
-            "fun" => Some(KeywordType::Function),
-            "let" => Some(KeywordType::Let),
-            "trait" => Some(KeywordType::Trait),
-            "impl" => Some(KeywordType::Impl),
-            "struct" => Some(KeywordType::Struct),
-            "for" => Some(KeywordType::For),
-            v => {
-                return if let Some(modifier) = Modifier::from(v) {
-                    Some(KeywordType::Modifier(modifier))
-                } else {
-                    None
-                };
+// Reads the file named by the argument or stdin when there is none
+fn read_input(path: Option<&String>) -> Result<(String, String), String> {
+    return match path {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => Ok((path.clone(), text)),
+            Err(err) => Err(format!("{}: {}", path, err)),
+        },
+        None => {
+            let mut text = String::new();
+            match std::io::stdin().read_to_string(&mut text) {
+                Ok(_) => Ok(("<stdin>".to_string(), text)),
+                Err(err) => Err(format!("<stdin>: {}", err)),
             }
-        };
-    }
-}
-
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub enum Modifier {
-    Public,
-    Static,
-    Inline,
-    Compile,
-    Mutable,
+        }
+    };
 }
 
-impl Modifier {
-    fn from(value: &str) -> Option<Modifier> {
-        return match value.as_ref() {
-            "pub" => Some(Modifier::Public),
-            "mut" => Some(Modifier::Mutable),
-            "static" => Some(Modifier::Static),
-            "inline" => Some(Modifier::Inline),
-            "compile" => Some(Modifier::Compile),
-            _ => None,
-        };
+// Parses a file and the files it imports, reporting what couldn't be
+// loaded
+fn load_program(path: &str, text: String) -> Option<Program> {
+    let program = load::load(path, text, |path| fs::read_to_string(path));
+    let errors: Vec<_> = program.errors.iter().map(|err| (err.span(), err.to_string())).collect();
+    report(&program, &errors);
+    if errors.is_empty() {
+        return Some(program);
     }
+    return None;
 }
 
-macro_rules! symbols {
-    (
-      $($name:ident: $value:literal),* $(,)?
-    ) => {
-        #[derive(PartialEq, Clone)]
-        pub enum Symbol {
-          $($name,)*
-        }
-
-        impl Symbol {
-          fn from(value: char) -> Option<Symbol> {
-            return match value {
-              $(
-                $value => Some(Symbol::$name),
-              )*
-              _ => None,
-            }
-          }
-        }
-
-        impl Debug for Symbol {
-          fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-              match self {
-                $(
-                  Symbol::$name => {
-                    write!(f, "{}",$value)?;
-                  },
-                )*
-              }
-              Ok(())
-          }
+/// Parses a file with its imports and reports every diagnostic of the
+/// checks run on them, the exit code is 1 when there are any
+fn check(args: &[String]) -> ExitCode {
+    if args.len() > 1 {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    }
+    let (path, text) = match read_input(args.first()) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::from(2);
         }
     };
+    let Some(program) = load_program(&path, text) else { return ExitCode::from(1) };
+    let (diagnostics, _) = diagnostics(&program.source, eval::STEP_LIMIT);
+    report(&program, &diagnostics);
+    if diagnostics.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    return ExitCode::from(1);
 }
 
-symbols! {
-  OpenCurly: '{',
-  CloseCurly: '}',
-  OpenParen: '(',
-  CloseParen: ')',
-  OpenSquare: '[',
-  CloseSquare: '[',
-  Plus: '+',
-  Minus: '-',
-  Left: '<',
-  Right: '>',
-  Underscore: '_',
-  Exclamation: '!',
-  Equals: '=',
-  And: '&',
-  Pipe: '|',
-  Period: '.',
-  Multiply: '*',
-  Percent: '%',
-  Divide: '/',
-  Colon: ':',
-  Comma: ',',
-  Question: '?',
-}
-
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub enum Literal {
-    String(String),
-    Number(String),
-    Boolean(bool),
-    Null,
-    Undefined,
+// Runs every check on a tree and evaluates what runs at compile time,
+// diagnostics are sorted by position
+fn diagnostics(source: &ASTSource, steps: usize) -> (Vec<(Span, String)>, Evaluation) {
+    let mut diagnostics: Vec<_> = AnnotationRegistry::default()
+        .validate(source)
+        .into_iter()
+        .map(|err| (err.span(), err.to_string()))
+        .collect();
+    let resolution = resolve::resolve(source);
+    diagnostics.extend(resolution.errors.iter().map(|err| (err.span(), err.to_string())));
+    diagnostics.extend(conform::conform(source, &resolution).into_iter().map(|err| (err.span(), err.to_string())));
+    diagnostics.extend(mutability::mutability(source, &resolution).into_iter().map(|err| (err.span(), err.to_string())));
+    diagnostics.extend(visibility::visibility(source).into_iter().map(|err| (err.span(), err.to_string())));
+    diagnostics.extend(flow::flow(source, &resolution).into_iter().map(|err| (err.span(), err.to_string())));
+    let check = check::check(source, &resolution);
+    diagnostics.extend(check.errors.iter().map(|err| (err.span(), err.to_string())));
+    let evaluation = eval::evaluate(source, &resolution, &check, steps);
+    diagnostics.extend(evaluation.errors.iter().map(|err| (err.span(), err.to_string())));
+    diagnostics.sort_by_key(|(span, _)| span.start);
+    return (diagnostics, evaluation);
 }
 
-#[derive(Clone)]
-#[allow(dead_code)]
-pub enum Token {
-    Comment(String),
-    Keyword(KeywordType),
-    Ident(String),
-    Symbol(Symbol),
-    Literal(Literal),
+// Diagnostics are reported in the file they are about
+fn report(program: &Program, diagnostics: &[(Span, String)]) {
+    for (span, message) in diagnostics {
+        let (path, position) = program.locate(*span);
+        eprintln!("{}:{}:{}: {}", path, position.line + 1, position.column + 1, message);
+    }
 }
 
-impl Debug for Token {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Token::Comment(comment) => {
-                write!(f, "Comment({})", comment)?;
-            }
-            Token::Keyword(keyword) => {
-                write!(f, "Keyword({:?})", keyword)?;
-            }
-            Token::Ident(ident) => {
-                write!(f, "Ident({})", ident)?;
-            }
-            Token::Symbol(symbol) => {
-                write!(f, "Symbol({:?})", symbol)?;
+/// Checks a file like `check` then prints it with every constant initializer
+/// and `compile` function call replaced by the literal it evaluates to, and
+/// calls of `inline` and small functions replaced by their bodies
+fn compile(args: &[String]) -> ExitCode {
+    let mut steps = eval::STEP_LIMIT;
+    let mut inlining = true;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--steps" => match args.next().and_then(|limit| limit.parse().ok()) {
+                Some(limit) => steps = limit,
+                None => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            "--no-inline" => inlining = false,
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown option `{}`\n{}", flag, USAGE);
+                return ExitCode::from(2);
             }
-            Token::Literal(literal) => {
-                write!(f, "Literal({:?})", literal)?;
+            _ if file.is_some() => {
+                eprintln!("{}", USAGE);
+                return ExitCode::from(2);
             }
+            path => file = Some(path.to_string()),
         }
-        Ok(())
-    }
-}
-
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-enum ParserError {
-    UnexpectedToken(char, String),
-    Expected(String),
-    Fail(String),
-    IncompleteLiteral(String, String),
-}
-
-type ParseResult<T> = Result<T, ParserError>;
-
-#[derive(Debug, Clone)]
-pub struct TokenSet {
-    cursor: usize,
-    tokens: Vec<Token>,
-}
-
-impl TokenSet {
-    fn new(tokens: Vec<Token>) -> TokenSet {
-        return TokenSet {
-            cursor: 0,
-            tokens,
-        };
     }
-
-    pub fn next(&mut self, amount: usize) {
-        self.cursor += amount
+    let (path, text) = match read_input(file.as_ref()) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let Some(program) = load_program(&path, text) else { return ExitCode::from(1) };
+    let (diagnostics, evaluation) = diagnostics(&program.source, steps);
+    report(&program, &diagnostics);
+    if !diagnostics.is_empty() {
+        return ExitCode::from(1);
     }
-
-    pub fn back(&mut self, amount: usize) {
-        self.cursor -= amount
+    let mut source = program.source;
+    evaluation.inline(&mut source);
+    if inlining {
+        let resolution = resolve::resolve(&source);
+        inline::inline(&mut source, &resolution);
     }
+    // Imported files are only checked, the file given is what gets printed
+    print!("{}", printer::print_file(&source, 0));
+    return ExitCode::SUCCESS;
+}
 
-    pub fn next_token(&mut self) -> Option<Token> {
-        while let Some(token) = self.tokens.get(self.cursor) { // Check that the token is some
-            self.cursor += 1;
-            if let Token::Comment(token) = token { // Ignoring comment tokens
-                continue;
-            } else {
-                return Some(token.clone());
-            }
+/// Prints the type of the innermost expression or binding at a position,
+/// lines and columns start at 1
+fn hover(args: &[String]) -> ExitCode {
+    let position = args.first().and_then(|position| {
+        let (line, column) = position.split_once(':')?;
+        Some(Position {
+            line: line.parse::<usize>().ok()?.checked_sub(1)?,
+            column: column.parse::<usize>().ok()?.checked_sub(1)?,
+        })
+    });
+    let Some(position) = position.filter(|_| args.len() <= 2) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+    let (path, text) = match read_input(args.get(1)) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::from(2);
         }
-        return None;
+    };
+    let index = LineIndex::new(&text);
+    let Some(Program { source, .. }) = load_program(&path, text.clone()) else { return ExitCode::from(1) };
+    let check = check::check(&source, &resolve::resolve(&source));
+    let found = index.offset(position, Encoding::Char).and_then(|offset| check.hover(&source, offset));
+    match found {
+        Some((id, value)) => match source.get(id) {
+            AST::Variable { variable, .. } => println!("{}: {}", variable.name, value),
+            AST::Argument(argument) => println!("{}: {}", argument.name, value),
+            _ => println!("{}", value),
+        },
+        None => println!("no type at {}:{}", position.line + 1, position.column + 1),
     }
+    return ExitCode::SUCCESS;
 }
 
-impl Parser {
-    fn consume_comment<'a>(
-        context: &'a mut ParserContext<'a>,
-    ) -> ParseResult<&'a mut ParserContext<'a>> {
-        return if let Some(char) = context.next_char() {
-            if char == '/' {
-                // Full line comment
-                let comment_text = context.take_while(|char| char != &'\n');
-                context.push_token(Token::Comment(comment_text));
-                Ok(context)
-            } else if char == '*' {
-                // Multi-line comment
-                let mut comment_text = String::new();
-                while let Some(first_char) = context.next_char() {
-                    if first_char == '*' {
-                        if let Some(second_char) = context.next_char() {
-                            if second_char == '/' {
-                                break;
-                            } else {
-                                comment_text.push(second_char)
-                            }
-                        } else {
-                            comment_text.push(first_char)
-                        }
-                    } else {
-                        comment_text.push(first_char)
-                    }
-                }
-                context.push_token(Token::Comment(comment_text));
-                Ok(context)
-            } else {
-                Err(ParserError::UnexpectedToken(
-                    char,
-                    String::from("Expected '/' for line comment or '*' for multiline comment"),
-                ))
+/// Writes the tokens or the tree of a file as JSON for other tools
+fn json(args: &[String]) -> ExitCode {
+    let tokens = match args.first().map(String::as_str) {
+        Some("tokens") => true,
+        Some("ast") => false,
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if args.len() > 2 {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    }
+    let (path, text) = match read_input(args.get(1)) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::from(2);
+        }
+    };
+    if tokens {
+        return match json::tokens_to_json(&text) {
+            Ok(output) => {
+                println!("{}", output);
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                print_lex_error(&path, &text, &err);
+                ExitCode::from(1)
             }
-        } else {
-            Err(ParserError::Expected(String::from("/ for comment at")))
         };
     }
-
-    fn consume_ident<'a>(
-        context: &'a mut ParserContext<'a>,
-    ) -> ParseResult<&'a mut ParserContext<'a>> {
-        context.step_back();
-        let ident = context
-            .take_while(|char| char.is_alphabetic() || char.is_alphanumeric() || char == &'_');
-        if let Some(keyword) = KeywordType::from(&ident) {
-            context.push_token(Token::Keyword(keyword));
-        } else if ident == "true" || ident == "false" {
-            let is_true = ident == "true";
-            context.push_token(Token::Literal(Literal::Boolean(is_true)))
-        } else if ident == "null" {
-            context.push_token(Token::Literal(Literal::Null))
-        } else if ident == "ndef" {
-            context.push_token(Token::Literal(Literal::Undefined))
-        } else {
-            context.push_token(Token::Ident(ident));
+    return match ASTSource::parse_ast(TokenSet::new(&text)) {
+        Ok(source) => {
+            println!("{}", json::ast_to_json(&source));
+            ExitCode::SUCCESS
         }
-        Ok(context)
-    }
+        Err(err) => {
+            let position = LineIndex::new(&text).position(err.span().start, Encoding::Char);
+            eprintln!("{}:{}:{}: {}", path, position.line + 1, position.column + 1, err);
+            ExitCode::from(1)
+        }
+    };
+}
 
-    fn consume_number_literal<'a>(
-        context: &'a mut ParserContext<'a>,
-    ) -> ParseResult<&'a mut ParserContext<'a>> {
-        context.step_back();
-        let mut out = String::new();
-        let mut decimal = false;
-        while let Some(char) = context.next_char() {
-            if char.is_numeric() {
-                out.push(char);
-            } else if char == '.' && !decimal {
-                decimal = true;
-            } else {
-                context.step_back();
-                break;
+/// Formats files in place, or stdin to stdout when no files are given.
+/// With `--check` nothing is written and the exit code is 1 when any file
+/// isn't formatted.
+fn fmt(args: &[String]) -> ExitCode {
+    let mut check = false;
+    let mut config_path = None;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--config" => match args.next() {
+                Some(path) => config_path = Some(path.clone()),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown option `{}`\n{}", flag, USAGE);
+                return ExitCode::from(2);
             }
+            file => files.push(file.to_string()),
         }
-        context.push_token(Token::Literal(Literal::Number(out)));
-        Ok(context)
     }
 
-    fn consume_string_literal<'a>(
-        open_char: &char,
-        context: &'a mut ParserContext<'a>,
-    ) -> ParseResult<&'a mut ParserContext<'a>> {
-        let mut str = String::new();
-        let mut last_char: char = '"';
-        while let Some(char) = context.next_char() {
-            if &char == open_char && last_char != '\\' {
-                break;
-            } else {
-                str.push(char);
-                last_char = char;
+    // An explicit config has to exist, the default one is optional
+    let config_path = match config_path {
+        Some(path) => Some(path),
+        None if Path::new(FormatConfig::FILE_NAME).exists() => Some(FormatConfig::FILE_NAME.to_string()),
+        None => None,
+    };
+    let config = match config_path {
+        Some(path) => {
+            let parsed = fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| FormatConfig::parse(&text).map_err(|err| err.to_string()));
+            match parsed {
+                Ok(config) => config,
+                Err(err) => {
+                    eprintln!("{}: {}", path, err);
+                    return ExitCode::from(2);
+                }
             }
         }
-        context.push_token(Token::Literal(Literal::String(str)));
-        Ok(context)
-    }
-
-    fn parse_tokens(text: &str) -> ParseResult<TokenSet> {
-        let mut tokens = Vec::new();
-        let mut chars: Vec<char> = text.chars().collect();
-
-        let mut context = &mut ParserContext::new(&mut chars, &mut tokens);
+        None => FormatConfig::default(),
+    };
 
-        while let Some(next_char) = context.next_char() {
-            if next_char.is_whitespace() {
-                // Consuming whitespace
-                context.skip_while(|char| char.is_whitespace());
-            } else if next_char == '/' {
-                // Consume comments
-                context = Parser::consume_comment(context)?;
-            } else if next_char.is_alphabetic() {
-                // Consume idents
-                context = Parser::consume_ident(context)?;
-            } else if next_char == '"' || next_char == '\'' {
-                context = Parser::consume_string_literal(&next_char, context)?;
-            } else if next_char.is_numeric() {
-                context = Parser::consume_number_literal(context)?;
-            } else if let Some(symbol) = Symbol::from(next_char) {
-                context.push_token(Token::Symbol(symbol))
+    if files.is_empty() {
+        let text = match read_input(None) {
+            Ok((_, text)) => text,
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::from(2);
             }
-        }
-        return Ok(TokenSet::new(tokens));
+        };
+        return match format::format(&text, &config) {
+            Ok(formatted) if check && formatted != text => {
+                println!("<stdin>");
+                ExitCode::from(1)
+            }
+            Ok(_) if check => ExitCode::SUCCESS,
+            Ok(formatted) => {
+                print!("{}", formatted);
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                print_lex_error("<stdin>", &text, &err);
+                ExitCode::from(2)
+            }
+        };
     }
-}
 
-const SOURCE: &str = include_str!("../example.spew");
-
-fn main() {
-    let tokens = Parser::parse_tokens(SOURCE).expect("Failed to parse");
-    println!("{:#?}", tokens);
-    let values = ASTSource::parse_ast(tokens);
-    match values {
-        Ok(ast) => {
-            println!("{:#?}", ast)
+    let mut unformatted = false;
+    let mut failed = false;
+    for path in &files {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match format::format(&text, &config) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                print_lex_error(path, &text, &err);
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == text {
+            continue;
         }
-        Err(err) => {
-            eprintln!("{:?}",err)
+        if check {
+            // Files that need formatting are listed for CI logs
+            println!("{}", path);
+            unformatted = true;
+        } else if let Err(err) = fs::write(path, formatted) {
+            eprintln!("{}: {}", path, err);
+            failed = true;
         }
     }
+    if failed {
+        return ExitCode::from(2);
+    }
+    if unformatted {
+        return ExitCode::from(1);
+    }
+    return ExitCode::SUCCESS;
 }
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
+
+use crate::ast::{ASTSource, NodeId, AST};
+use crate::flow::initializations;
+use crate::intern::Name;
+use crate::lexer::Modifier;
+use crate::resolve::{Declaration, Resolution};
+use crate::source::Span;
+use crate::visit::{self, Visitor};
+
+/// How a name that can't be assigned to was bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Let,
+    Const,
+    Argument,
+    // Binding of a match arm
+    Arm,
+}
+
+impl Binding {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Binding::Let => "immutable",
+            Binding::Const => "constant",
+            Binding::Argument => "argument",
+            Binding::Arm => "match binding",
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MutabilityError {
+    // Binding assigned to directly
+    Assign(Span, Binding, Name),
+    // Binding one of whose properties was assigned to
+    Property(Span, Binding, Name),
+    // Name that isn't a variable, such as a function or struct
+    NotVariable(Span, Name),
+    // Left hand side that is neither a name nor a property
+    Target(Span),
+}
+
+impl MutabilityError {
+    pub fn span(&self) -> Span {
+        return match self {
+            MutabilityError::Assign(span, _, _) => *span,
+            MutabilityError::Property(span, _, _) => *span,
+            MutabilityError::NotVariable(span, _) => *span,
+            MutabilityError::Target(span) => *span,
+        };
+    }
+}
+
+// How to make a binding mutable
+fn suggestion(binding: Binding, name: Name) -> String {
+    return match binding {
+        Binding::Let | Binding::Const => format!("declare it with `let mut {}`", name),
+        Binding::Argument | Binding::Arm => format!("copy it with `let mut {} = {}` first", name, name),
+    };
+}
+
+impl Display for MutabilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MutabilityError::Assign(_, binding, name) => {
+                write!(f, "cannot assign to {} `{}`, {}", binding.as_str(), name, suggestion(*binding, *name))
+            }
+            MutabilityError::Property(_, binding, name) => {
+                write!(f, "cannot assign to a property of {} `{}`, {}", binding.as_str(), name, suggestion(*binding, *name))
+            }
+            MutabilityError::NotVariable(_, name) => {
+                write!(f, "cannot assign to `{}`, only variables and properties can be assigned", name)
+            }
+            MutabilityError::Target(_) => f.write_str("only variables and properties can be assigned"),
+        }
+    }
+}
+
+struct Mutability<'a> {
+    resolution: &'a Resolution,
+    // Assignments giving a `let` without a value its first value
+    initializations: HashSet<NodeId>,
+    errors: Vec<MutabilityError>,
+}
+
+impl<'a> Mutability<'a> {
+    // Binding a name was declared by when it can't be written to, `None`
+    // for mutable variables. `initializes` allows `let`s without a value.
+    fn immutable(&self, source: &ASTSource, declaration: Declaration, initializes: bool) -> Option<Binding> {
+        let Declaration::Node(id) = declaration else { return None };
+        return match source.get(id) {
+            AST::Variable { variable, .. } if variable.modifiers.contains(&Modifier::Mutable) => None,
+            // Immutable variables declared without a value are given one
+            // later, but only once
+            AST::Variable { variable, value: None } if initializes && !variable.constant => None,
+            AST::Variable { variable, .. } if variable.constant => Some(Binding::Const),
+            AST::Variable { .. } => Some(Binding::Let),
+            AST::Argument(_) => Some(Binding::Argument),
+            AST::Arm(_) => Some(Binding::Arm),
+            _ => None,
+        };
+    }
+
+    // Assignment to a name, which has to be a variable that can be assigned
+    fn assign(&mut self, source: &ASTSource, span: Span, target: NodeId, name: Name, initializes: bool) {
+        match self.resolution.value(target) {
+            Some(declaration @ Declaration::Node(node)) => match self.immutable(source, declaration, initializes) {
+                Some(binding) => self.errors.push(MutabilityError::Assign(span, binding, name)),
+                None if !matches!(source.get(node), AST::Variable { .. }) => {
+                    self.errors.push(MutabilityError::NotVariable(span, name));
+                }
+                None => {}
+            },
+            Some(_) => self.errors.push(MutabilityError::NotVariable(span, name)),
+            // Already reported as undefined
+            None => {}
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Mutability<'a> {
+    fn visit_assign(&mut self, source: &'a ASTSource, id: NodeId, target: NodeId, value: NodeId) {
+        let span = source.span(target);
+        let initializes = self.initializations.contains(&id);
+        // Items of a namespace are assigned like names
+        let item = self.resolution.value(target).is_some();
+        match source.get(target) {
+            AST::Identifier(name) | AST::Path { name, .. } => self.assign(source, span, target, *name, initializes),
+            AST::Member { name, .. } if item => self.assign(source, span, target, *name, initializes),
+            AST::Member { target: object, .. } => {
+                // Properties can be changed through a mutable binding, `this`
+                // or any value that isn't bound to a name
+                let mut root = *object;
+                while let AST::Member { target, .. } = source.get(root) {
+                    if self.resolution.value(root).is_some() {
+                        break;
+                    }
+                    root = *target;
+                }
+                if let (AST::Identifier(name) | AST::Path { name, .. } | AST::Member { name, .. }, Some(declaration)) =
+                    (source.get(root), self.resolution.value(root)) {
+                    if let Some(binding) = self.immutable(source, declaration, false) {
+                        self.errors.push(MutabilityError::Property(span, binding, *name));
+                    }
+                }
+            }
+            _ => self.errors.push(MutabilityError::Target(span)),
+        }
+        visit::walk_assign(self, source, id, target, value);
+    }
+}
+
+/// Checks that every assignment writes to a `mut` variable or to a property
+/// of one, or gives a `let` declared without a value its only value. Errors
+/// are sorted by position.
+pub fn mutability(source: &ASTSource, resolution: &Resolution) -> Vec<MutabilityError> {
+    let mut mutability = Mutability {
+        resolution,
+        initializations: initializations(source, resolution),
+        errors: Vec::new(),
+    };
+    mutability.visit_source(source);
+    let mut errors = mutability.errors;
+    errors.sort_by_key(|err| err.span().start);
+    return errors;
+}
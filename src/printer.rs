@@ -0,0 +1,554 @@
+use std::fmt::Write;
+
+use crate::ast::{
+    ASTSource, Annotation, DataType, FunctionArgument, FunctionStub, Import, ImportNames, MatchArm, NodeId, Operation, Pattern,
+    SpewFunction, SpewImpl, SpewStruct, SpewTrait, StructProperty, TypeAlias, Variable, AST,
+};
+use crate::lexer::Modifier;
+
+const INDENT: &str = "  ";
+
+/// Prints a whole tree back to canonical spew source
+pub fn print_source(source: &ASTSource) -> String {
+    return print_items(source, source.roots());
+}
+
+/// Prints the items of one of the files parsed into a tree
+pub fn print_file(source: &ASTSource, file: usize) -> String {
+    return print_items(source, source.file_roots(file));
+}
+
+fn print_items(source: &ASTSource, roots: &[NodeId]) -> String {
+    let mut printer = Printer::new(source);
+    for (index, id) in roots.iter().enumerate() {
+        // Imports are kept together at the top of a file
+        let imports = index > 0 && [roots[index - 1], *id].iter().all(|id| matches!(source.get(*id), AST::Import(_)));
+        if index > 0 && !imports {
+            printer.out.push('\n');
+        }
+        printer.print_item(*id);
+        printer.out.push('\n');
+    }
+    return printer.out;
+}
+
+/// Prints a single node and everything below it, items and statements are
+/// printed at the top level of indentation
+pub fn print_node(source: &ASTSource, id: NodeId) -> String {
+    let mut printer = Printer::new(source);
+    match source.get(id) {
+        AST::Struct(_)
+        | AST::Trait(_)
+        | AST::Impl(_)
+        | AST::Function(_)
+        | AST::FunctionStub(_)
+        | AST::TypeAlias(_)
+        | AST::Import(_) => printer.print_item(id),
+        AST::Property(value) => printer.print_property(value),
+        AST::Arm(value) => printer.print_arm(value),
+        AST::Argument(value) => printer.print_argument(value),
+        _ => printer.print_statement(id, false),
+    }
+    return printer.out;
+}
+
+/// Writes nodes out as source text. The output always parses back into the
+/// same tree, parentheses are only added where precedence requires them
+/// and semicolons only where the next statement would otherwise continue
+/// the previous one.
+struct Printer<'a> {
+    source: &'a ASTSource,
+    out: String,
+    indent: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn new(source: &'a ASTSource) -> Printer<'a> {
+        return Printer {
+            source,
+            out: String::new(),
+            indent: 0,
+        };
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn print_annotations(&mut self, annotations: &[Annotation]) {
+        for annotation in annotations {
+            let _ = write!(self.out, "@{}", annotation.name);
+            if !annotation.arguments.is_empty() {
+                self.out.push('(');
+                for (index, argument) in annotation.arguments.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push_str(", ");
+                    }
+                    if let Some(name) = argument.name {
+                        let _ = write!(self.out, "{} = ", name);
+                    }
+                    let _ = write!(self.out, "{}", argument.value);
+                }
+                self.out.push(')');
+            }
+            self.newline();
+        }
+    }
+
+    fn print_modifiers(&mut self, modifiers: &[Modifier]) {
+        for modifier in modifiers {
+            self.out.push_str(modifier.as_str());
+            self.out.push(' ');
+        }
+    }
+
+    fn print_public(&mut self, public: bool) {
+        if public {
+            self.out.push_str("pub ");
+        }
+    }
+
+    fn print_data_type(&mut self, value: &DataType) {
+        let _ = write!(self.out, "{}", value);
+    }
+
+    fn print_item(&mut self, id: NodeId) {
+        match self.source.get(id) {
+            AST::Struct(value) => self.print_struct(value),
+            AST::Trait(value) => self.print_trait(value),
+            AST::Impl(value) => self.print_impl(value),
+            AST::Function(value) => self.print_function(value),
+            AST::FunctionStub(value) => self.print_function_stub(value),
+            AST::TypeAlias(value) => self.print_type_alias(value),
+            AST::Import(value) => self.print_import(value),
+            _ => self.print_statement(id, true),
+        }
+    }
+
+    fn print_struct(&mut self, value: &SpewStruct) {
+        self.print_annotations(&value.annotations);
+        self.print_public(value.public);
+        let _ = write!(self.out, "struct {} {{", value.name);
+        self.indent += 1;
+        for property in &value.properties {
+            self.newline();
+            self.print_property(self.source.get(*property));
+        }
+        self.close(!value.properties.is_empty());
+    }
+
+    fn print_type_alias(&mut self, value: &TypeAlias) {
+        self.print_public(value.public);
+        let _ = write!(self.out, "type {} = ", value.name);
+        for (index, data_type) in value.types.iter().enumerate() {
+            if index > 0 {
+                self.out.push_str(if value.union { " | " } else { " & " });
+            }
+            self.print_data_type(data_type);
+        }
+    }
+
+    fn print_import(&mut self, value: &Import) {
+        self.out.push_str("import ");
+        match &value.names {
+            ImportNames::All => self.out.push('*'),
+            ImportNames::Namespace(name) => {
+                let _ = write!(self.out, "* as {}", name);
+            }
+            ImportNames::Listed(names) => {
+                self.out.push('{');
+                for (index, name) in names.iter().enumerate() {
+                    self.out.push_str(if index == 0 { " " } else { ", " });
+                    let _ = write!(self.out, "{}", name.name);
+                    if let Some(alias) = name.alias {
+                        let _ = write!(self.out, " as {}", alias);
+                    }
+                }
+                self.out.push_str(if names.is_empty() { "}" } else { " }" });
+            }
+        }
+        let _ = write!(self.out, " from \"{}\"", value.path);
+    }
+
+    fn print_property(&mut self, value: &StructProperty) {
+        self.print_annotations(&value.annotations);
+        self.print_public(value.public);
+        let _ = write!(self.out, "{}", value.name);
+        if value.optional {
+            self.out.push('?');
+        }
+        self.out.push_str(": ");
+        self.print_data_type(&value.type_of);
+    }
+
+    fn print_trait(&mut self, value: &SpewTrait) {
+        self.print_annotations(&value.annotations);
+        self.print_public(value.public);
+        let _ = write!(self.out, "trait {}", value.name);
+        for (index, supertrait) in value.supertraits.iter().enumerate() {
+            self.out.push_str(if index == 0 { ": " } else { " + " });
+            let _ = write!(self.out, "{}", supertrait);
+        }
+        self.out.push_str(" {");
+        self.indent += 1;
+        for function in &value.functions {
+            self.newline();
+            self.print_function_stub(self.source.get(*function));
+        }
+        self.close(!value.functions.is_empty());
+    }
+
+    fn print_impl(&mut self, value: &SpewImpl) {
+        match value.trait_name {
+            Some(trait_name) => {
+                let _ = write!(self.out, "impl {} for {} {{", trait_name, value.name);
+            }
+            None => {
+                let _ = write!(self.out, "impl {} {{", value.name);
+            }
+        }
+        self.indent += 1;
+        // Constants come first with a blank line between them and the functions
+        for constant in &value.constants {
+            self.newline();
+            if let AST::Variable { variable, value } = self.source.get(*constant) {
+                self.print_variable(variable, *value, true);
+            }
+        }
+        for (index, function) in value.functions.iter().enumerate() {
+            if index > 0 || !value.constants.is_empty() {
+                self.out.push('\n');
+            }
+            self.newline();
+            self.print_function(self.source.get(*function));
+        }
+        self.close(!value.constants.is_empty() || !value.functions.is_empty());
+    }
+
+    // Closes a brace opened at the previous indentation level, empty bodies
+    // are kept on one line
+    fn close(&mut self, contents: bool) {
+        self.indent -= 1;
+        if contents {
+            self.newline();
+        }
+        self.out.push('}');
+    }
+
+    fn print_function_stub(&mut self, value: &FunctionStub) {
+        self.print_annotations(&value.annotations);
+        self.print_modifiers(&value.modifiers);
+        let _ = write!(self.out, "fun {}", value.name);
+        self.print_arguments(&value.arguments, value.return_type.as_ref());
+    }
+
+    // Arguments in parens followed by the return type
+    fn print_arguments(&mut self, arguments: &[NodeId<FunctionArgument>], return_type: Option<&DataType>) {
+        self.out.push('(');
+        for (index, argument) in arguments.iter().enumerate() {
+            if index > 0 {
+                self.out.push_str(", ");
+            }
+            self.print_argument(self.source.get(*argument));
+        }
+        self.out.push(')');
+        if let Some(return_type) = return_type {
+            self.out.push_str(" -> ");
+            self.print_data_type(return_type);
+        }
+    }
+
+    fn print_argument(&mut self, value: &FunctionArgument) {
+        let _ = write!(self.out, "{}", value.name);
+        if let Some(data_type) = &value.data_type {
+            self.out.push_str(": ");
+            self.print_data_type(data_type);
+        }
+    }
+
+    fn print_function(&mut self, value: &SpewFunction) {
+        self.print_function_stub(&value.stub);
+        self.out.push(' ');
+        self.print_block(&value.body);
+    }
+
+    fn print_block(&mut self, contents: &[NodeId]) {
+        self.out.push('{');
+        self.indent += 1;
+        // End of the previous statement if it ended with an expression that
+        // the next statement could be mistaken as continuing
+        let mut open: Option<usize> = None;
+        for statement in contents {
+            self.newline();
+            let start = self.out.len();
+            self.print_statement(*statement, false);
+            if let Some(end) = open {
+                if self.out[start..].starts_with(['(', '-']) {
+                    self.out.insert(end, ';');
+                }
+            }
+            open = match self.source.get(*statement) {
+                AST::ConditionBlock { .. }
+                | AST::While { .. }
+                | AST::Break
+                | AST::Block(_)
+                | AST::Variable { value: None, .. }
+                | AST::Return(None) => None,
+                _ => Some(self.out.len()),
+            };
+        }
+        self.close(!contents.is_empty());
+    }
+
+    fn print_statement(&mut self, id: NodeId, item: bool) {
+        match self.source.get(id) {
+            AST::Variable { variable, value } => self.print_variable(variable, *value, item),
+            AST::Return(value) => {
+                self.out.push_str("return");
+                if let Some(value) = value {
+                    self.out.push(' ');
+                    self.print_expression(*value);
+                }
+            }
+            AST::ConditionBlock { .. } => self.print_condition(id),
+            AST::While { condition, contents } => {
+                self.out.push_str("while ");
+                self.print_expression(*condition);
+                self.out.push(' ');
+                self.print_block(contents);
+            }
+            AST::Break => self.out.push_str("break"),
+            AST::Block(contents) => self.print_block(contents),
+            _ => self.print_expression(id),
+        }
+    }
+
+    fn print_variable(&mut self, variable: &Variable, value: Option<NodeId>, item: bool) {
+        // `mut` always follows the keyword, other modifiers lead. Inside of a
+        // function only `compile` can start a statement so it goes first and
+        // without it everything follows the keyword.
+        let (mut leading, mut trailing): (Vec<Modifier>, Vec<Modifier>) = variable
+            .modifiers
+            .iter()
+            .partition(|modifier| **modifier != Modifier::Mutable);
+        leading.sort_by_key(|modifier| *modifier != Modifier::Compile);
+        if !item && !leading.contains(&Modifier::Compile) {
+            leading.append(&mut trailing);
+            trailing = leading;
+            leading = Vec::new();
+        }
+        self.print_modifiers(&leading);
+        self.out.push_str(if variable.constant { "const " } else { "let " });
+        self.print_modifiers(&trailing);
+        let _ = write!(self.out, "{}", variable.name);
+        if let Some(type_of) = &variable.type_of {
+            self.out.push_str(": ");
+            self.print_data_type(type_of);
+        }
+        if let Some(value) = value {
+            self.out.push_str(" = ");
+            self.print_expression(value);
+        }
+    }
+
+    fn print_condition(&mut self, id: NodeId) {
+        let mut current = id;
+        // else if chains are printed in a loop rather than recursively
+        loop {
+            let (condition, contents, otherwise) = match self.source.get(current) {
+                AST::ConditionBlock {
+                    condition,
+                    contents,
+                    otherwise,
+                } => (*condition, contents, *otherwise),
+                _ => unreachable!(),
+            };
+            self.out.push_str("if ");
+            self.print_expression(condition);
+            self.out.push(' ');
+            self.print_block(contents);
+            match otherwise.map(|otherwise| (otherwise, self.source.get(otherwise))) {
+                Some((otherwise, AST::ConditionBlock { .. })) => {
+                    self.out.push_str(" else ");
+                    current = otherwise;
+                }
+                Some((_, AST::Block(contents))) => {
+                    self.out.push_str(" else ");
+                    self.print_block(contents);
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn binary(&self, id: NodeId) -> Option<&'a Operation> {
+        return match self.source.get(id) {
+            AST::Operation(operation) if operation.right.is_some() => Some(operation),
+            _ => None,
+        };
+    }
+
+    // Prints an operand wrapping it in parentheses when it binds looser than
+    // `precedence`
+    fn print_operand(&mut self, id: NodeId, precedence: u8) {
+        match self.binary(id) {
+            _ if matches!(self.source.get(id), AST::Assign { .. }) => {
+                self.out.push('(');
+                self.print_expression(id);
+                self.out.push(')');
+            }
+            Some(operation) if operation.operator.precedence() < precedence => {
+                self.out.push('(');
+                self.print_expression(id);
+                self.out.push(')');
+            }
+            _ => self.print_expression(id),
+        }
+    }
+
+    /// Operators are left associative so a chain like `a + b + c` nests
+    /// down the left, the chain is walked in a loop to keep long ones from
+    /// using up the stack
+    fn print_expression(&mut self, id: NodeId) {
+        if let AST::Assign { target, value } = self.source.get(id) {
+            self.print_operand(*target, 0);
+            self.out.push_str(" = ");
+            self.print_expression(*value);
+            return;
+        }
+        let mut chain = Vec::new();
+        let mut current = id;
+        while let Some(operation) = self.binary(current) {
+            chain.push(operation);
+            match self.binary(operation.left) {
+                Some(left) if left.operator.precedence() >= operation.operator.precedence() => current = operation.left,
+                _ => break,
+            }
+        }
+        let operation = match chain.last() {
+            Some(operation) => operation,
+            None => return self.print_unary(id),
+        };
+        self.print_operand(operation.left, operation.operator.precedence());
+        for operation in chain.iter().rev() {
+            let _ = write!(self.out, " {} ", operation.operator.as_str());
+            // Equal precedence on the right only comes from parentheses
+            self.print_operand(operation.right.unwrap(), operation.operator.precedence() + 1);
+        }
+    }
+
+    fn print_unary(&mut self, id: NodeId) {
+        let mut current = id;
+        while let AST::Operation(operation) = self.source.get(current) {
+            // `--x` reads like a decrement
+            if operation.operator.as_str() == "-" && self.out.ends_with('-') {
+                self.out.push(' ');
+            }
+            self.out.push_str(operation.operator.as_str());
+            current = operation.left;
+            if self.binary(current).is_some() {
+                self.out.push('(');
+                self.print_expression(current);
+                self.out.push(')');
+                return;
+            }
+        }
+        self.print_postfix(current);
+    }
+
+    fn print_postfix(&mut self, id: NodeId) {
+        let mut chain = Vec::new();
+        let mut current = id;
+        while let AST::Call { target, .. } | AST::Member { target, .. } | AST::Is { value: target, .. } =
+            self.source.get(current) {
+            chain.push(current);
+            current = *target;
+        }
+        self.print_primary(current);
+        for id in chain.iter().rev() {
+            match self.source.get(*id) {
+                AST::Call { arguments, .. } => {
+                    self.out.push('(');
+                    for (index, argument) in arguments.iter().enumerate() {
+                        if index > 0 {
+                            self.out.push_str(", ");
+                        }
+                        self.print_expression(*argument);
+                    }
+                    self.out.push(')');
+                }
+                AST::Member { name, .. } => {
+                    let _ = write!(self.out, ".{}", name);
+                }
+                AST::Is { data_type, .. } => {
+                    self.out.push_str(" is ");
+                    self.print_data_type(data_type);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    fn print_arm(&mut self, value: &MatchArm) {
+        match &value.pattern {
+            Pattern::Literal(literal) => {
+                let _ = write!(self.out, "{}", literal);
+            }
+            Pattern::Wildcard => self.out.push('_'),
+            Pattern::Binding(name) => {
+                let _ = write!(self.out, "{}", name);
+            }
+            Pattern::Type(data_type) => {
+                self.out.push_str("is ");
+                self.print_data_type(data_type);
+            }
+        }
+        self.out.push_str(" => ");
+        match self.source.get(value.body) {
+            AST::Block(contents) => self.print_block(contents),
+            _ => self.print_expression(value.body),
+        }
+    }
+
+    fn print_primary(&mut self, id: NodeId) {
+        match self.source.get(id) {
+            AST::Literal(value) => {
+                let _ = write!(self.out, "{}", value);
+            }
+            AST::Identifier(name) => {
+                let _ = write!(self.out, "{}", name);
+            }
+            AST::Path { data_type, name } => {
+                let _ = write!(self.out, "{}::{}", data_type.name, name);
+            }
+            AST::Match { value, arms } => {
+                self.out.push_str("match ");
+                self.print_expression(*value);
+                self.out.push_str(" {");
+                self.indent += 1;
+                for arm in arms {
+                    self.newline();
+                    self.print_arm(self.source.get(*arm));
+                    self.out.push(',');
+                }
+                self.close(!arms.is_empty());
+            }
+            AST::Lambda(value) => {
+                self.out.push_str("fun ");
+                self.print_arguments(&value.arguments, value.return_type.as_ref());
+                self.out.push(' ');
+                self.print_block(&value.body);
+            }
+            // Operations used as the target of a call or member access
+            _ => {
+                self.out.push('(');
+                self.print_expression(id);
+                self.out.push(')');
+            }
+        }
+    }
+}
@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+
+use crate::ast::{
+    qualified, ASTSource, DataType, FunctionArgument, FunctionStub, Import, ImportNames, Lambda, MatchArm, NodeId,
+    NodeMap, Pattern, SpewFunction, SpewImpl, SpewStruct, SpewTrait, Variable, AST,
+};
+use crate::intern::Name;
+use crate::lexer::Modifier;
+use crate::source::Span;
+use crate::visibility::{exports, is_public, item_name};
+use crate::visit::{self, Visitor};
+
+/// Types every module can refer to without declaring them
+pub const BUILTIN_TYPES: &[&str] = &["str", "num", "obj", "bool", "null", "any", "ndef", "void"];
+
+/// Functions every module can call without declaring them
+pub const BUILTIN_FUNCTIONS: &[&str] = &["print", "assert"];
+
+/// What a name refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Declaration {
+    // Builtin type or function
+    Builtin(Name),
+    // Struct, trait, function, variable, argument or match arm node, or the
+    // import of a namespace
+    Node(NodeId),
+    // `this` inside the functions of an impl
+    This(NodeId<SpewImpl>),
+}
+
+/// Trait and type an impl is for, None where the name couldn't be resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImplTarget {
+    pub trait_of: Option<NodeId<SpewTrait>>,
+    pub target: Option<Declaration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolveError {
+    Undefined(Span, Name),
+    UndefinedType(Span, Name),
+    Duplicate(Span, Name),
+    NotATrait(Span, Name),
+    // Type followed by the name of a static member it doesn't have
+    NoStatic(Span, Name, Name),
+    // Type followed by an instance function used through the type
+    NotStatic(Span, Name, Name),
+    // `static` on something other than a member of an impl or a function
+    // of a trait
+    Static(Span),
+    // `inline` on something other than a function
+    Inline(Span),
+    // `const mut`, constants can never be assigned
+    MutableConstant(Span),
+    // Imported file followed by the name it has no item for
+    NoItem(Span, String, Name),
+    // Item, field or member of an impl of another file that isn't `pub`
+    Private(Span, Name),
+    // Namespace import used as a value
+    Namespace(Span, Name),
+}
+
+impl ResolveError {
+    pub fn span(&self) -> Span {
+        return match self {
+            ResolveError::Undefined(span, _) => *span,
+            ResolveError::UndefinedType(span, _) => *span,
+            ResolveError::Duplicate(span, _) => *span,
+            ResolveError::NotATrait(span, _) => *span,
+            ResolveError::NoStatic(span, _, _) => *span,
+            ResolveError::NotStatic(span, _, _) => *span,
+            ResolveError::Static(span) => *span,
+            ResolveError::Inline(span) => *span,
+            ResolveError::MutableConstant(span) => *span,
+            ResolveError::NoItem(span, _, _) => *span,
+            ResolveError::Private(span, _) => *span,
+            ResolveError::Namespace(span, _) => *span,
+        };
+    }
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Undefined(_, name) => write!(f, "cannot find `{}` in this scope", name),
+            ResolveError::UndefinedType(_, name) => write!(f, "cannot find type `{}` in this scope", name),
+            ResolveError::Duplicate(_, name) => write!(f, "`{}` is defined more than once", name),
+            ResolveError::NotATrait(_, name) => write!(f, "`{}` is not a trait", name),
+            ResolveError::NoStatic(_, name, member) => {
+                write!(f, "`{}` has no static function or constant `{}`", name, member)
+            }
+            ResolveError::NotStatic(_, name, member) => {
+                write!(f, "`{}` isn't static, call it on a value of `{}`", member, name)
+            }
+            ResolveError::Static(_) => {
+                f.write_str("`static` can only be used on functions and constants of impls and functions of traits")
+            }
+            ResolveError::Inline(_) => f.write_str("`inline` can only be used on functions"),
+            ResolveError::MutableConstant(_) => f.write_str("constants can't be `mut`, declare it with `let mut`"),
+            ResolveError::NoItem(_, path, name) => write!(f, "\"{}\" has no item `{}`", path, name),
+            ResolveError::Private(_, name) => write!(f, "`{}` isn't `pub` so it can only be used in its own file", name),
+            ResolveError::Namespace(_, name) => {
+                write!(f, "`{}` is a namespace, use one of its items as `{}.name`", name, name)
+            }
+        }
+    }
+}
+
+/// Result of resolving a tree
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    /// Declaration of every identifier node
+    pub values: NodeMap<Declaration>,
+    /// Declarations of the types written in every node, one for every
+    /// member of a union and every type of an alias in source order, `None`
+    /// where the name couldn't be resolved
+    pub types: NodeMap<Vec<Option<Declaration>>>,
+    pub impls: NodeMap<ImplTarget>,
+    /// Supertraits of every trait that could be resolved
+    pub supertraits: NodeMap<Vec<NodeId<SpewTrait>>>,
+    pub errors: Vec<ResolveError>,
+}
+
+impl Resolution {
+    pub fn value(&self, id: NodeId) -> Option<Declaration> {
+        return self.values.get(id).copied();
+    }
+
+    /// Declaration of the first type written in a node
+    pub fn type_of<T>(&self, id: NodeId<T>) -> Option<Declaration> {
+        return self.type_at(id, 0);
+    }
+
+    /// Declaration of a type written in a node, `index` counts every member
+    /// of a union and every type of an alias
+    pub fn type_at<T>(&self, id: NodeId<T>, index: usize) -> Option<Declaration> {
+        return self.types.get(id)?.get(index).copied().flatten();
+    }
+
+    /// Follows aliases of a single type to the declaration they stand for,
+    /// along with whether one of them made it nullable. Intersections, unions
+    /// and aliases that refer back to themselves are left as they are.
+    pub fn unalias(&self, source: &ASTSource, declaration: Declaration) -> (Declaration, bool) {
+        let mut current = declaration;
+        let mut nullable = false;
+        let mut seen = Vec::new();
+        while let Declaration::Node(id) = current {
+            let AST::TypeAlias(alias) = source.get(id) else { break };
+            if alias.union || alias.types.len() != 1 || seen.contains(&id) {
+                break;
+            }
+            let Some(next) = self.type_of(id) else { break };
+            seen.push(id);
+            nullable |= alias.types[0].nullable;
+            current = next;
+        }
+        return (current, nullable);
+    }
+}
+
+#[derive(Default)]
+struct Scope {
+    values: HashMap<Name, Declaration>,
+    // Only the module scope declares types
+    types: HashMap<Name, Declaration>,
+}
+
+struct Resolver {
+    scopes: Vec<Scope>,
+    // `Type::name` paths, resolved once every impl is
+    paths: Vec<NodeId>,
+    resolution: Resolution,
+}
+
+/// Whether a function is called through its type rather than on a value
+pub fn is_static(value: &FunctionStub) -> bool {
+    return value.modifiers.contains(&Modifier::Static);
+}
+
+/// Whether a field or member of an impl can only be used in its own file,
+/// functions of trait impls are as visible as the trait
+pub fn is_private(source: &ASTSource, id: NodeId) -> bool {
+    let trait_impl = source.parent(id)
+        .and_then(|parent| source.cast::<SpewImpl>(parent))
+        .is_some_and(|parent| source.get(parent).trait_name.is_some());
+    return !trait_impl && !is_public(source.get(id));
+}
+
+impl Resolver {
+    fn lookup_value(&self, name: Name) -> Option<Declaration> {
+        return self.scopes.iter().rev().find_map(|scope| scope.values.get(&name).copied());
+    }
+
+    fn lookup_type(&self, name: Name) -> Option<Declaration> {
+        return self.scopes.iter().rev().find_map(|scope| scope.types.get(&name).copied());
+    }
+
+    // Trait a name refers to, errors are reported at `span`
+    fn lookup_trait(&mut self, source: &ASTSource, span: Span, name: Name) -> Option<NodeId<SpewTrait>> {
+        return match self.lookup_type(name) {
+            Some(Declaration::Node(id)) if source.cast::<SpewTrait>(id).is_some() => source.cast(id),
+            Some(_) => {
+                self.resolution.errors.push(ResolveError::NotATrait(span, name));
+                None
+            }
+            None => {
+                self.resolution.errors.push(ResolveError::UndefinedType(span, name));
+                None
+            }
+        };
+    }
+
+    // Names can shadow those of outer scopes but not others in the same
+    // scope, an item imported more than once is still the same item
+    fn declare_value(&mut self, span: Span, name: Name, declaration: Declaration) {
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.values.insert(name, declaration).is_some_and(|previous| previous != declaration) {
+            self.resolution.errors.push(ResolveError::Duplicate(span, name));
+        }
+    }
+
+    fn declare_type(&mut self, span: Span, name: Name, declaration: Declaration) {
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.types.insert(name, declaration).is_some_and(|previous| previous != declaration) {
+            self.resolution.errors.push(ResolveError::Duplicate(span, name));
+        }
+    }
+
+    fn check_unique<I>(&mut self, names: I)
+        where
+            I: IntoIterator<Item = (Span, Name)>,
+    {
+        let mut seen = Vec::new();
+        for (span, name) in names {
+            if seen.contains(&name) {
+                self.resolution.errors.push(ResolveError::Duplicate(span, name));
+            } else {
+                seen.push(name);
+            }
+        }
+    }
+
+    // Declares an item of the file or one it imports under `name`
+    fn declare_item(&mut self, source: &ASTSource, span: Span, name: Name, id: NodeId) {
+        match source.get(id) {
+            AST::Struct(_) => {
+                // Struct names double as their constructor
+                self.declare_type(span, name, Declaration::Node(id));
+                self.declare_value(span, name, Declaration::Node(id));
+            }
+            AST::Trait(_) | AST::TypeAlias(_) => self.declare_type(span, name, Declaration::Node(id)),
+            AST::Function(_) | AST::Variable { .. } => self.declare_value(span, name, Declaration::Node(id)),
+            _ => {}
+        }
+    }
+
+    // Items are declared before any body is resolved so they can be used
+    // ahead of their definition
+    fn declare_items(&mut self, source: &ASTSource, file: usize) {
+        for id in source.file_roots(file) {
+            let id = *id;
+            let span = source.span(id);
+            match source.get(id) {
+                AST::Import(value) => self.declare_import(source, source.cast(id).unwrap(), value),
+                value => {
+                    if let Some(name) = item_name(value) {
+                        self.declare_item(source, span, name, id);
+                    }
+                }
+            }
+        }
+    }
+
+    // Imports that couldn't be loaded were already reported
+    fn declare_import(&mut self, source: &ASTSource, id: NodeId<Import>, value: &Import) {
+        let Some(file) = source.imported(id) else { return };
+        let span = source.span(id.untyped());
+        match &value.names {
+            ImportNames::All => {
+                for (name, item) in exports(source, file) {
+                    self.declare_item(source, span, name, item);
+                }
+            }
+            // Every type is declared so one that isn't `pub` is reported as
+            // private rather than unknown where it is used
+            ImportNames::Namespace(namespace) => {
+                self.declare_value(span, *namespace, Declaration::Node(id.untyped()));
+                for item in source.file_roots(file) {
+                    let Some(name) = item_name(source.get(*item)) else { continue };
+                    if matches!(source.get(*item), AST::Struct(_) | AST::Trait(_) | AST::TypeAlias(_)) {
+                        self.declare_type(span, qualified(*namespace, name), Declaration::Node(*item));
+                    }
+                }
+            }
+            ImportNames::Listed(names) => {
+                for name in names {
+                    let items: Vec<_> = source.file_roots(file).iter()
+                        .filter(|item| item_name(source.get(**item)) == Some(name.name))
+                        .collect();
+                    if items.is_empty() {
+                        self.resolution.errors.push(ResolveError::NoItem(name.span, value.file(), name.name));
+                    }
+                    for item in items {
+                        if !is_public(source.get(*item)) {
+                            self.resolution.errors.push(ResolveError::Private(name.span, name.name));
+                        }
+                        self.declare_item(source, name.span, name.alias.unwrap_or(name.name), *item);
+                    }
+                }
+            }
+        }
+    }
+
+    // Import of the namespace an identifier refers to, if it does
+    fn namespace(&self, source: &ASTSource, id: NodeId) -> Option<NodeId<Import>> {
+        return match self.resolution.value(id)? {
+            Declaration::Node(node) => source.cast::<Import>(node),
+            _ => None,
+        };
+    }
+
+    // Static functions and constants of the impls of the type a path names
+    fn resolve_paths(&mut self, source: &ASTSource) {
+        for id in std::mem::take(&mut self.paths) {
+            let AST::Path { data_type, name } = source.get(id) else { continue };
+            // Unknown types were already reported
+            let Some(target) = self.resolution.type_of(id) else { continue };
+            let mut found = None;
+            for (impl_id, impl_target) in self.resolution.impls.iter() {
+                let Some(impl_id) = source.cast::<SpewImpl>(impl_id) else { continue };
+                if impl_target.target != Some(target) {
+                    continue;
+                }
+                let value = source.get(impl_id);
+                let constant = value.constants.iter().copied().find(|constant| match source.get(*constant) {
+                    AST::Variable { variable, .. } => variable.name == *name,
+                    _ => false,
+                });
+                let function = value.functions.iter()
+                    .find(|function| source.get(**function).stub.name == *name)
+                    .map(|function| function.untyped());
+                found = found.or(constant).or(function);
+            }
+            match found.map(|member| source.get(member)) {
+                Some(AST::Function(function)) if !is_static(&function.stub) => {
+                    self.resolution.errors.push(ResolveError::NotStatic(source.span(id), data_type.name, *name));
+                }
+                Some(_) => {
+                    let member = found.unwrap();
+                    if is_private(source, member) && !source.same_file(source.span(member), source.span(id)) {
+                        self.resolution.errors.push(ResolveError::Private(source.span(id), *name));
+                    }
+                    self.resolution.values.insert(id, Declaration::Node(member));
+                }
+                None => self.resolution.errors.push(ResolveError::NoStatic(source.span(id), data_type.name, *name)),
+            }
+        }
+    }
+
+    fn visit_scoped(&mut self, source: &ASTSource, contents: &[NodeId]) {
+        self.scopes.push(Scope::default());
+        for statement in contents {
+            self.visit_node(source, *statement);
+        }
+        self.scopes.pop();
+    }
+}
+
+impl<'a> Visitor<'a> for Resolver {
+    // Every file has a module scope of its own with the items it imports
+    fn visit_source(&mut self, source: &'a ASTSource) {
+        for file in 0..source.files().len().max(1) {
+            self.scopes = vec![module()];
+            self.declare_items(source, file);
+            for id in source.file_roots(file) {
+                self.visit_node(source, *id);
+            }
+        }
+        self.resolve_paths(source);
+    }
+
+    fn visit_struct(&mut self, source: &'a ASTSource, id: NodeId<SpewStruct>, value: &'a SpewStruct) {
+        let names: Vec<_> = value.properties.iter()
+            .map(|property| (source.span(*property), source.get(*property).name))
+            .collect();
+        self.check_unique(names);
+        visit::walk_struct(self, source, id, value);
+    }
+
+    fn visit_trait(&mut self, source: &'a ASTSource, id: NodeId<SpewTrait>, value: &'a SpewTrait) {
+        let names: Vec<_> = value.functions.iter()
+            .map(|function| (source.span(*function), source.get(*function).name))
+            .collect();
+        self.check_unique(names);
+        let span = source.span(id.untyped());
+        let supertraits = value.supertraits.iter()
+            .filter_map(|name| self.lookup_trait(source, span, *name))
+            .collect();
+        self.resolution.supertraits.insert(id, supertraits);
+        visit::walk_trait(self, source, id, value);
+    }
+
+    fn visit_function_stub(&mut self, source: &'a ASTSource, id: NodeId<FunctionStub>, value: &'a FunctionStub) {
+        // Arguments of a stub only need to be unique
+        self.scopes.push(Scope::default());
+        visit::walk_function_stub(self, source, id, value);
+        self.scopes.pop();
+    }
+
+    fn visit_impl(&mut self, source: &'a ASTSource, id: NodeId<SpewImpl>, value: &'a SpewImpl) {
+        let span = source.span(id);
+        let trait_of = value.trait_name.and_then(|name| self.lookup_trait(source, span, name));
+        let target = self.lookup_type(value.name);
+        if target.is_none() {
+            self.resolution.errors.push(ResolveError::UndefinedType(span, value.name));
+        }
+        self.resolution.impls.insert(id, ImplTarget { trait_of, target });
+
+        let constants = value.constants.iter().filter_map(|constant| match source.get(*constant) {
+            AST::Variable { variable, .. } => Some((source.span(*constant), variable.name)),
+            _ => None,
+        });
+        let functions = value.functions.iter()
+            .map(|function| (source.span(function.untyped()), source.get(*function).stub.name));
+        let names: Vec<_> = constants.chain(functions).collect();
+        self.check_unique(names);
+        visit::walk_impl(self, source, id, value);
+    }
+
+    fn visit_function(&mut self, source: &'a ASTSource, id: NodeId<SpewFunction>, value: &'a SpewFunction) {
+        self.scopes.push(Scope::default());
+        match source.parent(id.untyped()).and_then(|parent| source.cast::<SpewImpl>(parent)) {
+            // Static functions have no value to be called on
+            Some(parent) if !is_static(&value.stub) => {
+                self.scopes.last_mut().unwrap().values.insert(Name::intern("this"), Declaration::This(parent));
+            }
+            Some(_) => {}
+            None if is_static(&value.stub) => self.resolution.errors.push(ResolveError::Static(source.span(id))),
+            None => {}
+        }
+        for argument in &value.stub.arguments {
+            self.visit_argument(source, *argument, source.get(*argument));
+        }
+        if let Some(return_type) = &value.stub.return_type {
+            self.visit_data_type(source, id.untyped(), return_type);
+        }
+        // The body is a scope of its own so locals can shadow arguments
+        self.visit_scoped(source, &value.body);
+        self.scopes.pop();
+    }
+
+    fn visit_lambda(&mut self, source: &'a ASTSource, id: NodeId<Lambda>, value: &'a Lambda) {
+        // Lambdas see every name of the scopes around them
+        self.scopes.push(Scope::default());
+        for argument in &value.arguments {
+            self.visit_argument(source, *argument, source.get(*argument));
+        }
+        if let Some(return_type) = &value.return_type {
+            self.visit_data_type(source, id.untyped(), return_type);
+        }
+        self.visit_scoped(source, &value.body);
+        self.scopes.pop();
+    }
+
+    fn visit_argument(&mut self, source: &'a ASTSource, id: NodeId<FunctionArgument>, value: &'a FunctionArgument) {
+        self.declare_value(source.span(id.untyped()), value.name, Declaration::Node(id.untyped()));
+        visit::walk_argument(self, source, id, value);
+    }
+
+    fn visit_variable(&mut self, source: &'a ASTSource, id: NodeId, variable: &'a Variable, value: Option<NodeId>) {
+        // The value is resolved first so `let x = x` refers to an outer `x`
+        visit::walk_variable(self, source, id, variable, value);
+        let parent = source.parent(id);
+        let member = parent.is_some_and(|parent| source.cast::<SpewImpl>(parent).is_some());
+        if variable.modifiers.contains(&Modifier::Static) && !member {
+            self.resolution.errors.push(ResolveError::Static(source.span(id)));
+        }
+        if variable.modifiers.contains(&Modifier::Inline) {
+            self.resolution.errors.push(ResolveError::Inline(source.span(id)));
+        }
+        if variable.constant && variable.modifiers.contains(&Modifier::Mutable) {
+            self.resolution.errors.push(ResolveError::MutableConstant(source.span(id)));
+        }
+        // Top level variables were declared with the other items and those
+        // of impls are only found through their type
+        if parent.is_some() && !member {
+            self.declare_value(source.span(id), variable.name, Declaration::Node(id));
+        }
+    }
+
+    fn visit_identifier(&mut self, source: &'a ASTSource, id: NodeId, name: Name) {
+        match self.lookup_value(name) {
+            Some(declaration) => {
+                self.resolution.values.insert(id, declaration);
+            }
+            None => self.resolution.errors.push(ResolveError::Undefined(source.span(id), name)),
+        }
+        let member = source.parent(id).map(|parent| source.get(parent));
+        if !matches!(member, Some(AST::Member { target, .. }) if *target == id) && self.namespace(source, id).is_some() {
+            self.resolution.errors.push(ResolveError::Namespace(source.span(id), name));
+        }
+    }
+
+    // Items of a namespace are members of its name
+    fn visit_member(&mut self, source: &'a ASTSource, id: NodeId, target: NodeId, name: Name) {
+        visit::walk_member(self, source, id, target, name);
+        let Some(import) = self.namespace(source, target) else { return };
+        let Some(file) = source.imported(import) else { return };
+        // Types of a namespace are only used as `other.Name`
+        let item = source.file_roots(file).iter()
+            .copied()
+            .filter(|item| !matches!(source.get(*item), AST::Trait(_) | AST::TypeAlias(_)))
+            .find(|item| item_name(source.get(*item)) == Some(name));
+        match item {
+            Some(item) => {
+                if !is_public(source.get(item)) {
+                    self.resolution.errors.push(ResolveError::Private(source.span(id), name));
+                }
+                self.resolution.values.insert(id, Declaration::Node(item));
+            }
+            None => {
+                let path = source.get(import).file();
+                self.resolution.errors.push(ResolveError::NoItem(source.span(id), path, name));
+            }
+        }
+    }
+
+    fn visit_condition(
+        &mut self,
+        source: &'a ASTSource,
+        _id: NodeId,
+        condition: NodeId,
+        contents: &'a [NodeId],
+        otherwise: Option<NodeId>,
+    ) {
+        self.visit_node(source, condition);
+        self.visit_scoped(source, contents);
+        if let Some(otherwise) = otherwise {
+            self.visit_node(source, otherwise);
+        }
+    }
+
+    fn visit_while(&mut self, source: &'a ASTSource, _id: NodeId, condition: NodeId, contents: &'a [NodeId]) {
+        self.visit_node(source, condition);
+        self.visit_scoped(source, contents);
+    }
+
+    fn visit_arm(&mut self, source: &'a ASTSource, id: NodeId<MatchArm>, value: &'a MatchArm) {
+        // Bindings are only visible inside of their arm
+        self.scopes.push(Scope::default());
+        if let Pattern::Binding(name) = value.pattern {
+            self.declare_value(source.span(id), name, Declaration::Node(id.untyped()));
+        }
+        visit::walk_arm(self, source, id, value);
+        self.scopes.pop();
+    }
+
+    fn visit_path(&mut self, source: &'a ASTSource, id: NodeId, data_type: &'a DataType, name: Name) {
+        visit::walk_path(self, source, id, data_type, name);
+        self.paths.push(id);
+    }
+
+    fn visit_block(&mut self, source: &'a ASTSource, _id: NodeId, contents: &'a [NodeId]) {
+        self.visit_scoped(source, contents);
+    }
+
+    fn visit_data_type(&mut self, source: &'a ASTSource, id: NodeId, value: &'a DataType) {
+        for member in value.members() {
+            let declaration = self.lookup_type(member.name);
+            self.resolution.types.get_or_default(id).push(declaration);
+            match declaration {
+                Some(declaration) => {
+                    // Only types of namespaces are declared whether they are `pub` or not
+                    if let Declaration::Node(id) = declaration {
+                        let qualified = member.name.as_str().contains('.');
+                        if qualified && !is_public(source.get(id)) && !source.same_file(source.span(id), member.span) {
+                            self.resolution.errors.push(ResolveError::Private(member.span, member.name));
+                        }
+                    }
+                }
+                None => self.resolution.errors.push(ResolveError::UndefinedType(member.span, member.name)),
+            }
+        }
+    }
+}
+
+// Builtins share the module scope so items can't redefine them
+fn module() -> Scope {
+    let mut module = Scope::default();
+    for name in BUILTIN_TYPES {
+        let name = Name::intern(name);
+        module.types.insert(name, Declaration::Builtin(name));
+    }
+    for name in BUILTIN_FUNCTIONS {
+        let name = Name::intern(name);
+        module.values.insert(name, Declaration::Builtin(name));
+    }
+    return module;
+}
+
+/// Binds every identifier and type reference in the tree to its declaration.
+/// Errors are sorted by position.
+pub fn resolve(source: &ASTSource) -> Resolution {
+    let mut resolver = Resolver {
+        scopes: Vec::new(),
+        paths: Vec::new(),
+        resolution: Resolution::default(),
+    };
+    resolver.visit_source(source);
+    let mut resolution = resolver.resolution;
+    resolution.errors.sort_by_key(|err| err.span().start);
+    return resolution;
+}
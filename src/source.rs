@@ -0,0 +1,152 @@
+/// Unit used to measure offsets and columns. Byte offsets are what the lexer
+/// works in, editors usually want chars or UTF-16 code units (LSP default).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16,
+    Char,
+}
+
+impl Encoding {
+    fn width(&self, char: char) -> usize {
+        return match self {
+            Encoding::Utf8 => char.len_utf8(),
+            Encoding::Utf16 => char.len_utf16(),
+            Encoding::Char => 1,
+        };
+    }
+}
+
+/// Zero based line and column, the column is measured in whatever
+/// encoding was requested when the position was created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Index of the line starts in a source text used to convert between byte
+/// offsets and line/column positions in any of the supported encodings.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex<'_> {
+    pub fn new(text: &str) -> LineIndex<'_> {
+        let mut line_starts = vec![0];
+        for (offset, char) in text.char_indices() {
+            if char == '\n' {
+                line_starts.push(offset + 1);
+            }
+        }
+        return LineIndex { text, line_starts };
+    }
+
+    pub fn line_count(&self) -> usize {
+        return self.line_starts.len();
+    }
+
+    /// The byte range of the provided line excluding its line terminator
+    pub fn line_range(&self, line: usize) -> Option<(usize, usize)> {
+        let start = *self.line_starts.get(line)?;
+        let mut end = match self.line_starts.get(line + 1) {
+            Some(next) => next - 1,
+            None => self.text.len(),
+        };
+        if end > start && self.text.as_bytes()[end - 1] == b'\r' {
+            end -= 1;
+        }
+        return Some((start, end));
+    }
+
+    /// Finds the line containing the provided byte offset
+    pub fn line_of(&self, offset: usize) -> usize {
+        return match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+    }
+
+    /// Clamps a byte offset to the text length and moves it back onto the
+    /// start of the char it falls inside of
+    fn floor_offset(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        return offset;
+    }
+
+    /// Converts a byte offset into a line/column position
+    pub fn position(&self, offset: usize, encoding: Encoding) -> Position {
+        let offset = self.floor_offset(offset);
+        let line = self.line_of(offset);
+        let start = self.line_starts[line];
+        let column = self.text[start..offset]
+            .chars()
+            .map(|char| encoding.width(char))
+            .sum();
+        return Position { line, column };
+    }
+
+    /// Converts a line/column position back into a byte offset. Columns past
+    /// the end of the line or inside a char are clamped the same way LSP does.
+    pub fn offset(&self, position: Position, encoding: Encoding) -> Option<usize> {
+        let (start, end) = self.line_range(position.line)?;
+        let mut units = 0;
+        for (offset, char) in self.text[start..end].char_indices() {
+            units += encoding.width(char);
+            if units > position.column {
+                return Some(start + offset);
+            }
+        }
+        return Some(end);
+    }
+
+    /// Converts a byte offset into an absolute offset measured in `encoding`
+    pub fn units_of(&self, offset: usize, encoding: Encoding) -> usize {
+        let offset = self.floor_offset(offset);
+        return self.text[..offset]
+            .chars()
+            .map(|char| encoding.width(char))
+            .sum();
+    }
+
+    /// Converts an absolute offset measured in `encoding` back into a byte offset
+    pub fn offset_of_units(&self, units: usize, encoding: Encoding) -> usize {
+        let mut total = 0;
+        for (offset, char) in self.text.char_indices() {
+            total += encoding.width(char);
+            if total > units {
+                return offset;
+            }
+        }
+        return self.text.len();
+    }
+}
+
+/// Byte range in the source text, end is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        return Span { start, end };
+    }
+
+    /// Whether another span lies within this one
+    pub fn contains(&self, other: Span) -> bool {
+        return self.start <= other.start && other.end <= self.end;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
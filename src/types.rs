@@ -0,0 +1,212 @@
+use std::fmt;
+use std::fmt::Display;
+
+use crate::ast::{NodeId, SpewStruct, SpewTrait, TypeAlias};
+use crate::intern::Name;
+
+/// Type of a value as seen by the checker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Str,
+    Num,
+    Obj,
+    Bool,
+    Null,
+    // Opts out of checking, converts to and from every type with a value
+    Any,
+    Ndef,
+    // Absence of a value, only valid as a return type
+    Void,
+    Struct(Name, NodeId<SpewStruct>),
+    Trait(Name, NodeId<SpewTrait>),
+    // Alias of two or more structs or traits, values have the properties and
+    // functions of all of them
+    Intersection(Name, NodeId<TypeAlias>),
+    Function(Box<Signature>),
+    // Any one of two or more types, built with `Type::union` so null and
+    // ndef are kept outside as `Nullable` and `Optional`
+    Union(Vec<Type>),
+    // `T?`, the type or null
+    Nullable(Box<Type>),
+    // Optional struct properties, the type or ndef. Wraps Nullable when
+    // both apply.
+    Optional(Box<Type>),
+    // Type that is still being inferred, never left in a finished check
+    Var(usize),
+}
+
+/// Argument and return types of something callable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub arguments: Vec<Type>,
+    pub return_type: Type,
+}
+
+impl Type {
+    /// Type for the name of a builtin type
+    pub fn builtin(name: &str) -> Option<Type> {
+        return match name {
+            "str" => Some(Type::Str),
+            "num" => Some(Type::Num),
+            "obj" => Some(Type::Obj),
+            "bool" => Some(Type::Bool),
+            "null" => Some(Type::Null),
+            "any" => Some(Type::Any),
+            "ndef" => Some(Type::Ndef),
+            "void" => Some(Type::Void),
+            _ => None,
+        };
+    }
+
+    /// Name of a builtin type
+    pub fn builtin_name(&self) -> Option<&'static str> {
+        return match self {
+            Type::Str => Some("str"),
+            Type::Num => Some("num"),
+            Type::Obj => Some("obj"),
+            Type::Bool => Some("bool"),
+            Type::Null => Some("null"),
+            Type::Any => Some("any"),
+            Type::Ndef => Some("ndef"),
+            Type::Void => Some("void"),
+            _ => None,
+        };
+    }
+
+    pub fn function(arguments: Vec<Type>, return_type: Type) -> Type {
+        return Type::Function(Box::new(Signature {
+            arguments,
+            return_type,
+        }));
+    }
+
+    /// Type of a value that is any of `members`. Nested unions are flattened,
+    /// null and ndef become `T?` and `T | ndef` and a single type is kept as
+    /// is. Nothing at all is `any`.
+    pub fn union(members: Vec<Type>) -> Type {
+        let mut found = Vec::new();
+        let (mut null, mut ndef) = (false, false);
+        for member in members.iter().flat_map(|member| member.alternatives()) {
+            match member {
+                Type::Any => return Type::Any,
+                Type::Null => null = true,
+                Type::Ndef => ndef = true,
+                member if !found.contains(&member) => found.push(member),
+                _ => {}
+            }
+        }
+        let mut result = match found.len() {
+            0 if null => Type::Null,
+            0 if ndef => Type::Ndef,
+            0 => return Type::Any,
+            1 => found.pop().unwrap(),
+            _ => Type::Union(found),
+        };
+        if null {
+            result = result.nullable();
+        }
+        if ndef {
+            result = result.optional();
+        }
+        return result;
+    }
+
+    /// Every type a value of this type can be, null and ndef included
+    pub fn alternatives(&self) -> Vec<Type> {
+        return match self {
+            Type::Union(members) => members.clone(),
+            Type::Nullable(value) => {
+                let mut found = value.alternatives();
+                found.push(Type::Null);
+                found
+            }
+            Type::Optional(value) => {
+                let mut found = value.alternatives();
+                found.push(Type::Ndef);
+                found
+            }
+            value => vec![value.clone()],
+        };
+    }
+
+    /// The type or null, types that already include null are kept as is
+    pub fn nullable(self) -> Type {
+        return match self {
+            Type::Nullable(_) | Type::Null | Type::Any => self,
+            Type::Optional(value) => Type::Optional(Box::new(value.nullable())),
+            value => Type::Nullable(Box::new(value)),
+        };
+    }
+
+    /// The type or ndef
+    pub fn optional(self) -> Type {
+        return match self {
+            Type::Optional(_) | Type::Ndef | Type::Any => self,
+            value => Type::Optional(Box::new(value)),
+        };
+    }
+
+    pub fn includes_null(&self) -> bool {
+        return match self {
+            Type::Null | Type::Nullable(_) => true,
+            Type::Optional(value) => value.includes_null(),
+            _ => false,
+        };
+    }
+
+    pub fn includes_ndef(&self) -> bool {
+        return matches!(self, Type::Ndef | Type::Optional(_));
+    }
+
+    /// The type after ruling out null
+    pub fn without_null(&self) -> Type {
+        return match self {
+            Type::Nullable(value) => (**value).clone(),
+            Type::Optional(value) => value.without_null().optional(),
+            value => value.clone(),
+        };
+    }
+
+    /// The type after ruling out ndef
+    pub fn without_ndef(&self) -> Type {
+        return match self {
+            Type::Optional(value) => (**value).clone(),
+            value => value.clone(),
+        };
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Str | Type::Num | Type::Obj | Type::Bool | Type::Null | Type::Any | Type::Ndef | Type::Void => {
+                f.write_str(self.builtin_name().unwrap())
+            }
+            Type::Struct(name, _) | Type::Trait(name, _) | Type::Intersection(name, _) => write!(f, "{}", name),
+            Type::Function(signature) => {
+                f.write_str("fun(")?;
+                for (i, argument) in signature.arguments.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ") -> {}", signature.return_type)
+            }
+            Type::Union(members) => {
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{}", member)?;
+                }
+                Ok(())
+            }
+            // `str | num?` would read as only the last one being nullable
+            Type::Nullable(value) if matches!(**value, Type::Union(_)) => write!(f, "({})?", value),
+            Type::Nullable(value) => write!(f, "{}?", value),
+            Type::Optional(value) => write!(f, "{} | ndef", value),
+            Type::Var(_) => f.write_str("_"),
+        }
+    }
+}
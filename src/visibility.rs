@@ -0,0 +1,106 @@
+use std::fmt;
+use std::fmt::Display;
+
+use crate::ast::{ASTSource, NodeId, SpewImpl, SpewTrait, AST};
+use crate::intern::Name;
+use crate::lexer::Modifier;
+use crate::source::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VisibilityError {
+    // `pub` on a variable declared inside of a function
+    Local(Span, Name),
+    // `pub` on a function of the trait, or of an impl of it
+    Trait(Span, Name),
+}
+
+impl VisibilityError {
+    pub fn span(&self) -> Span {
+        return match self {
+            VisibilityError::Local(span, _) => *span,
+            VisibilityError::Trait(span, _) => *span,
+        };
+    }
+}
+
+impl Display for VisibilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VisibilityError::Local(_, name) => {
+                write!(f, "`{}` is local so it can't be `pub`, only items, fields and members of impls can", name)
+            }
+            VisibilityError::Trait(_, name) => {
+                write!(f, "functions of trait `{}` are as visible as the trait, remove `pub`", name)
+            }
+        }
+    }
+}
+
+/// Whether other files can use an item, field or member of an impl
+pub fn is_public(value: &AST) -> bool {
+    return match value {
+        AST::Struct(value) => value.public,
+        AST::Property(value) => value.public,
+        AST::Trait(value) => value.public,
+        AST::TypeAlias(value) => value.public,
+        AST::Function(value) => value.stub.modifiers.contains(&Modifier::Public),
+        AST::FunctionStub(value) => value.modifiers.contains(&Modifier::Public),
+        AST::Variable { variable, .. } => variable.modifiers.contains(&Modifier::Public),
+        _ => false,
+    };
+}
+
+/// Name of a top level item that can be imported
+pub fn item_name(value: &AST) -> Option<Name> {
+    return match value {
+        AST::Struct(value) => Some(value.name),
+        AST::Trait(value) => Some(value.name),
+        AST::TypeAlias(value) => Some(value.name),
+        AST::Function(value) => Some(value.stub.name),
+        AST::Variable { variable, .. } => Some(variable.name),
+        _ => None,
+    };
+}
+
+/// Items of a file that other files can import, with their names in source
+/// order
+pub fn exports(source: &ASTSource, file: usize) -> Vec<(Name, NodeId)> {
+    return source.file_roots(file).iter()
+        .filter(|id| is_public(source.get(**id)))
+        .filter_map(|id| Some((item_name(source.get(*id))?, *id)))
+        .collect();
+}
+
+/// Checks that `pub` is only written where it means something, functions of
+/// traits and their impls follow the trait. Errors are sorted by position.
+pub fn visibility(source: &ASTSource) -> Vec<VisibilityError> {
+    let mut errors = Vec::new();
+    for id in source.ids() {
+        let value = source.get(id);
+        if !is_public(value) {
+            continue;
+        }
+        let parent = source.parent(id);
+        match value {
+            // Top level variables and constants of impls are members of the file
+            AST::Variable { variable, .. }
+                if parent.is_some_and(|parent| source.cast::<SpewImpl>(parent).is_none()) =>
+            {
+                errors.push(VisibilityError::Local(source.span(id), variable.name));
+            }
+            AST::FunctionStub(_) => {
+                let trait_id = source.cast::<SpewTrait>(parent.unwrap()).unwrap();
+                errors.push(VisibilityError::Trait(source.span(id), source.get(trait_id).name));
+            }
+            AST::Function(_) => {
+                let impl_value = parent.and_then(|parent| source.cast::<SpewImpl>(parent)).map(|parent| source.get(parent));
+                if let Some(trait_name) = impl_value.and_then(|value| value.trait_name) {
+                    errors.push(VisibilityError::Trait(source.span(id), trait_name));
+                }
+            }
+            _ => {}
+        }
+    }
+    errors.sort_by_key(|err| err.span().start);
+    return errors;
+}
@@ -0,0 +1,694 @@
+use crate::ast::{
+    ASTSource, Annotation, DataType, FunctionArgument, FunctionStub, Import, Lambda, Literal, MatchArm, NodeId, Operation, Pattern,
+    SpewFunction, SpewImpl, SpewStruct, SpewTrait, StructProperty, TypeAlias, Variable, AST,
+};
+use crate::intern::Name;
+
+/// Read only traversal over a tree. Every method defaults to walking the
+/// children of its node so implementations only override what they need,
+/// calling the matching `walk_*` function to keep descending.
+pub trait Visitor<'a>: Sized {
+    fn visit_source(&mut self, source: &'a ASTSource) {
+        walk_source(self, source);
+    }
+
+    fn visit_node(&mut self, source: &'a ASTSource, id: NodeId) {
+        walk_node(self, source, id);
+    }
+
+    fn visit_struct(&mut self, source: &'a ASTSource, id: NodeId<SpewStruct>, value: &'a SpewStruct) {
+        walk_struct(self, source, id, value);
+    }
+
+    fn visit_property(&mut self, source: &'a ASTSource, id: NodeId<StructProperty>, value: &'a StructProperty) {
+        walk_property(self, source, id, value);
+    }
+
+    fn visit_trait(&mut self, source: &'a ASTSource, id: NodeId<SpewTrait>, value: &'a SpewTrait) {
+        walk_trait(self, source, id, value);
+    }
+
+    /// Function without a body declared inside of a trait
+    fn visit_function_stub(&mut self, source: &'a ASTSource, id: NodeId<FunctionStub>, value: &'a FunctionStub) {
+        walk_function_stub(self, source, id, value);
+    }
+
+    fn visit_impl(&mut self, source: &'a ASTSource, id: NodeId<SpewImpl>, value: &'a SpewImpl) {
+        walk_impl(self, source, id, value);
+    }
+
+    fn visit_function(&mut self, source: &'a ASTSource, id: NodeId<SpewFunction>, value: &'a SpewFunction) {
+        walk_function(self, source, id, value);
+    }
+
+    fn visit_argument(&mut self, source: &'a ASTSource, id: NodeId<FunctionArgument>, value: &'a FunctionArgument) {
+        walk_argument(self, source, id, value);
+    }
+
+    fn visit_type_alias(&mut self, source: &'a ASTSource, id: NodeId<TypeAlias>, value: &'a TypeAlias) {
+        walk_type_alias(self, source, id, value);
+    }
+
+    fn visit_import(&mut self, _source: &'a ASTSource, _id: NodeId<Import>, _value: &'a Import) {}
+
+    fn visit_variable(&mut self, source: &'a ASTSource, id: NodeId, variable: &'a Variable, value: Option<NodeId>) {
+        walk_variable(self, source, id, variable, value);
+    }
+
+    fn visit_literal(&mut self, _source: &'a ASTSource, _id: NodeId, _value: &'a Literal) {}
+
+    fn visit_identifier(&mut self, _source: &'a ASTSource, _id: NodeId, _name: Name) {}
+
+    fn visit_operation(&mut self, source: &'a ASTSource, id: NodeId, value: &'a Operation) {
+        walk_operation(self, source, id, value);
+    }
+
+    fn visit_call(&mut self, source: &'a ASTSource, id: NodeId, target: NodeId, arguments: &'a [NodeId]) {
+        walk_call(self, source, id, target, arguments);
+    }
+
+    fn visit_member(&mut self, source: &'a ASTSource, id: NodeId, target: NodeId, name: Name) {
+        walk_member(self, source, id, target, name);
+    }
+
+    fn visit_path(&mut self, source: &'a ASTSource, id: NodeId, data_type: &'a DataType, name: Name) {
+        walk_path(self, source, id, data_type, name);
+    }
+
+    fn visit_is(&mut self, source: &'a ASTSource, id: NodeId, value: NodeId, data_type: &'a DataType) {
+        walk_is(self, source, id, value, data_type);
+    }
+
+    fn visit_assign(&mut self, source: &'a ASTSource, id: NodeId, target: NodeId, value: NodeId) {
+        walk_assign(self, source, id, target, value);
+    }
+
+    fn visit_condition(
+        &mut self,
+        source: &'a ASTSource,
+        id: NodeId,
+        condition: NodeId,
+        contents: &'a [NodeId],
+        otherwise: Option<NodeId>,
+    ) {
+        walk_condition(self, source, id, condition, contents, otherwise);
+    }
+
+    fn visit_while(&mut self, source: &'a ASTSource, id: NodeId, condition: NodeId, contents: &'a [NodeId]) {
+        walk_while(self, source, id, condition, contents);
+    }
+
+    fn visit_break(&mut self, _source: &'a ASTSource, _id: NodeId) {}
+
+    fn visit_return(&mut self, source: &'a ASTSource, id: NodeId, value: Option<NodeId>) {
+        walk_return(self, source, id, value);
+    }
+
+    fn visit_block(&mut self, source: &'a ASTSource, id: NodeId, contents: &'a [NodeId]) {
+        walk_block(self, source, id, contents);
+    }
+
+    fn visit_match(&mut self, source: &'a ASTSource, id: NodeId, value: NodeId, arms: &'a [NodeId<MatchArm>]) {
+        walk_match(self, source, id, value, arms);
+    }
+
+    fn visit_arm(&mut self, source: &'a ASTSource, id: NodeId<MatchArm>, value: &'a MatchArm) {
+        walk_arm(self, source, id, value);
+    }
+
+    fn visit_lambda(&mut self, source: &'a ASTSource, id: NodeId<Lambda>, value: &'a Lambda) {
+        walk_lambda(self, source, id, value);
+    }
+
+    /// A type written in the node `id`
+    fn visit_data_type(&mut self, _source: &'a ASTSource, _id: NodeId, _value: &'a DataType) {}
+
+    fn visit_annotation(&mut self, _source: &'a ASTSource, _value: &'a Annotation) {}
+}
+
+pub fn walk_source<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource) {
+    for id in source.roots() {
+        visitor.visit_node(source, *id);
+    }
+}
+
+/// Dispatches to the visit method for the kind of node `id` points at
+pub fn walk_node<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, id: NodeId) {
+    match source.get(id) {
+        AST::Variable { variable, value } => visitor.visit_variable(source, id, variable, *value),
+        AST::Literal(value) => visitor.visit_literal(source, id, value),
+        AST::Identifier(name) => visitor.visit_identifier(source, id, *name),
+        AST::Operation(value) => visitor.visit_operation(source, id, value),
+        AST::Call { target, arguments } => visitor.visit_call(source, id, *target, arguments),
+        AST::Member { target, name } => visitor.visit_member(source, id, *target, *name),
+        AST::Path { data_type, name } => visitor.visit_path(source, id, data_type, *name),
+        AST::Is { value, data_type } => visitor.visit_is(source, id, *value, data_type),
+        AST::Assign { target, value } => visitor.visit_assign(source, id, *target, *value),
+        AST::ConditionBlock {
+            condition,
+            contents,
+            otherwise,
+        } => visitor.visit_condition(source, id, *condition, contents, *otherwise),
+        AST::While { condition, contents } => visitor.visit_while(source, id, *condition, contents),
+        AST::Break => visitor.visit_break(source, id),
+        AST::Return(value) => visitor.visit_return(source, id, *value),
+        AST::Block(contents) => visitor.visit_block(source, id, contents),
+        AST::Match { value, arms } => visitor.visit_match(source, id, *value, arms),
+        AST::Arm(value) => visitor.visit_arm(source, source.cast(id).unwrap(), value),
+        AST::Lambda(value) => visitor.visit_lambda(source, source.cast(id).unwrap(), value),
+        AST::Struct(value) => visitor.visit_struct(source, source.cast(id).unwrap(), value),
+        AST::Property(value) => visitor.visit_property(source, source.cast(id).unwrap(), value),
+        AST::Trait(value) => visitor.visit_trait(source, source.cast(id).unwrap(), value),
+        AST::FunctionStub(value) => visitor.visit_function_stub(source, source.cast(id).unwrap(), value),
+        AST::Impl(value) => visitor.visit_impl(source, source.cast(id).unwrap(), value),
+        AST::Function(value) => visitor.visit_function(source, source.cast(id).unwrap(), value),
+        AST::Argument(value) => visitor.visit_argument(source, source.cast(id).unwrap(), value),
+        AST::TypeAlias(value) => visitor.visit_type_alias(source, source.cast(id).unwrap(), value),
+        AST::Import(value) => visitor.visit_import(source, source.cast(id).unwrap(), value),
+    }
+}
+
+fn walk_annotations<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, annotations: &'a [Annotation]) {
+    for annotation in annotations {
+        visitor.visit_annotation(source, annotation);
+    }
+}
+
+// Shared by trait stubs and implemented functions
+fn walk_signature<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, id: NodeId, stub: &'a FunctionStub) {
+    walk_annotations(visitor, source, &stub.annotations);
+    for argument in &stub.arguments {
+        visitor.visit_argument(source, *argument, source.get(*argument));
+    }
+    if let Some(return_type) = &stub.return_type {
+        visitor.visit_data_type(source, id, return_type);
+    }
+}
+
+pub fn walk_struct<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    _id: NodeId<SpewStruct>,
+    value: &'a SpewStruct,
+) {
+    walk_annotations(visitor, source, &value.annotations);
+    for property in &value.properties {
+        visitor.visit_property(source, *property, source.get(*property));
+    }
+}
+
+pub fn walk_property<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    id: NodeId<StructProperty>,
+    value: &'a StructProperty,
+) {
+    walk_annotations(visitor, source, &value.annotations);
+    visitor.visit_data_type(source, id.untyped(), &value.type_of);
+}
+
+pub fn walk_trait<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    _id: NodeId<SpewTrait>,
+    value: &'a SpewTrait,
+) {
+    walk_annotations(visitor, source, &value.annotations);
+    for function in &value.functions {
+        visitor.visit_function_stub(source, *function, source.get(*function));
+    }
+}
+
+pub fn walk_function_stub<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    id: NodeId<FunctionStub>,
+    value: &'a FunctionStub,
+) {
+    walk_signature(visitor, source, id.untyped(), value);
+}
+
+pub fn walk_impl<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, _id: NodeId<SpewImpl>, value: &'a SpewImpl) {
+    for constant in &value.constants {
+        visitor.visit_node(source, *constant);
+    }
+    for function in &value.functions {
+        visitor.visit_function(source, *function, source.get(*function));
+    }
+}
+
+pub fn walk_function<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    id: NodeId<SpewFunction>,
+    value: &'a SpewFunction,
+) {
+    walk_signature(visitor, source, id.untyped(), &value.stub);
+    for statement in &value.body {
+        visitor.visit_node(source, *statement);
+    }
+}
+
+pub fn walk_argument<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    id: NodeId<FunctionArgument>,
+    value: &'a FunctionArgument,
+) {
+    if let Some(data_type) = &value.data_type {
+        visitor.visit_data_type(source, id.untyped(), data_type);
+    }
+}
+
+pub fn walk_variable<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    id: NodeId,
+    variable: &'a Variable,
+    value: Option<NodeId>,
+) {
+    if let Some(type_of) = &variable.type_of {
+        visitor.visit_data_type(source, id, type_of);
+    }
+    if let Some(value) = value {
+        visitor.visit_node(source, value);
+    }
+}
+
+/// Walks the operands of a whole chain of binary operations in a loop, so
+/// visit_operation is only called for the outermost operation of `a + b + c`
+pub fn walk_operation<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, id: NodeId, value: &'a Operation) {
+    if value.right.is_none() {
+        visitor.visit_node(source, value.left);
+        return;
+    }
+    let (first, operations) = source.chain(id);
+    visitor.visit_node(source, first);
+    for operation in operations {
+        if let AST::Operation(Operation { right: Some(right), .. }) = source.get(operation) {
+            visitor.visit_node(source, *right);
+        }
+    }
+}
+
+pub fn walk_call<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    _id: NodeId,
+    target: NodeId,
+    arguments: &'a [NodeId],
+) {
+    visitor.visit_node(source, target);
+    for argument in arguments {
+        visitor.visit_node(source, *argument);
+    }
+}
+
+pub fn walk_member<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, _id: NodeId, target: NodeId, _name: Name) {
+    visitor.visit_node(source, target);
+}
+
+pub fn walk_path<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    id: NodeId,
+    data_type: &'a DataType,
+    _name: Name,
+) {
+    visitor.visit_data_type(source, id, data_type);
+}
+
+pub fn walk_is<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    id: NodeId,
+    value: NodeId,
+    data_type: &'a DataType,
+) {
+    visitor.visit_node(source, value);
+    visitor.visit_data_type(source, id, data_type);
+}
+
+pub fn walk_assign<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, _id: NodeId, target: NodeId, value: NodeId) {
+    visitor.visit_node(source, target);
+    visitor.visit_node(source, value);
+}
+
+pub fn walk_condition<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    _id: NodeId,
+    condition: NodeId,
+    contents: &'a [NodeId],
+    otherwise: Option<NodeId>,
+) {
+    visitor.visit_node(source, condition);
+    for statement in contents {
+        visitor.visit_node(source, *statement);
+    }
+    if let Some(otherwise) = otherwise {
+        visitor.visit_node(source, otherwise);
+    }
+}
+
+pub fn walk_while<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    _id: NodeId,
+    condition: NodeId,
+    contents: &'a [NodeId],
+) {
+    visitor.visit_node(source, condition);
+    for statement in contents {
+        visitor.visit_node(source, *statement);
+    }
+}
+
+pub fn walk_return<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, _id: NodeId, value: Option<NodeId>) {
+    if let Some(value) = value {
+        visitor.visit_node(source, value);
+    }
+}
+
+pub fn walk_block<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, _id: NodeId, contents: &'a [NodeId]) {
+    for statement in contents {
+        visitor.visit_node(source, *statement);
+    }
+}
+
+pub fn walk_match<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    _id: NodeId,
+    value: NodeId,
+    arms: &'a [NodeId<MatchArm>],
+) {
+    visitor.visit_node(source, value);
+    for arm in arms {
+        visitor.visit_arm(source, *arm, source.get(*arm));
+    }
+}
+
+pub fn walk_arm<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, id: NodeId<MatchArm>, value: &'a MatchArm) {
+    if let Pattern::Type(data_type) = &value.pattern {
+        visitor.visit_data_type(source, id.untyped(), data_type);
+    }
+    visitor.visit_node(source, value.body);
+}
+
+pub fn walk_type_alias<'a, V: Visitor<'a>>(
+    visitor: &mut V,
+    source: &'a ASTSource,
+    id: NodeId<TypeAlias>,
+    value: &'a TypeAlias,
+) {
+    for data_type in &value.types {
+        visitor.visit_data_type(source, id.untyped(), data_type);
+    }
+}
+
+pub fn walk_lambda<'a, V: Visitor<'a>>(visitor: &mut V, source: &'a ASTSource, id: NodeId<Lambda>, value: &'a Lambda) {
+    for argument in &value.arguments {
+        visitor.visit_argument(source, *argument, source.get(*argument));
+    }
+    if let Some(return_type) = &value.return_type {
+        visitor.visit_data_type(source, id.untyped(), return_type);
+    }
+    for statement in &value.body {
+        visitor.visit_node(source, *statement);
+    }
+}
+
+/// Rewriting traversal over a tree. Methods receive ids rather than values
+/// so they are free to change the node through `ASTSource::get_mut` or swap
+/// it out entirely with `ASTSource::replace` before or after walking it.
+pub trait MutVisitor: Sized {
+    fn visit_source(&mut self, source: &mut ASTSource) {
+        walk_source_mut(self, source);
+    }
+
+    fn visit_node(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_node_mut(self, source, id);
+    }
+
+    fn visit_struct(&mut self, source: &mut ASTSource, id: NodeId<SpewStruct>) {
+        walk_struct_mut(self, source, id);
+    }
+
+    fn visit_property(&mut self, source: &mut ASTSource, id: NodeId<StructProperty>) {
+        walk_property_mut(self, source, id);
+    }
+
+    fn visit_trait(&mut self, source: &mut ASTSource, id: NodeId<SpewTrait>) {
+        walk_trait_mut(self, source, id);
+    }
+
+    /// Function without a body declared inside of a trait
+    fn visit_function_stub(&mut self, source: &mut ASTSource, id: NodeId<FunctionStub>) {
+        walk_function_stub_mut(self, source, id);
+    }
+
+    fn visit_impl(&mut self, source: &mut ASTSource, id: NodeId<SpewImpl>) {
+        walk_impl_mut(self, source, id);
+    }
+
+    fn visit_function(&mut self, source: &mut ASTSource, id: NodeId<SpewFunction>) {
+        walk_function_mut(self, source, id);
+    }
+
+    fn visit_argument(&mut self, source: &mut ASTSource, id: NodeId<FunctionArgument>) {
+        walk_argument_mut(self, source, id);
+    }
+
+    fn visit_type_alias(&mut self, source: &mut ASTSource, id: NodeId<TypeAlias>) {
+        walk_type_alias_mut(self, source, id);
+    }
+
+    fn visit_import(&mut self, _source: &mut ASTSource, _id: NodeId<Import>) {}
+
+    // The expression and statement nodes without a struct of their own are
+    // all visited through their untyped id
+
+    fn visit_variable(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_literal(&mut self, _source: &mut ASTSource, _id: NodeId) {}
+
+    fn visit_identifier(&mut self, _source: &mut ASTSource, _id: NodeId) {}
+
+    fn visit_operation(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_operation_mut(self, source, id);
+    }
+
+    fn visit_call(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_member(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_path(&mut self, _source: &mut ASTSource, _id: NodeId) {}
+
+    fn visit_is(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_assign(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_condition(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_while(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_break(&mut self, _source: &mut ASTSource, _id: NodeId) {}
+
+    fn visit_return(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_block(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_match(&mut self, source: &mut ASTSource, id: NodeId) {
+        walk_children_mut(self, source, id);
+    }
+
+    fn visit_arm(&mut self, source: &mut ASTSource, id: NodeId<MatchArm>) {
+        walk_children_mut(self, source, id.untyped());
+    }
+
+    fn visit_lambda(&mut self, source: &mut ASTSource, id: NodeId<Lambda>) {
+        walk_lambda_mut(self, source, id);
+    }
+
+    fn visit_data_type(&mut self, _value: &mut DataType) {}
+
+    fn visit_annotation(&mut self, _value: &mut Annotation) {}
+}
+
+pub fn walk_source_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource) {
+    for id in source.roots().to_vec() {
+        visitor.visit_node(source, id);
+    }
+}
+
+/// Dispatches to the visit method for the kind of node `id` points at
+pub fn walk_node_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId) {
+    match source.get(id) {
+        AST::Variable { .. } => visitor.visit_variable(source, id),
+        AST::Literal(_) => visitor.visit_literal(source, id),
+        AST::Identifier(_) => visitor.visit_identifier(source, id),
+        AST::Operation(_) => visitor.visit_operation(source, id),
+        AST::Call { .. } => visitor.visit_call(source, id),
+        AST::Member { .. } => visitor.visit_member(source, id),
+        AST::Path { .. } => visitor.visit_path(source, id),
+        AST::Is { .. } => visitor.visit_is(source, id),
+        AST::Assign { .. } => visitor.visit_assign(source, id),
+        AST::ConditionBlock { .. } => visitor.visit_condition(source, id),
+        AST::While { .. } => visitor.visit_while(source, id),
+        AST::Break => visitor.visit_break(source, id),
+        AST::Return(_) => visitor.visit_return(source, id),
+        AST::Block(_) => visitor.visit_block(source, id),
+        AST::Match { .. } => visitor.visit_match(source, id),
+        AST::Arm(_) => visitor.visit_arm(source, source.cast(id).unwrap()),
+        AST::Lambda(_) => visitor.visit_lambda(source, source.cast(id).unwrap()),
+        AST::Struct(_) => visitor.visit_struct(source, source.cast(id).unwrap()),
+        AST::Property(_) => visitor.visit_property(source, source.cast(id).unwrap()),
+        AST::Trait(_) => visitor.visit_trait(source, source.cast(id).unwrap()),
+        AST::FunctionStub(_) => visitor.visit_function_stub(source, source.cast(id).unwrap()),
+        AST::Impl(_) => visitor.visit_impl(source, source.cast(id).unwrap()),
+        AST::Function(_) => visitor.visit_function(source, source.cast(id).unwrap()),
+        AST::Argument(_) => visitor.visit_argument(source, source.cast(id).unwrap()),
+        AST::TypeAlias(_) => visitor.visit_type_alias(source, source.cast(id).unwrap()),
+        AST::Import(_) => visitor.visit_import(source, source.cast(id).unwrap()),
+    }
+}
+
+/// Visits the data types held directly by a node then each of its children
+/// in source order. Children are collected up front so the visitor can
+/// replace them while walking.
+pub fn walk_children_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId) {
+    match source.get_mut(id) {
+        AST::Variable {
+            variable: Variable {
+                type_of: Some(type_of), ..
+            },
+            ..
+        }
+        | AST::Is { data_type: type_of, .. }
+        | AST::Arm(MatchArm {
+            pattern: Pattern::Type(type_of),
+            ..
+        }) => visitor.visit_data_type(type_of),
+        _ => {}
+    }
+    for child in source.get(id).children() {
+        visitor.visit_node(source, child);
+    }
+}
+
+/// Walks the operands of a whole chain of binary operations in a loop like
+/// walk_operation
+pub fn walk_operation_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId) {
+    let (first, operations) = source.chain(id);
+    if operations.is_empty() {
+        walk_children_mut(visitor, source, id);
+        return;
+    }
+    visitor.visit_node(source, first);
+    for operation in operations {
+        if let AST::Operation(Operation { right: Some(right), .. }) = source.get(operation) {
+            visitor.visit_node(source, *right);
+        }
+    }
+}
+
+fn walk_annotations_mut<V: MutVisitor>(visitor: &mut V, annotations: &mut [Annotation]) {
+    for annotation in annotations {
+        visitor.visit_annotation(annotation);
+    }
+}
+
+// Shared by trait stubs and implemented functions, `id` may point at either
+fn walk_signature_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<FunctionStub>) {
+    let stub = source.get_mut(id);
+    walk_annotations_mut(visitor, &mut stub.annotations);
+    if let Some(return_type) = &mut stub.return_type {
+        visitor.visit_data_type(return_type);
+    }
+    for argument in source.get(id).arguments.clone() {
+        visitor.visit_argument(source, argument);
+    }
+}
+
+pub fn walk_struct_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<SpewStruct>) {
+    walk_annotations_mut(visitor, &mut source.get_mut(id).annotations);
+    for property in source.get(id).properties.clone() {
+        visitor.visit_property(source, property);
+    }
+}
+
+pub fn walk_property_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<StructProperty>) {
+    let property = source.get_mut(id);
+    walk_annotations_mut(visitor, &mut property.annotations);
+    visitor.visit_data_type(&mut property.type_of);
+}
+
+pub fn walk_trait_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<SpewTrait>) {
+    walk_annotations_mut(visitor, &mut source.get_mut(id).annotations);
+    for function in source.get(id).functions.clone() {
+        visitor.visit_function_stub(source, function);
+    }
+}
+
+pub fn walk_function_stub_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<FunctionStub>) {
+    walk_signature_mut(visitor, source, id);
+}
+
+pub fn walk_impl_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<SpewImpl>) {
+    for constant in source.get(id).constants.clone() {
+        visitor.visit_node(source, constant);
+    }
+    for function in source.get(id).functions.clone() {
+        visitor.visit_function(source, function);
+    }
+}
+
+pub fn walk_function_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<SpewFunction>) {
+    walk_signature_mut(visitor, source, source.cast(id.untyped()).unwrap());
+    for statement in source.get(id).body.clone() {
+        visitor.visit_node(source, statement);
+    }
+}
+
+pub fn walk_argument_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<FunctionArgument>) {
+    if let Some(data_type) = &mut source.get_mut(id).data_type {
+        visitor.visit_data_type(data_type);
+    }
+}
+
+pub fn walk_type_alias_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<TypeAlias>) {
+    for data_type in &mut source.get_mut(id).types {
+        visitor.visit_data_type(data_type);
+    }
+}
+
+pub fn walk_lambda_mut<V: MutVisitor>(visitor: &mut V, source: &mut ASTSource, id: NodeId<Lambda>) {
+    for argument in source.get(id).arguments.clone() {
+        visitor.visit_argument(source, argument);
+    }
+    if let Some(return_type) = &mut source.get_mut(id).return_type {
+        visitor.visit_data_type(return_type);
+    }
+    for statement in source.get(id).body.clone() {
+        visitor.visit_node(source, statement);
+    }
+}
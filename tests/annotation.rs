@@ -0,0 +1,57 @@
+use spew::annotation::{AnnotationRegistry, AnnotationSpec, AnnotationTarget, ArgumentKind, ArgumentSpec};
+use spew::ast::ASTSource;
+use spew::lexer::TokenSet;
+
+fn errors(registry: &AnnotationRegistry, text: &str) -> Vec<String> {
+    let source = ASTSource::parse_ast(TokenSet::new(text)).unwrap();
+    registry
+        .validate(&source)
+        .iter()
+        .map(|err| format!("{} at `{}`", err, &text[err.span().start..err.span().end]))
+        .collect()
+}
+
+#[test]
+fn valid() {
+    let text = "@json(name = \"account\", skip = false)\n@deprecated(\"old\")\nstruct A {\n  @json(skip = true)\n  id: num\n}\n@deprecated\ntrait T {\n  @test\n  fun a()\n}\n@test\nfun f() {}";
+    assert_eq!(errors(&AnnotationRegistry::default(), text), Vec::<String>::new());
+}
+
+#[test]
+fn invalid() {
+    let text = "@unknown\nstruct A {\n  @test\n  id: num\n}\n@deprecated\n@deprecated\nfun f() {}\n@json(name = 1)\nstruct B {}\n@deprecated(\"a\", \"b\")\n@json(other = true)\nstruct C {}";
+    assert_eq!(
+        errors(&AnnotationRegistry::default(), text),
+        vec![
+            "unknown annotation `@unknown` at `@unknown`",
+            "`@test` cannot be used on a property at `@test`",
+            "`@deprecated` is used more than once at `@deprecated`",
+            "argument of `@json` must be a str at `name = 1`",
+            "too many arguments for `@deprecated` at `\"b\"`",
+            "`@json` has no argument named `other` at `other = true`",
+        ]
+    );
+}
+
+#[test]
+fn registered() {
+    let mut registry = AnnotationRegistry::empty();
+    registry.register(AnnotationSpec {
+        name: "since",
+        targets: &[AnnotationTarget::Function],
+        arguments: vec![ArgumentSpec {
+            name: Some("version"),
+            kind: ArgumentKind::Number,
+            required: true,
+        }],
+    });
+    assert!(registry.get("since".into()).is_some());
+    assert!(registry.get("test".into()).is_none());
+    assert_eq!(
+        errors(&registry, "@since(version = 2)\nfun a() {}\n@since\nfun b() {}\n@test\nfun c() {}"),
+        vec![
+            "`@since` is missing the `version` argument at `@since`",
+            "unknown annotation `@test` at `@test`",
+        ]
+    );
+}
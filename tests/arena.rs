@@ -0,0 +1,123 @@
+use spew::ast::{
+    ASTSource, Literal, NodeMap, SpewFunction, SpewImpl, SpewStruct, StructProperty, AST,
+};
+use spew::lexer::TokenSet;
+use spew::source::Span;
+
+fn parse(text: &str) -> ASTSource {
+    ASTSource::parse_ast(TokenSet::new(text)).unwrap()
+}
+
+#[test]
+fn parents() {
+    let text = "struct A { id: num }\nimpl A {\n  fun f() { let x = 1 + 2 }\n}";
+    let source = parse(text);
+    assert_eq!(source.roots().len(), 2);
+    for root in source.roots() {
+        assert_eq!(source.parent(*root), None);
+    }
+    // Every child points back at the node that holds it
+    for id in source.ids() {
+        for child in source.get(id).children() {
+            assert_eq!(source.parent(child), Some(id));
+            assert!(child.index() < id.index());
+        }
+    }
+
+    let two = source
+        .ids()
+        .find(|id| matches!(source.get(*id), AST::Literal(Literal::Number(value)) if value == "2"))
+        .unwrap();
+    assert_eq!(&text[source.span(two).start..source.span(two).end], "2");
+    let kinds: Vec<_> = source
+        .ancestors(two)
+        .map(|id| match source.get(id) {
+            AST::Operation(_) => "operation",
+            AST::Variable { .. } => "variable",
+            AST::Function(_) => "function",
+            AST::Impl(_) => "impl",
+            value => panic!("unexpected ancestor {:?}", value),
+        })
+        .collect();
+    assert_eq!(kinds, vec!["operation", "variable", "function", "impl"]);
+    assert_eq!(source.ancestors(two).last(), Some(source.roots()[1]));
+}
+
+#[test]
+fn casts() {
+    let source = parse("struct A { id: num }\nimpl A {\n  fun f() {}\n}");
+    let [item, block] = [source.roots()[0], source.roots()[1]];
+    let value: SpewStruct = source.get(source.cast::<SpewStruct>(item).unwrap()).clone();
+    assert_eq!(value.name, "A");
+    assert!(source.cast::<SpewImpl>(item).is_none());
+    assert!(source.cast::<SpewFunction>(item).is_none());
+    let property = source
+        .cast::<StructProperty>(value.properties[0].untyped())
+        .unwrap();
+    assert_eq!(property, value.properties[0]);
+    assert_eq!(source.get(property).name, "id");
+    assert_eq!(source.parent(property), Some(item));
+
+    let block = source.cast::<SpewImpl>(block).unwrap();
+    let function = source.get(block).functions[0];
+    assert_eq!(source.get(function).stub.name, "f");
+    assert_eq!(source.parent(function), Some(block.untyped()));
+}
+
+#[test]
+fn replaces() {
+    let mut source = parse("fun f() { let x = 1 }");
+    let one = source
+        .ids()
+        .find(|id| matches!(source.get(*id), AST::Literal(_)))
+        .unwrap();
+    let variable = source.parent(one).unwrap();
+    let two = source.alloc(
+        AST::Literal(Literal::Number("2".to_string())),
+        Span::new(0, 0),
+    );
+    let old = source.replace(
+        variable,
+        AST::Variable {
+            variable: match source.get(variable) {
+                AST::Variable { variable, .. } => variable.clone(),
+                _ => unreachable!(),
+            },
+            value: Some(two),
+        },
+    );
+    assert!(matches!(old, AST::Variable { value: Some(value), .. } if value == one));
+    assert_eq!(source.parent(two), Some(variable));
+    assert_eq!(source.get(variable).children(), vec![two]);
+}
+
+#[test]
+fn side_tables() {
+    let source = parse("struct A { id: num name: str }");
+    let properties = source
+        .get(source.cast::<SpewStruct>(source.roots()[0]).unwrap())
+        .properties
+        .clone();
+    let mut map = NodeMap::new();
+    assert!(!map.contains(properties[1]));
+    assert_eq!(map.insert(properties[1], "name"), None);
+    assert_eq!(map.insert(properties[1], "second"), Some("name"));
+    map.insert(properties[0], "first");
+    *map.get_mut(properties[0]).unwrap() = "id";
+    assert_eq!(map.get(properties[0]), Some(&"id"));
+    assert_eq!(map.get(source.roots()[0]), None);
+    assert_eq!(
+        map.iter()
+            .map(|(id, value)| (id, *value))
+            .collect::<Vec<_>>(),
+        vec![
+            (properties[0].untyped(), "id"),
+            (properties[1].untyped(), "second"),
+        ]
+    );
+    assert_eq!(map.remove(properties[0]), Some("id"));
+    assert!(!map.contains(properties[0]));
+    assert_eq!(*map.get_or_default(properties[0]), "");
+    *map.get_or_default(properties[1]) = "name";
+    assert_eq!(map.get(properties[1]), Some(&"name"));
+}
@@ -0,0 +1,251 @@
+use spew::ast::{ASTSource, NodeId, AST};
+use spew::check::{check, TypeCheck};
+use spew::types::Type;
+
+mod common;
+
+fn run(text: &str) -> (ASTSource, TypeCheck) {
+    let (source, resolution) = common::resolved(text);
+    let check = check(&source, &resolution);
+    (source, check)
+}
+
+fn errors(text: &str) -> Vec<String> {
+    common::errors(text, |source, resolution| check(source, resolution).errors)
+}
+
+fn variable(source: &ASTSource, name: &str) -> NodeId {
+    source
+        .ids()
+        .find(|id| matches!(source.get(*id), AST::Variable { variable, .. } if variable.name == name))
+        .unwrap()
+}
+
+#[test]
+fn well_typed() {
+    let text = "struct User { id: num name: str? }\ntrait Named { fun name() -> str }\nimpl Named for User {\n  fun name() -> str { return \"user\" }\n}\nfun greet(n: Named, times: num) -> str {\n  let prefix: str = \"hi \"\n  if times > 1 && !false { return prefix + n.name() }\n  return prefix\n}\nfun main() {\n  let user = User(1, null)\n  let anything: any = 1\n  let text: str = anything\n  let other: obj = user\n  print(greet(user, user.id * 2 - 1))\n  assert(user.id == 1 || user.name != null)\n}";
+    let (source, check) = run(text);
+    assert!(check.errors.is_empty(), "{:?}", check.errors);
+    // Unannotated bindings take the type of their value
+    let user = check.type_of(variable(&source, "user")).unwrap();
+    assert!(matches!(user, Type::Struct(name, _) if *name == "User"));
+    assert_eq!(user.to_string(), "User");
+}
+
+#[test]
+fn annotations_and_returns() {
+    assert_eq!(
+        errors("fun f(a: num) -> str {\n  let b: bool = a\n  if a { return a }\n  return\n}\nfun g() { return 1 }"),
+        vec![
+            "expected `bool`, found `num` at `a`",
+            "expected `bool`, found `num` at `a`",
+            "expected `str`, found `num` at `a`",
+            "expected `str`, found `void` at `return`",
+            "expected `void`, found `num` at `1`",
+        ]
+    );
+}
+
+#[test]
+fn operators() {
+    assert_eq!(
+        errors("fun f(a: num, b: str, c: bool) {\n  a + b;\n  -b\n  !a\n  c < c\n  b == a\n  b + b\n  a % a\n  c & c\n}"),
+        vec![
+            "cannot apply `+` to `num` and `str` at `a + b`",
+            "cannot apply `-` to `str` at `-b`",
+            "cannot apply `!` to `num` at `!a`",
+            "cannot apply `<` to `bool` and `bool` at `c < c`",
+            "cannot apply `==` to `str` and `num` at `b == a`",
+        ]
+    );
+}
+
+#[test]
+fn calls_and_members() {
+    assert_eq!(
+        errors("struct P { x: num }\nimpl P {\n  fun scale(by: num) -> P { return P(this.x * by) }\n}\nfun f(p: P, n: num) {\n  p.scale(\"2\")\n  p.scale()\n  p.y\n  n(1)\n  n.x\n  let v = print(1)\n}"),
+        vec![
+            "expected `num`, found `str` at `\"2\"`",
+            "expected 1 argument, found 0 at `p.scale()`",
+            "`P` has no member `y` at `p.y`",
+            "`num` cannot be called at `n`",
+            "`num` has no member `x` at `n.x`",
+            "expression has no value at `print(1)`",
+        ]
+    );
+}
+
+#[test]
+fn void_values_and_traits() {
+    assert_eq!(
+        errors("struct S { v: void }\ntrait T {}\nfun f(a: void, t: T) {}\nfun g() { f(1, S(1)) }"),
+        vec![
+            "`void` can only be used as a return type at `void`",
+            "`void` can only be used as a return type at `void`",
+            "expected `T`, found `S` at `S(1)`",
+        ]
+    );
+}
+
+#[test]
+fn null_safety() {
+    assert_eq!(
+        errors("struct U { name: str? nick?: str }\nfun f(s: str?, u: U) -> str {\n  let a: str = s\n  let b: str = u.nick\n  u.name.len\n  if s != null { let c: str = s }\n  if s == null || u.name == null { return \"\" }\n  let d: str = s + u.name\n  let e: str = u.nick ?? \"none\"\n  let g: str? = u.nick\n  return s\n}"),
+        vec![
+            "expected `str`, found `str?` which may be null, check it with `!= null` or give a default with `??` at `s`",
+            "expected `str`, found `str | ndef` which may be ndef, check it with `!= ndef` or give a default with `??` at `u.nick`",
+            "cannot access `len` on `str?` which may be null, check it with `!= null` or give a default with `??` at `u.name.len`",
+            "expected `str?`, found `str | ndef` which may be ndef, check it with `!= ndef` or give a default with `??` at `u.nick`",
+        ]
+    );
+}
+
+#[test]
+fn matches() {
+    let text = "fun f(s: str?, n: num) -> str {\n  let a = match s { null => \"none\", text => text }\n  let b = match s { \"x\" => 1, _ => 2 }\n  let c = match n { \"x\" => 1, _ => null }\n  match s {\n    null => { return \"\" },\n    _ => print(s),\n  }\n  return a\n}";
+    let (source, check) = run(text);
+    let found: Vec<_> = check.errors.iter().map(|err| err.to_string()).collect();
+    assert_eq!(found, vec!["a `num` can never match a `str` pattern"]);
+    // Arms after `null` see the value without null
+    assert_eq!(check.type_of(variable(&source, "a")).unwrap().to_string(), "str");
+    assert_eq!(check.type_of(variable(&source, "b")).unwrap().to_string(), "num");
+    assert_eq!(check.type_of(variable(&source, "c")).unwrap().to_string(), "num?");
+}
+
+#[test]
+fn inference() {
+    let text = "let flag = false\nlet add = fun (a, b) { return a + b * 2 }\nfun main() {\n  let id = fun (x) { return x }\n  let n: num = id(1)\n  let s: str = id(\"a\")\n  let apply = fun (f, v: str) { return f(v) }\n  let length = apply(fun (t) { return 1 }, \"x\")\n  let nothing = fun () {}\n  let total = add(1, 2)\n}";
+    let (source, check) = run(text);
+    assert!(check.errors.is_empty(), "{:?}", check.errors);
+    let type_of = |name| check.type_of(variable(&source, name)).unwrap().to_string();
+    assert_eq!(type_of("flag"), "bool");
+    assert_eq!(type_of("add"), "fun(num, num) -> num");
+    assert_eq!(type_of("apply"), "fun(fun(str) -> any, str) -> any");
+    assert_eq!(type_of("length"), "num");
+    assert_eq!(type_of("nothing"), "fun() -> void");
+    // Hovering the `t` argument shows the type inferred from the call
+    let offset = text.find("(t)").unwrap() + 1;
+    let (id, found) = check.hover(&source, offset).unwrap();
+    assert!(matches!(source.get(id), AST::Argument(_)));
+    assert_eq!(found.to_string(), "str");
+}
+
+#[test]
+fn inference_errors() {
+    assert_eq!(
+        errors("fun f() {\n  let g = fun (u) { return u.name }\n  let h = fun (a) { return a }\n  let count: num = h(\"a\")\n  let k = fun (b) { return -b }\n  k(\"b\")\n}"),
+        vec![
+            "type must be known at this point, add a type annotation at `u.name`",
+            "expected `num`, found `str` at `h(\"a\")`",
+            "expected `num`, found `str` at `\"b\"`",
+        ]
+    );
+}
+
+#[test]
+fn intersections() {
+    let text = "struct Test { id: num name: str? }\nstruct Other { name: str tag?: str }\nstruct Full { id: num name: str }\ntrait Named { fun label() -> str }\nimpl Named for Test {\n  fun label() -> str { return \"test\" }\n}\ntype MyType = Test & Other\ntype Labelled = MyType & Named\ntype Id = num\nfun f(value: Labelled, id: Id) -> str {\n  let name = value.name\n  let test: Test = value\n  let named: Named = value\n  let full: MyType = Full(id, \"a\")\n  return value.label() + name\n}";
+    let (source, check) = run(text);
+    assert!(check.errors.is_empty(), "{:?}", check.errors);
+    // `str?` and `str` combine to the narrower `str`
+    assert_eq!(check.type_of(variable(&source, "name")).unwrap().to_string(), "str");
+
+    assert_eq!(
+        errors("struct A { id: num name: str? }\nstruct B { id: str }\ntype Clash = A & B\ntype Bad = A & num\ntype Loop = Loop & A\nfun f(a: A) {\n  let v: Clash = a\n}"),
+        vec![
+            "`id` is `num` in one part of the intersection and `str` in another at `type Clash = A & B`",
+            "only structs and traits can be intersected, found `num` at `num`",
+            "`Loop` is defined in terms of itself at `type Loop = Loop & A`",
+        ]
+    );
+}
+
+#[test]
+fn unions() {
+    let text = "struct User { id: num name: str }\nstruct Group { id: num }\ntype Value = str | num\ntype Entity = User | Group?\nfun f(v: Value, e: Entity) -> str {\n  if e == null { return \"\" }\n  let id = e.id\n  let name = match e { is User => e.name, is Group => \"group\" }\n  let either = match v { is num => true, other => other }\n  if !(v is str) {\n    let n: num = v\n    return name\n  }\n  return v\n}";
+    let (source, check) = run(text);
+    assert!(check.errors.is_empty(), "{:?}", check.errors);
+    let type_of = |name| check.type_of(variable(&source, name)).unwrap().to_string();
+    assert_eq!(type_of("id"), "num");
+    assert_eq!(type_of("name"), "str");
+    // Arms after `is num` only see the rest of the union
+    assert_eq!(type_of("either"), "bool | str");
+
+    assert_eq!(
+        errors("type Value = str | num\nfun f(v: Value, n: num) {\n  let s: str = v\n  let ok = n is str\n  let m: num = v.size\n}"),
+        vec![
+            "expected `str`, found `str | num` at `v`",
+            "a `num` can never be a `str` at `n is str`",
+            "`str | num` has no member `size` at `v.size`",
+        ]
+    );
+}
+
+#[test]
+fn written_unions() {
+    let text = "struct Box { content: str | num }
+fun un(v: str | num?) -> str | num {
+  let b: num | str = Box(v ?? 0).content
+  let f = fun (x: bool | num) -> num | bool { x }
+  return b
+}";
+    let (source, check) = run(text);
+    assert!(check.errors.is_empty(), "{:?}", check.errors);
+    assert_eq!(check.type_of(variable(&source, "b")).unwrap().to_string(), "num | str");
+    assert_eq!(check.type_of(variable(&source, "f")).unwrap().to_string(), "fun(bool | num) -> num | bool");
+
+    assert_eq!(
+        errors("fun f(v: str | num) -> str {
+  return v
+}
+fun g(v: num | void) -> void | str {
+  return \"\"
+}"),
+        vec![
+            "expected `str`, found `str | num` at `v`",
+            "`void` can only be used as a return type at `void`",
+            "`void` can only be used as a return type at `void`",
+        ]
+    );
+}
+
+#[test]
+fn assignments() {
+    let text = "struct U { nick?: str }\nfun f(flag: bool) -> str {\n  let mut name: str? = null\n  let later\n  let mut u = U(ndef)\n  name = \"a\"\n  let known: str = name\n  later = 1\n  u.nick = \"b\"\n  let nick: str = u.nick\n  if flag {\n    name = null\n  }\n  return name\n}";
+    let (source, check) = run(text);
+    let found: Vec<_> = check.errors.iter().map(|err| err.to_string()).collect();
+    // Assigning in a branch undoes what was known from the earlier assignment
+    assert_eq!(
+        found,
+        vec!["expected `str`, found `str?` which may be null, check it with `!= null` or give a default with `??`"]
+    );
+    // `let` without a type or value takes the type of what is assigned
+    assert_eq!(check.type_of(variable(&source, "later")).unwrap().to_string(), "num");
+    assert_eq!(errors("fun f() {\n  let mut n = 1\n  n = \"a\"\n}"), vec!["expected `num`, found `str` at `\"a\"`"]);
+}
+
+#[test]
+fn loops() {
+    // The second pass through a body sees what the first one assigned, a
+    // `break` rules out what it was checked against like a `return`
+    assert_eq!(
+        errors("fun f(start: str?, flag: bool) -> str {\n  let mut name = start\n  if name == null { return \"\" }\n  while flag {\n    let length: str = name\n    name = null\n  }\n  while true {\n    if name == null { break }\n    let known: str = name\n  }\n  return name\n}"),
+        vec![
+            "expected `str`, found `str?` which may be null, check it with `!= null` or give a default with `??` at `name`",
+            "expected `str`, found `str?` which may be null, check it with `!= null` or give a default with `??` at `name`",
+        ]
+    );
+}
+
+#[test]
+fn statics() {
+    let text = "struct P { x: num }\ntrait Origin { static fun origin() -> P }\nimpl P {\n  const UNIT = 1\n  static fun at(x: num) -> P { return P(x) }\n  fun moved(by: num) -> P { return P::at(this.x + by * P::UNIT) }\n}\nimpl Origin for P {\n  static fun origin() -> P { return P::at(0) }\n}\nfun f(p: P) -> num {\n  let q: P = P::origin().moved(P::UNIT)\n  let s: str = P::UNIT\n  p.at(1)\n  return P::at(\"a\").x\n}";
+    assert_eq!(
+        errors(text),
+        vec![
+            "expected `str`, found `num` at `P::UNIT`",
+            "`P` has no member `at` at `p.at`",
+            "expected `num`, found `str` at `\"a\"`",
+        ]
+    );
+}
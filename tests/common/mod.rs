@@ -0,0 +1,60 @@
+// Helpers shared by the integration tests, not every test uses all of them
+#![allow(dead_code)]
+
+use std::fmt::Display;
+
+use spew::ast::ASTSource;
+use spew::check::TypeError;
+use spew::conform::ConformError;
+use spew::eval::EvalError;
+use spew::flow::FlowError;
+use spew::load::LoadError;
+use spew::lexer::TokenSet;
+use spew::mutability::MutabilityError;
+use spew::resolve::{resolve, Resolution, ResolveError};
+use spew::source::Span;
+use spew::visibility::VisibilityError;
+
+/// Error of one of the passes that points at the text it is about
+pub trait Diagnostic: Display {
+    fn span(&self) -> Span;
+}
+
+macro_rules! diagnostics {
+    ($($error:ty),*) => {
+        $(impl Diagnostic for $error {
+            fn span(&self) -> Span {
+                return <$error>::span(self);
+            }
+        })*
+    };
+}
+
+diagnostics!(ResolveError, TypeError, ConformError, MutabilityError, FlowError, EvalError, VisibilityError, LoadError);
+
+/// Parses and resolves a text that has to be free of resolution errors
+pub fn resolved(text: &str) -> (ASTSource, Resolution) {
+    let source = ASTSource::parse_ast(TokenSet::new(text)).unwrap();
+    let resolution = resolve(&source);
+    assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+    (source, resolution)
+}
+
+/// Messages paired with the text they point at
+pub fn messages<E: Diagnostic>(text: &str, errors: &[E]) -> Vec<String> {
+    errors
+        .iter()
+        .map(|err| format!("{} at `{}`", err, &text[err.span().start..err.span().end]))
+        .collect()
+}
+
+/// Messages of the errors a pass finds in a text, paired with the text they
+/// point at
+pub fn errors<E, F>(text: &str, pass: F) -> Vec<String>
+where
+    E: Diagnostic,
+    F: FnOnce(&ASTSource, &Resolution) -> Vec<E>,
+{
+    let (source, resolution) = resolved(text);
+    messages(text, &pass(&source, &resolution))
+}
@@ -0,0 +1,77 @@
+use spew::ast::ASTSource;
+use spew::check::check;
+use spew::conform::conform;
+use spew::lexer::TokenSet;
+use spew::resolve::resolve;
+
+mod common;
+
+fn errors(text: &str) -> Vec<String> {
+    common::errors(text, conform)
+}
+
+#[test]
+fn conforming() {
+    let text = "trait Named { fun name() -> str }\ntrait Greeter: Named { fun greet(other: Named?) }\nstruct U {}\nimpl Named for U {\n  fun name() -> str { return \"u\" }\n}\nimpl Greeter for U {\n  fun greet(other: Named?) {}\n}\nimpl U {\n  fun other() {}\n}\nimpl Named for str {\n  fun name() -> str { return this }\n}";
+    assert_eq!(errors(text), Vec::<String>::new());
+}
+
+#[test]
+fn functions() {
+    assert_eq!(
+        errors("trait T {\n  fun a(x: num) -> str\n  fun b()\n  fun c(x: str?)\n  fun e(x: str | num?) -> num | str\n  fun g(x: str | num)\n}\nstruct U {}\nimpl T for U {\n  fun a() {}\n  fun c(x: str) {}\n  fun d() {}\n  fun e(x: num? | str) -> str | num { return 1 }\n  fun g(x: str | bool) {}\n}"),
+        vec![
+            "missing `b` required by trait `T` at `impl T for U {\n  fun a() {}\n  fun c(x: str) {}\n  fun d() {}\n  fun e(x: num? | str) -> str | num { return 1 }\n  fun g(x: str | bool) {}\n}`",
+            "`a` should take 1 argument like its trait, found 0 at `fun a() {}`",
+            "return type should be `str` like in the trait, found `void` at `fun a() {}`",
+            "argument should be `str?` like in the trait, found `str` at `str`",
+            "`d` is not a function of trait `T` at `fun d() {}`",
+            "argument should be `str | num` like in the trait, found `str | bool` at `str | bool`",
+        ]
+    );
+}
+
+#[test]
+fn statics() {
+    assert_eq!(
+        errors("trait T {\n  static fun make() -> U\n  fun get() -> num\n}\nstruct U {}\nimpl T for U {\n  fun make() -> U { return U() }\n  static fun get() -> num { return 1 }\n}\nimpl U {\n  const get = 2\n}"),
+        vec![
+            "`make` is static in trait `T` so it has to be `static` here too at `fun make() -> U { return U() }`",
+            "`get` isn't static in trait `T` so it can't be `static` here at `static fun get() -> num { return 1 }`",
+            "`get` is defined by more than one impl of `U` at `const get = 2`",
+        ]
+    );
+}
+
+#[test]
+fn supertraits_and_overlaps() {
+    assert_eq!(
+        errors("trait A: C {}\ntrait B: A {}\ntrait C: B {}\ntrait D {}\ntrait E: D {}\nstruct U {}\nimpl E for U {}\nimpl E for U {}\nimpl U { fun f() {} }\nimpl U { fun f() {} }"),
+        vec![
+            "`A` is a supertrait of itself at `trait A: C {}`",
+            "`B` is a supertrait of itself at `trait B: A {}`",
+            "`C` is a supertrait of itself at `trait C: B {}`",
+            "`U` implements `E` but not its supertrait `D` at `impl E for U {}`",
+            "`E` is implemented for `U` more than once at `impl E for U {}`",
+            "`f` is defined by more than one impl of `U` at `fun f() {}`",
+        ]
+    );
+}
+
+#[test]
+fn aliases() {
+    assert!(errors("trait A { fun a(x: S) -> S }\nstruct S {}\ntype Alias = S\nimpl A for S { fun a(x: Alias) -> Alias { return x } }").is_empty());
+    assert_eq!(
+        errors("trait A {}\nstruct S {}\ntype Alias = S\nimpl A for S {}\nimpl A for Alias {}"),
+        vec!["`A` is implemented for `Alias` more than once at `impl A for Alias {}`"]
+    );
+}
+
+#[test]
+fn supertrait_values() {
+    let text = "trait A { fun a() -> num }\ntrait B: A {}\nfun f(b: B) -> num {\n  let a: A = b\n  return b.a()\n}\nfun g(a: A) { let b: B = a }";
+    let source = ASTSource::parse_ast(TokenSet::new(text)).unwrap();
+    let resolution = resolve(&source);
+    let found: Vec<_> = check(&source, &resolution).errors.iter().map(|err| err.to_string()).collect();
+    assert_eq!(found, vec!["expected `B`, found `A`"]);
+}
@@ -0,0 +1,90 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use spew::ast::ASTSource;
+use spew::check::check;
+use spew::eval::{evaluate, STEP_LIMIT};
+use spew::lexer::TokenSet;
+use spew::printer::print_source;
+use spew::resolve::resolve;
+
+mod common;
+
+fn errors(text: &str, limit: usize) -> Vec<String> {
+    common::errors(text, |source, resolution| evaluate(source, resolution, &check(source, resolution), limit).errors)
+}
+
+#[test]
+fn inlines() {
+    let text = "compile fun fib(n: num) -> num {\n  if n < 2 { return n }\n  return fib(n - 1) + fib(n - 2)\n}\ncompile fun repeat(text: str, times: num) -> str {\n  let mut out = \"\"\n  let mut left = times\n  while true {\n    if left == 0 { break }\n    out = out + text\n    left = left - 1\n  }\n  return match out { \"\" => \"none\", other => other + \"\\n\" }\n}\nconst TEN = fib(10)\nconst BELOW = 3 - TEN\nconst SAME = TEN is num && BELOW != 0\nfun f(x: num) {\n  print(repeat(\"a\\\"\", 2))\n  print(x + TEN)\n}";
+    let mut source = ASTSource::parse_ast(TokenSet::new(text)).unwrap();
+    let resolution = resolve(&source);
+    let evaluation = evaluate(&source, &resolution, &check(&source, &resolution), STEP_LIMIT);
+    assert_eq!(evaluation.errors, Vec::new());
+    evaluation.inline(&mut source);
+    let printed = print_source(&source);
+    let items: Vec<_> = printed.split("\n\n").skip(2).collect();
+    assert_eq!(
+        items,
+        vec![
+            "const TEN = 55",
+            "const BELOW = -52",
+            "const SAME = true",
+            "fun f(x: num) {\n  print(\"a\\\"a\\\"\\n\")\n  print(x + TEN)\n}\n",
+        ]
+    );
+}
+
+#[test]
+fn runtime() {
+    assert_eq!(
+        errors("compile fun log(n: num) -> num {\n  print(n)\n  return n\n}\ncompile fun nothing() -> num {}\nfun plain() -> num { return 1 }\nconst A = log(1)\nconst B = plain()\nconst C = nothing()\nlet mut D = 1\nconst E = D + 1\nconst F = 1 / 0\nconst G = \"a\" - 1\nfun f(x: num) {\n  const H = x\n  print(log(x))\n}", STEP_LIMIT),
+        vec![
+            "`print` isn't a `compile` function so it can't be called at compile time at `print`",
+            "`plain` isn't a `compile` function so it can't be called at compile time at `plain`",
+            "`nothing` finished without returning a value at `nothing()`",
+            "`D` isn't known at compile time at `D`",
+            "`inf` can't be written as a literal at `1 / 0`",
+            "`x` isn't known at compile time at `x`",
+            "`x` isn't known at compile time at `x`",
+        ]
+    );
+}
+
+#[test]
+fn limits() {
+    assert_eq!(
+        errors("compile fun spin() -> num {\n  while true {}\n  return 1\n}\ncompile fun deep(n: num) -> num { return deep(n + 1) }\nconst A = B\nconst B = A\nconst C = spin()\nconst D = deep(0)", 1000),
+        vec![
+            "the value of `A` depends on itself at `A`",
+            "compile time evaluation didn't finish within 1000 steps at `spin()`",
+            "compile time evaluation didn't finish within 1000 steps at `deep(0)`",
+        ]
+    );
+    assert_eq!(
+        errors("compile fun deep(n: num) -> num { return deep(n + 1) }\nconst D = deep(0)", STEP_LIMIT),
+        vec!["compile functions are nested more than 200 calls deep at `deep(n + 1)`"]
+    );
+}
+
+#[test]
+fn mistyped() {
+    // Type errors were reported by the checker, evaluating fails the same way
+    assert_eq!(
+        errors("compile fun bad() -> num { return \"a\" - 1 }\ncompile fun good() -> num { return 1 }\nconst A = \"a\" - 1\nconst B = A + 1\nconst C = bad()\nconst D = good() + 1 / 0", STEP_LIMIT),
+        vec!["`inf` can't be written as a literal at `good() + 1 / 0`"]
+    );
+}
+
+#[test]
+fn reported_once() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_spew"))
+        .arg("check")
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"const w = \"a\" + 1").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "<stdin>:1:11: cannot apply `+` to `str` and `num`\n");
+}
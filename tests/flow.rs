@@ -0,0 +1,47 @@
+use spew::flow::flow;
+
+mod common;
+
+fn errors(text: &str) -> Vec<String> {
+    common::errors(text, flow)
+}
+
+#[test]
+fn returns() {
+    assert_eq!(
+        errors("fun a(x: num) -> str {\n  if x > 0 { return \"a\" } else { return \"b\" }\n}\nfun b(x: num) -> num {\n  while true {\n    if x > 0 { return x }\n  }\n}\nfun c(x: num) -> str {\n  if x > 0 { return \"c\" }\n}\nfun d(x: str?) -> str? {\n  match x { null => { return null }, other => { return other } }\n}\nfun e(x: str?) -> str {\n  let f = fun (y: num) -> bool { y > 0 }\n  match x { null => { return \"\" } }\n}\nfun g() {}\nfun h(x: num) -> str | num {\n  if x > 0 { return x }\n}"),
+        vec![
+            "missing `return`, the end of the body is reached without returning `str` at `str`",
+            "missing `return`, the end of the body is reached without returning `str` at `str`",
+            "missing `return`, the end of the body is reached without returning `bool` at `bool`",
+            "missing `return`, the end of the body is reached without returning `str | num` at `str | num`",
+        ]
+    );
+}
+
+#[test]
+fn unreachable() {
+    assert_eq!(
+        errors("fun f(x: num) -> num {\n  while x > 0 {\n    break\n    x\n    x\n  }\n  if x > 0 { return 1 } else { return 2 }\n  if x > 1 { x }\n  return 3\n}\nfun g() {\n  break\n}\nfun h() {\n  return\n  print(1)\n}"),
+        vec![
+            "unreachable statement at `x`",
+            "unreachable statement at `if x > 1 { x }`",
+            "`break` outside of a loop at `break`",
+            "unreachable statement at `print(1)`",
+        ]
+    );
+}
+
+#[test]
+fn assignments() {
+    assert_eq!(
+        errors("fun f(x: num) -> num {\n  let a\n  let b\n  let c\n  let d\n  if x > 0 { a = 1; b = 1 } else { a = 2 }\n  let e = x > 0 && (c = 1) == null\n  while x > 0 {\n    x + d\n    d = 1\n  }\n  return a + b + c + d\n}\nfun g() -> num {\n  let a\n  let h = fun () { a }\n  return a\n}"),
+        vec![
+            "`d` may be used before it is assigned, not every path assigns it at `d`",
+            "`b` may be used before it is assigned, not every path assigns it at `b`",
+            "`c` may be used before it is assigned, not every path assigns it at `c`",
+            "`d` may be used before it is assigned, not every path assigns it at `d`",
+            "`a` is used before it is assigned at `a`",
+        ]
+    );
+}
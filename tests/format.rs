@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use spew::ast::ASTSource;
+use spew::format::{format, FormatConfig};
+use spew::lexer::{LexErrorKind, TokenSet};
+use spew::printer::print_source;
+
+fn corpus() -> Vec<(String, String)> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut files = vec![("example.spew".to_string(), include_str!("../example.spew").to_string())];
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "spew") {
+            files.push((path.display().to_string(), fs::read_to_string(&path).unwrap()));
+        }
+    }
+    files
+}
+
+// Canonical print of the tree, used to check formatting kept the meaning
+fn tree(path: &str, text: &str) -> String {
+    match ASTSource::parse_ast(TokenSet::new(text)) {
+        Ok(source) => print_source(&source),
+        Err(err) => panic!("{}: {} at {:?}\n{}", path, err, err.span(), text),
+    }
+}
+
+#[test]
+fn idempotent_and_preserves_tree() {
+    for width in [20, 40, 100] {
+        let config = FormatConfig { line_width: width };
+        for (path, text) in corpus() {
+            let formatted = format(&text, &config).unwrap();
+            assert_eq!(format(&formatted, &config).unwrap(), formatted, "{} is not idempotent", path);
+            assert_eq!(tree(&path, &text), tree(&path, &formatted), "{} changed meaning:\n{}", path, formatted);
+        }
+    }
+}
+
+#[test]
+fn preserves_comments() {
+    let text = "// head\nstruct A { /* inline */ a: str // trailing\n}\n/* tail */\n";
+    let formatted = format(text, &FormatConfig::default()).unwrap();
+    assert_eq!(formatted, "// head\nstruct A { /* inline */\n  a: str // trailing\n}\n/* tail */\n");
+}
+
+#[test]
+fn refuses_unknown_characters() {
+    let err = format("let x = a + 1 # note", &FormatConfig::default()).unwrap_err();
+    assert_eq!(err.position, 14);
+    assert!(matches!(err.kind, LexErrorKind::UnexpectedToken('#', _)));
+    // Nothing is written when the file can't be formatted
+    let directory = std::env::temp_dir().join(format!("spew-fmt-unknown-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("unknown.spew");
+    fs::write(&path, "let  x = 1 § 2").unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_spew")).arg("fmt").arg(&path).stderr(Stdio::null()).status().unwrap();
+    assert!(!status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "let  x = 1 § 2");
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn normalises_spacing() {
+    let text = "fun f(a:num,b:str?)->num{let x=a+-b*(2)\nreturn x}";
+    let formatted = format(text, &FormatConfig::default()).unwrap();
+    assert_eq!(formatted, "fun f(a: num, b: str?) -> num {\n  let x = a + -b * (2)\n  return x\n}\n");
+}
+
+#[test]
+fn imports_stay_on_one_line() {
+    let text = "import {\n  a,\n  B as C\n} from \"other\" import *   as x from \"x\"\nfun f() {}";
+    let formatted = format(text, &FormatConfig::default()).unwrap();
+    assert_eq!(formatted, "import { a, B as C } from \"other\"\nimport * as x from \"x\"\nfun f() {}\n");
+}
+
+#[test]
+fn wraps_long_argument_lists() {
+    let config = FormatConfig { line_width: 30 };
+    let formatted = format("fun f(first: str, second: num) {}", &config).unwrap();
+    assert_eq!(formatted, "fun f(\n  first: str,\n  second: num,\n) {}\n");
+    // The trailing comma goes away again once the list fits
+    let formatted = format(&formatted, &FormatConfig::default()).unwrap();
+    assert_eq!(formatted, "fun f(first: str, second: num) {}\n");
+}
+
+#[test]
+fn config_file() {
+    assert_eq!(FormatConfig::parse("# width\nline_width = 80\n").unwrap().line_width, 80);
+    assert_eq!(FormatConfig::parse("").unwrap(), FormatConfig::default());
+    assert!(FormatConfig::parse("line_width = wide").is_err());
+    assert!(FormatConfig::parse("tabs = true").is_err());
+    assert!(FormatConfig::parse("line_width").is_err());
+}
+
+#[test]
+fn check_exit_code() {
+    let directory = std::env::temp_dir().join(format!("spew-fmt-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("check.spew");
+    fs::write(&path, "struct A{a:str}").unwrap();
+    let check = || {
+        Command::new(env!("CARGO_BIN_EXE_spew"))
+            .args(["fmt", "--check"])
+            .arg(&path)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap()
+            .code()
+    };
+    assert_eq!(check(), Some(1));
+    // Check mode must not touch the file
+    assert_eq!(fs::read_to_string(&path).unwrap(), "struct A{a:str}");
+    let status = Command::new(env!("CARGO_BIN_EXE_spew")).arg("fmt").arg(&path).status().unwrap();
+    assert!(status.success());
+    assert_eq!(check(), Some(0));
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,45 @@
+use spew::ast::ASTSource;
+use spew::inline::inline;
+use spew::lexer::TokenSet;
+use spew::printer::print_source;
+use spew::resolve::resolve;
+
+// Printed items after inlining, without the first `skip` ones
+fn inlined(text: &str, skip: usize) -> Vec<String> {
+    let mut source = ASTSource::parse_ast(TokenSet::new(text)).unwrap();
+    let resolution = resolve(&source);
+    assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+    inline(&mut source, &resolution);
+    print_source(&source).split("\n\n").skip(skip).map(|item| item.trim_end().to_string()).collect()
+}
+
+#[test]
+fn expressions() {
+    let text = "fun add(a: num, b: num) -> num { return a + b }\ninline fun twice(n: num) -> num { return add(n, n) }\nfun next() -> num { return 1 }\nfun f(x: num) {\n  let mut y = 2\n  let z = twice(add(y, next()))\n  print(add(x, 1) * add(3, x))\n  print(add(next(), x))\n  y = twice(y)\n}";
+    assert_eq!(
+        inlined(text, 3),
+        vec![
+            "fun f(x: num) {\n  let mut y = 2\n  let a_0 = y\n  let n_0 = a_0 + 1\n  let z = n_0 + n_0\n  print((x + 1) * (3 + x))\n  print(1 + x)\n  let n_1 = y\n  y = n_1 + n_1\n}",
+        ]
+    );
+}
+
+#[test]
+fn statements() {
+    let text = "inline fun log(message: str) {\n  let copy = message\n  print(copy)\n}\ninline fun greet(name: str) -> str {\n  log(name)\n  return name\n}\nfun f(copy: str) {\n  log(\"a\")\n  greet(copy)\n  let b = greet(\"b\")\n}";
+    assert_eq!(
+        inlined(text, 2),
+        vec![
+            "fun f(copy: str) {\n  {\n    let copy = \"a\"\n    print(copy)\n  }\n  {\n    let name_0 = copy\n    {\n      let copy = name_0\n      print(copy)\n    }\n    name_0\n  }\n  let b = greet(\"b\")\n}",
+        ]
+    );
+}
+
+#[test]
+fn skipped() {
+    let text = "inline fun fact(n: num) -> num {\n  if n < 2 { return 1 }\n  return n * fact(n - 1)\n}\ninline fun even(n: num) -> bool { return n == 0 || odd(n - 1) }\ninline fun odd(n: num) -> bool { return n != 0 && even(n - 1) }\ncompile fun square(n: num) -> num { return n * n }\nfun big(n: num) -> num { return n * n + n * n + n }\nfun scale(n: num) -> num { return n * FACTOR }\nconst FACTOR = 2\nfun f(x: num) {\n  let FACTOR = 3\n  print(fact(x) + square(x) + big(x) + scale(x))\n  print(even(x))\n}";
+    assert_eq!(
+        inlined(text, 7).last().unwrap(),
+        "fun f(x: num) {\n  let FACTOR = 3\n  print(fact(x) + square(x) + big(x) + scale(x))\n  print(even(x))\n}"
+    );
+}
@@ -0,0 +1,28 @@
+use spew::intern::Name;
+
+#[test]
+fn round_trip() {
+    let name = Name::intern("interned_name");
+    assert_eq!(name.as_str(), "interned_name");
+    assert_eq!(name.to_string(), "interned_name");
+    assert_eq!(format!("{:?}", name), "\"interned_name\"");
+    assert_eq!(name, "interned_name");
+    // The same text always maps to the same name and index
+    assert_eq!(Name::intern("interned_name"), name);
+    assert_eq!(Name::from(String::from("interned_name").as_str()).index(), name.index());
+    let other = Name::intern("other_interned_name");
+    assert_ne!(other, name);
+    assert_eq!(other.as_str(), "other_interned_name");
+    assert_eq!(Name::intern("").as_str(), "");
+}
+
+#[test]
+fn across_threads() {
+    let names: Vec<Name> = (0..4)
+        .map(|_| std::thread::spawn(|| Name::intern("threaded_name")))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect();
+    assert!(names.iter().all(|name| *name == names[0] && *name == "threaded_name"));
+}
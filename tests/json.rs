@@ -0,0 +1,79 @@
+use serde_json::Value;
+
+use spew::ast::ASTSource;
+use spew::json::{ast_to_json, tokens_to_json, JSON_VERSION};
+use spew::lexer::TokenSet;
+
+const SOURCE: &str = "@json(name = \"t\")\nstruct Test { name: str? }\n// note\nfun f(a: num) -> num { return -a + 1 }\n";
+
+fn ast() -> Value {
+    let source = ASTSource::parse_ast(TokenSet::new(SOURCE)).unwrap();
+    serde_json::from_str(&ast_to_json(&source)).unwrap()
+}
+
+#[test]
+fn tokens() {
+    let value: Value = serde_json::from_str(&tokens_to_json(SOURCE).unwrap()).unwrap();
+    assert_eq!(value["version"], JSON_VERSION);
+    let tokens = value["tokens"].as_array().unwrap();
+    assert_eq!(tokens[0]["kind"], "symbol");
+    assert_eq!(tokens[0]["text"], "@");
+    assert_eq!(tokens[5]["literal"]["type"], "string");
+    assert_eq!(tokens[5]["literal"]["value"], "t");
+    // Comments are part of the stream and every span covers its text
+    assert!(tokens.iter().any(|token| token["kind"] == "comment" && token["text"] == "// note"));
+    for token in tokens {
+        let start = token["span"]["start"].as_u64().unwrap() as usize;
+        let end = token["span"]["end"].as_u64().unwrap() as usize;
+        assert_eq!(token["text"], &SOURCE[start..end]);
+    }
+}
+
+#[test]
+fn nodes_are_indexed_by_id() {
+    let value = ast();
+    assert_eq!(value["version"], JSON_VERSION);
+    let nodes = value["nodes"].as_array().unwrap();
+    for (index, node) in nodes.iter().enumerate() {
+        assert_eq!(node["id"], index);
+        // Children point back at their parent
+        if let Some(parent) = node["parent"].as_u64() {
+            assert!(parent as usize > index);
+        }
+    }
+    let roots: Vec<&str> = value["roots"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|id| nodes[id.as_u64().unwrap() as usize]["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(roots, ["struct", "function"]);
+}
+
+#[test]
+fn node_fields() {
+    let value = ast();
+    let nodes = value["nodes"].as_array().unwrap();
+    let find = |kind: &str| nodes.iter().find(|node| node["kind"] == kind).unwrap();
+
+    let structure = find("struct");
+    assert_eq!(structure["name"], "Test");
+    assert_eq!(structure["annotations"][0]["name"], "json");
+    assert_eq!(structure["annotations"][0]["arguments"][0]["name"], "name");
+    assert_eq!(structure["span"]["start"], 0);
+
+    let property = find("property");
+    assert_eq!(property["type"]["name"], "str");
+    assert_eq!(property["type"]["nullable"], true);
+
+    let function = find("function");
+    assert_eq!(function["return_type"]["name"], "num");
+    assert_eq!(function["modifiers"], Value::Array(Vec::new()));
+
+    // `-a + 1` is a binary operation whose left side is unary
+    let operation = nodes.iter().find(|node| node["kind"] == "operation" && !node["right"].is_null()).unwrap();
+    assert_eq!(operation["operator"], "+");
+    let left = &nodes[operation["left"].as_u64().unwrap() as usize];
+    assert_eq!(left["operator"], "-");
+    assert!(left["right"].is_null());
+}
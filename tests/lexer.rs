@@ -0,0 +1,34 @@
+use spew::lexer::{Lexer, Symbol, Token};
+
+fn tokens(text: &str) -> Vec<Token<'_>> {
+    Lexer::new(text).map(|token| token.unwrap().value).collect()
+}
+
+#[test]
+fn identifiers() {
+    let found = tokens("_ _a __ a_1 ünïcode 名前 _");
+    assert_eq!(found.len(), 7);
+    assert_eq!(found[0], Token::Symbol(Symbol::Underscore));
+    assert_eq!(found[6], Token::Symbol(Symbol::Underscore));
+    let names: Vec<_> = found[1..6]
+        .iter()
+        .map(|token| match token {
+            Token::Ident(name) => name.to_string(),
+            token => panic!("{:?} isn't an identifier", token),
+        })
+        .collect();
+    assert_eq!(names, vec!["_a", "__", "a_1", "ünïcode", "名前"]);
+}
+
+#[test]
+fn normalised() {
+    // `é` precomposed and as `e` with a combining acute accent
+    let found = tokens("caf\u{e9} cafe\u{301}");
+    assert_eq!(found.len(), 2);
+    assert!(matches!(found[0], Token::Ident(_)));
+    assert_eq!(found[0], found[1]);
+    match found[1] {
+        Token::Ident(name) => assert_eq!(name.to_string(), "caf\u{e9}"),
+        token => panic!("{:?} isn't an identifier", token),
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+use spew::ast::AST;
+use spew::check::check;
+use spew::load::{load, Program};
+use spew::printer::print_file;
+use spew::resolve::{resolve, Declaration};
+
+mod common;
+
+use common::Diagnostic;
+
+// Loads the first file, the others are read from memory
+fn program(files: &[(&str, &str)]) -> Program {
+    let texts: HashMap<PathBuf, String> = files.iter().map(|(path, text)| (PathBuf::from(path), text.to_string())).collect();
+    let (path, text) = files[0];
+    load(path, text.to_string(), |path| texts.get(path).cloned().ok_or(io::Error::from(io::ErrorKind::NotFound)))
+}
+
+// Messages with the file and position they are at
+fn located<E: Diagnostic>(program: &Program, errors: &[E]) -> Vec<String> {
+    errors
+        .iter()
+        .map(|err| {
+            let (path, position) = program.locate(err.span());
+            format!("{}:{}:{}: {}", path, position.line + 1, position.column + 1, err)
+        })
+        .collect()
+}
+
+#[test]
+fn links_files() {
+    let program = program(&[
+        ("app/main.spew", "import * from \"lib/a\"\nimport * as b from \"./lib/../lib/b\"\nlet x = a() + b.b()"),
+        ("app/lib/a.spew", "import { b } from \"b\"\npub fun a() -> num { return b() }"),
+        ("app/lib/b.spew", "pub fun b() -> num { return 1 }"),
+    ]);
+    assert!(program.errors.is_empty());
+    let paths: Vec<_> = program.source.files().iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, vec!["app/main.spew", "app/lib/a.spew", "app/lib/b.spew"]);
+    // Both imports of b are the same file
+    let imports: Vec<_> = program.source.ids()
+        .filter_map(|id| program.source.cast(id))
+        .map(|id| program.source.imported(id))
+        .collect();
+    assert_eq!(imports, vec![Some(1), Some(2), Some(2)]);
+
+    let resolution = resolve(&program.source);
+    assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+    let b = program.source.file_roots(2)[0];
+    let uses: Vec<_> = program.source.ids()
+        .filter(|id| resolution.value(*id) == Some(Declaration::Node(b)))
+        .map(|id| program.source.file_of(program.source.span(id)))
+        .collect();
+    assert_eq!(uses, vec![0, 1]);
+    assert!(check(&program.source, &resolution).errors.is_empty());
+    assert_eq!(print_file(&program.source, 2), "pub fun b() -> num {\n  return 1\n}\n");
+}
+
+#[test]
+fn private_items() {
+    let program = program(&[
+        (
+            "main.spew",
+            "import * from \"other\"\nimport * as other from \"other\"\nimport { Shown, Hidden as H, hidden, missing } from \"other\"\nfun f(a: other.Hidden, b: other.Shown) {\n  hidden()\n  other.hidden()\n  other.shown()\n  other.missing()\n  let c = other\n  print(Hidden)\n}",
+        ),
+        ("other.spew", "pub struct Shown {}\nstruct Hidden {}\npub fun shown() {}\nfun hidden() {}"),
+    ]);
+    assert!(program.errors.is_empty());
+    let resolution = resolve(&program.source);
+    assert_eq!(
+        located(&program, &resolution.errors),
+        vec![
+            "main.spew:3:17: `Hidden` isn't `pub` so it can only be used in its own file",
+            "main.spew:3:30: `hidden` isn't `pub` so it can only be used in its own file",
+            "main.spew:3:38: \"other\" has no item `missing`",
+            "main.spew:4:10: `other.Hidden` isn't `pub` so it can only be used in its own file",
+            "main.spew:6:3: `hidden` isn't `pub` so it can only be used in its own file",
+            "main.spew:8:3: \"other\" has no item `missing`",
+            "main.spew:9:11: `other` is a namespace, use one of its items as `other.name`",
+            "main.spew:10:9: cannot find `Hidden` in this scope",
+        ]
+    );
+}
+
+#[test]
+fn private_members() {
+    let program = program(&[
+        (
+            "main.spew",
+            "import { Point } from \"point\"\nfun f(p: Point) -> num {\n  p.y = p.x\n  return p.length() + p.scale() + Point::ZERO + Point::ONE\n}",
+        ),
+        (
+            "point.spew",
+            "pub struct Point {\n  pub x: num\n  y: num\n}\ntrait Sized {\n  fun length() -> num\n}\nimpl Sized for Point {\n  fun length() -> num { return this.y }\n}\nimpl Point {\n  fun scale() -> num { return this.y }\n  pub const ZERO = 0\n  const ONE = 1\n}",
+        ),
+    ]);
+    assert!(program.errors.is_empty());
+    let resolution = resolve(&program.source);
+    assert_eq!(
+        located(&program, &resolution.errors),
+        vec!["main.spew:4:49: `ONE` isn't `pub` so it can only be used in its own file"]
+    );
+    assert_eq!(
+        located(&program, &check(&program.source, &resolution).errors),
+        vec![
+            "main.spew:3:3: `y` of `Point` isn't `pub` so it can only be used in its own file",
+            "main.spew:4:23: `scale` of `Point` isn't `pub` so it can only be used in its own file",
+        ]
+    );
+}
+
+#[test]
+fn unreadable_files() {
+    let program = program(&[
+        ("main.spew", "import * from \"other\"\nimport * from \"gone\""),
+        ("other.spew", "fun f() {\n  let = 1\n}"),
+    ]);
+    assert_eq!(
+        located(&program, &program.errors),
+        vec![
+            "main.spew:2:1: cannot import `gone.spew`: entity not found",
+            "other.spew:2:7: unexpected `=`, expected identifier",
+        ]
+    );
+    assert!(matches!(program.source.get(program.source.file_roots(0)[1]), AST::Import(_)));
+}
@@ -0,0 +1,45 @@
+use spew::mutability::mutability;
+
+mod common;
+
+fn errors(text: &str) -> Vec<String> {
+    common::errors(text, mutability)
+}
+
+#[test]
+fn mutable() {
+    let text = "struct U { name: str }\nlet mut shared = 1\nfun f() {\n  let mut a = 1\n  let later\n  let mut u = U(\"a\")\n  a = a + 1\n  later = 2\n  shared = a\n  u.name = \"b\"\n  U(\"c\").name = \"d\"\n}\nimpl U {\n  fun rename() { this.name = \"e\" }\n}";
+    assert_eq!(errors(text), Vec::<String>::new());
+}
+
+#[test]
+fn immutable() {
+    assert_eq!(
+        errors("struct U { inner: U? }\nconst LIMIT = 1\nfun f(arg: U, n: num) {\n  let a = 1\n  a = 2\n  LIMIT = a\n  arg = U(null)\n  arg.inner.inner = null\n  let u = arg\n  u.inner = arg\n  match n { other => other = 1 }\n  f = f\n  1 = 2\n}"),
+        vec![
+            "cannot assign to immutable `a`, declare it with `let mut a` at `a`",
+            "cannot assign to constant `LIMIT`, declare it with `let mut LIMIT` at `LIMIT`",
+            "cannot assign to argument `arg`, copy it with `let mut arg = arg` first at `arg`",
+            "cannot assign to a property of argument `arg`, copy it with `let mut arg = arg` first at `arg.inner.inner`",
+            "cannot assign to a property of immutable `u`, declare it with `let mut u` at `u.inner`",
+            "cannot assign to match binding `other`, copy it with `let mut other = other` first at `other`",
+            "cannot assign to `f`, only variables and properties can be assigned at `f`",
+            "only variables and properties can be assigned at `1`",
+        ]
+    );
+}
+
+#[test]
+fn assigned_once() {
+    assert_eq!(
+        errors("fun f() { let later; later = 1; later = 2 }"),
+        vec!["cannot assign to immutable `later`, declare it with `let mut later` at `later`"]
+    );
+    assert_eq!(
+        errors("fun f(x: num) {\n  let a\n  if x > 0 { a = 1 } else { a = 2 }\n  let b\n  while x > 0 { b = 1 }\n  let c\n  let g = fun () { c = 1 }\n}"),
+        vec![
+            "cannot assign to immutable `b`, declare it with `let mut b` at `b`",
+            "cannot assign to immutable `c`, declare it with `let mut c` at `c`",
+        ]
+    );
+}
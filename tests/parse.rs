@@ -0,0 +1,62 @@
+use spew::ast::{ASTError, ASTSource, ParseOptions, DEFAULT_MAX_DEPTH};
+use spew::check::check;
+use spew::flow::flow;
+use spew::lexer::TokenSet;
+use spew::mutability::mutability;
+use spew::printer::print_source;
+use spew::resolve::resolve;
+
+fn too_deep(text: &str, max_depth: usize) -> usize {
+    match ASTSource::parse_ast_with(TokenSet::new(text), ParseOptions { max_depth }) {
+        Err(ASTError::TooDeep(span, limit)) => {
+            assert_eq!(limit, max_depth);
+            span.start
+        }
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(_) => panic!("parsed past the nesting limit"),
+    }
+}
+
+#[test]
+fn nesting_limit() {
+    let parens = format!("const a = {}1{}", "(".repeat(1000), ")".repeat(1000));
+    too_deep(&parens, DEFAULT_MAX_DEPTH);
+    let blocks = format!("fun f() {{ {}{} }}", "{ ".repeat(1000), "} ".repeat(1000));
+    too_deep(&blocks, DEFAULT_MAX_DEPTH);
+    let negations = format!("const a = {}true", "!".repeat(1000));
+    too_deep(&negations, DEFAULT_MAX_DEPTH);
+    let members = format!("const a = b{}", ".c".repeat(1000));
+    too_deep(&members, DEFAULT_MAX_DEPTH);
+
+    // The limit is configurable and nesting up to it is fine
+    let nested = |depth: usize| format!("const a = {}1{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(ASTSource::parse_ast_with(TokenSet::new(&nested(5)), ParseOptions { max_depth: 8 }).is_ok());
+    assert_eq!(too_deep(&nested(20), 8), "const a = ".len() + 8);
+    let message = ASTSource::parse_ast_with(TokenSet::new(&nested(20)), ParseOptions { max_depth: 8 }).unwrap_err();
+    assert!(message.to_string().contains("at most 8"), "{}", message);
+}
+
+#[test]
+fn long_chains() {
+    // Operator chains nest on the left but don't count towards the limit
+    let operands = vec!["x"; 10_000].join(" + ");
+    let text = format!("fun f(x: num) -> bool {{\n  return {} > 0 && {}\n}}", operands, vec!["x > 0"; 10_000].join(" && "));
+    let source = ASTSource::parse_ast(TokenSet::new(&text)).unwrap();
+    let resolution = resolve(&source);
+    assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+    assert!(check(&source, &resolution).errors.is_empty());
+    assert!(flow(&source, &resolution).is_empty());
+    assert!(mutability(&source, &resolution).is_empty());
+    assert_eq!(print_source(&source), format!("{}\n", text));
+}
+
+#[test]
+fn bare_return() {
+    // Without a `;` the next line is a statement, not the returned value
+    let text = "fun f() {\n  return\n  print(1)\n}\nfun g() {\n  return\n  let z = 1\n}\nfun h() -> num {\n  return 1 +\n    2\n}";
+    let source = ASTSource::parse_ast(TokenSet::new(text)).unwrap();
+    assert_eq!(
+        print_source(&source),
+        "fun f() {\n  return\n  print(1)\n}\n\nfun g() {\n  return\n  let z = 1\n}\n\nfun h() -> num {\n  return 1 + 2\n}\n"
+    );
+}
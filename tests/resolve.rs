@@ -0,0 +1,151 @@
+use spew::ast::{ASTSource, NodeId, AST};
+use spew::lexer::TokenSet;
+use spew::resolve::{resolve, Declaration, Resolution, ResolveError};
+
+mod common;
+
+fn parse(text: &str) -> ASTSource {
+    ASTSource::parse_ast(TokenSet::new(text)).unwrap()
+}
+
+// Identifier nodes with the given name in source order
+fn identifiers(source: &ASTSource, name: &str) -> Vec<NodeId> {
+    source.ids().filter(|id| matches!(source.get(*id), AST::Identifier(ident) if *ident == name)).collect()
+}
+
+// Node a variable or argument named `name` is declared by, the nth one
+fn declaration(source: &ASTSource, name: &str, nth: usize) -> NodeId {
+    source
+        .ids()
+        .filter(|id| match source.get(*id) {
+            AST::Variable { variable, .. } => variable.name == name,
+            AST::Argument(argument) => argument.name == name,
+            AST::Function(function) => function.stub.name == name,
+            _ => false,
+        })
+        .nth(nth)
+        .unwrap()
+}
+
+fn messages(resolution: &Resolution, text: &str) -> Vec<String> {
+    common::messages(text, &resolution.errors)
+}
+
+#[test]
+fn binds_references() {
+    let text = "struct User { id: num }\nfun main(a: num) -> User {\n  let b = later(a)\n  print(b)\n  return User(b)\n}\nfun later(v: num) -> num { return v }";
+    let source = parse(text);
+    let resolution = resolve(&source);
+    assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+    let a = identifiers(&source, "a");
+    assert_eq!(resolution.value(a[0]), Some(Declaration::Node(declaration(&source, "a", 0))));
+    let later = identifiers(&source, "later");
+    assert_eq!(resolution.value(later[0]), Some(Declaration::Node(declaration(&source, "later", 0))));
+    let print = identifiers(&source, "print");
+    assert!(matches!(resolution.value(print[0]), Some(Declaration::Builtin(name)) if name == "print"));
+    // Every identifier in the tree is bound
+    for id in source.ids() {
+        if matches!(source.get(id), AST::Identifier(_)) {
+            assert!(resolution.value(id).is_some(), "{:?} is unbound", id);
+        }
+    }
+}
+
+#[test]
+fn shadowing() {
+    let text = "fun f(x: num) {\n  let x = x\n  {\n    let x = x\n    print(x)\n  }\n  print(x)\n}";
+    let source = parse(text);
+    let resolution = resolve(&source);
+    assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+    let uses = identifiers(&source, "x");
+    let argument = Declaration::Node(declaration(&source, "x", 0));
+    let outer = Declaration::Node(declaration(&source, "x", 1));
+    let inner = Declaration::Node(declaration(&source, "x", 2));
+    // Initialisers see the binding from before their own declaration
+    assert_eq!(resolution.value(uses[0]), Some(argument));
+    assert_eq!(resolution.value(uses[1]), Some(outer));
+    assert_eq!(resolution.value(uses[2]), Some(inner));
+    assert_eq!(resolution.value(uses[3]), Some(outer));
+}
+
+#[test]
+fn this_in_impls() {
+    let text = "struct A { v: num }\nimpl A {\n  fun get() -> num { return this.v }\n}\nfun f() { this }";
+    let source = parse(text);
+    let resolution = resolve(&source);
+    let uses = identifiers(&source, "this");
+    assert!(matches!(resolution.value(uses[0]), Some(Declaration::This(_))));
+    assert_eq!(messages(&resolution, text), vec!["cannot find `this` in this scope at `this`"]);
+}
+
+#[test]
+fn types() {
+    let text = "struct A { b: B? c: str }\ntrait B {}\nimpl B for A {}\nimpl A for B {}\nfun f(x: Missing) {}\nfun g(y: str | Gone | B) {}";
+    let source = parse(text);
+    let resolution = resolve(&source);
+    assert_eq!(
+        messages(&resolution, text),
+        vec![
+            "`A` is not a trait at `impl A for B {}`",
+            "cannot find type `Missing` in this scope at `Missing`",
+            "cannot find type `Gone` in this scope at `Gone`",
+        ]
+    );
+    let target = resolution.impls.iter().next().unwrap().1;
+    assert!(target.trait_of.is_some());
+    let AST::Struct(value) = source.get(source.roots()[0]) else { panic!() };
+    assert!(matches!(resolution.type_of(value.properties[1]), Some(Declaration::Builtin(name)) if name == "str"));
+    // Every member of a union is resolved in the order it is written
+    let AST::Function(g) = source.get(source.roots()[5]) else { panic!() };
+    let y = g.stub.arguments[0];
+    assert!(matches!(resolution.type_at(y, 0), Some(Declaration::Builtin(name)) if name == "str"));
+    assert_eq!(resolution.type_at(y, 1), None);
+    assert_eq!(resolution.type_at(y, 2), Some(Declaration::Node(source.roots()[1])));
+}
+
+#[test]
+fn undefined_and_duplicates() {
+    let text = "struct S { a: num a: str }\nfun f(x: num, x: str) {\n  let y = 1\n  let y = 2\n  if y { let z = 1 } else { z }\n}\nfun f() {}\nstruct num {}\ntrait T: S + Q {}";
+    let source = parse(text);
+    let resolution = resolve(&source);
+    assert_eq!(
+        messages(&resolution, text),
+        vec![
+            "`a` is defined more than once at `a: str`",
+            "`x` is defined more than once at `x: str`",
+            "`y` is defined more than once at `let y = 2`",
+            "cannot find `z` in this scope at `z`",
+            "`f` is defined more than once at `fun f() {}`",
+            "`num` is defined more than once at `struct num {}`",
+            "`S` is not a trait at `trait T: S + Q {}`",
+            "cannot find type `Q` in this scope at `trait T: S + Q {}`",
+        ]
+    );
+    assert!(matches!(resolution.errors[3], ResolveError::Undefined(_, name) if name == "z"));
+}
+
+#[test]
+fn statics() {
+    let text = "struct A { v: num }\nimpl A {\n  const ONE = 1\n  static const TWO = 2\n  static fun make() -> A { return A(A::ONE + this.v) }\n  fun get() -> num { return ONE }\n}\nstatic fun loose() {}\nstatic const LOOSE = 1\nfun f() {\n  static let x = 1\n  inline let y = 2\n  A::make()\n  A::get()\n  A::other\n  B::make()\n}\nconst mut M = 1";
+    let source = parse(text);
+    let resolution = resolve(&source);
+    assert_eq!(
+        messages(&resolution, text),
+        vec![
+            "cannot find `this` in this scope at `this`",
+            "cannot find `ONE` in this scope at `ONE`",
+            "`static` can only be used on functions and constants of impls and functions of traits at `static fun loose() {}`",
+            "`static` can only be used on functions and constants of impls and functions of traits at `static const LOOSE = 1`",
+            "`static` can only be used on functions and constants of impls and functions of traits at `static let x = 1`",
+            "`inline` can only be used on functions at `inline let y = 2`",
+            "`get` isn't static, call it on a value of `A` at `A::get`",
+            "`A` has no static function or constant `other` at `A::other`",
+            "cannot find type `B` in this scope at `B`",
+            "constants can't be `mut`, declare it with `let mut` at `const mut M = 1`",
+        ]
+    );
+    let paths: Vec<_> = source.ids().filter(|id| matches!(source.get(*id), AST::Path { .. })).collect();
+    assert_eq!(resolution.value(paths[0]), Some(Declaration::Node(declaration(&source, "ONE", 0))));
+    assert_eq!(resolution.value(paths[1]), Some(Declaration::Node(declaration(&source, "make", 0))));
+}
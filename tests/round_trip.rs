@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use spew::ast::ASTSource;
+use spew::lexer::TokenSet;
+use spew::printer::print_source;
+
+fn parse(path: &str, text: &str) -> ASTSource {
+    match ASTSource::parse_ast(TokenSet::new(text)) {
+        Ok(source) => source,
+        Err(err) => panic!("{}: {} at {:?}\n{}", path, err, err.span(), text),
+    }
+}
+
+// Debug output of a node without the spans which move when printed
+fn without_spans(value: String) -> String {
+    let mut value = value;
+    while let Some(start) = value.find("span: Span {") {
+        let end = start + value[start..].find('}').unwrap() + 1;
+        value.replace_range(start..end, "");
+    }
+    value
+}
+
+// Parses, prints and parses the output again. The reparsed tree has to
+// print identically and contain the same nodes.
+fn round_trip(path: &str, text: &str) {
+    let source = parse(path, text);
+    let printed = print_source(&source);
+    let reparsed = parse(path, &printed);
+    let reprinted = print_source(&reparsed);
+    assert_eq!(printed, reprinted, "{} printed differently after reparsing", path);
+    assert_eq!(source.len(), reparsed.len(), "{} lost nodes when printed:\n{}", path, printed);
+    for id in source.ids() {
+        assert_eq!(
+            without_spans(format!("{:?}", source.get(id))),
+            without_spans(format!("{:?}", reparsed.get(id))),
+            "{} node {:?} changed when printed:\n{}",
+            path,
+            id,
+            printed,
+        );
+    }
+}
+
+#[test]
+fn example() {
+    round_trip("example.spew", include_str!("../example.spew"));
+}
+
+#[test]
+fn corpus() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut count = 0;
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "spew") {
+            round_trip(&path.display().to_string(), &fs::read_to_string(&path).unwrap());
+            count += 1;
+        }
+    }
+    assert!(count > 0, "no corpus files found");
+}
+
+#[test]
+fn canonical_output() {
+    let text = "struct A{b:str? c:num}\nfun f(a:num)->num{let x=1+2*3\nif(x>1){return -(-x)}else{}\nreturn(x+1)*2}";
+    let printed = print_source(&parse("inline", text));
+    assert_eq!(
+        printed,
+        "struct A {\n  b: str?\n  c: num\n}\n\nfun f(a: num) -> num {\n  let x = 1 + 2 * 3\n  if x > 1 {\n    return - -x\n  } else {}\n  return (x + 1) * 2\n}\n"
+    );
+}
+
+#[test]
+fn semicolons_where_needed() {
+    let text = "fun f() { a; (b); c; -d; return; e }";
+    let printed = print_source(&parse("inline", text));
+    assert_eq!(printed, "fun f() {\n  a\n  b\n  c;\n  -d\n  return\n  e\n}\n");
+}
+
+#[test]
+fn written_unions() {
+    let text = "struct A{b:str|num?}\nfun un(v:str|num,w:other.B?|bool)->str|num{let x:num|str=v\nreturn x}";
+    let printed = print_source(&parse("inline", text));
+    assert_eq!(
+        printed,
+        "struct A {\n  b: str | num?\n}\n\nfun un(v: str | num, w: other.B? | bool) -> str | num {\n  let x: num | str = v\n  return x\n}\n"
+    );
+    round_trip("inline", &printed);
+}
@@ -0,0 +1,57 @@
+use spew::source::{Encoding, LineIndex, Position};
+
+// An astral char (two UTF-16 units, four bytes) followed by a CRLF line end
+const TEXT: &str = "a😀b\r\ncé";
+
+fn at(line: usize, column: usize) -> Position {
+    Position { line, column }
+}
+
+#[test]
+fn positions() {
+    let index = LineIndex::new(TEXT);
+    assert_eq!(index.line_count(), 2);
+    assert_eq!(index.line_range(0), Some((0, 6)));
+    assert_eq!(index.line_range(1), Some((8, 11)));
+    assert_eq!(index.line_range(2), None);
+
+    // `b` is after the emoji
+    assert_eq!(index.position(5, Encoding::Utf8), at(0, 5));
+    assert_eq!(index.position(5, Encoding::Utf16), at(0, 3));
+    assert_eq!(index.position(5, Encoding::Char), at(0, 2));
+    // Offsets inside a char move back onto its start
+    assert_eq!(index.position(3, Encoding::Utf16), at(0, 1));
+    assert_eq!(index.position(8, Encoding::Utf16), at(1, 0));
+    // Past the end of the text clamps to the end
+    assert_eq!(index.position(100, Encoding::Utf8), at(1, 3));
+    assert_eq!(index.position(100, Encoding::Char), at(1, 2));
+}
+
+#[test]
+fn offsets() {
+    let index = LineIndex::new(TEXT);
+    for encoding in [Encoding::Utf8, Encoding::Utf16, Encoding::Char] {
+        for offset in [0, 1, 5, 8, 9, 11] {
+            assert_eq!(index.offset(index.position(offset, encoding), encoding), Some(offset), "{:?}", encoding);
+        }
+    }
+    // A column between the two halves of a surrogate pair lands on the pair
+    assert_eq!(index.offset(at(0, 2), Encoding::Utf16), Some(1));
+    // Columns past the end of a line stop before its CRLF
+    assert_eq!(index.offset(at(0, 50), Encoding::Utf16), Some(6));
+    assert_eq!(index.offset(at(1, 50), Encoding::Char), Some(11));
+    assert_eq!(index.offset(at(2, 0), Encoding::Char), None);
+}
+
+#[test]
+fn units() {
+    let index = LineIndex::new(TEXT);
+    assert_eq!(index.units_of(8, Encoding::Utf8), 8);
+    assert_eq!(index.units_of(8, Encoding::Utf16), 6);
+    assert_eq!(index.units_of(8, Encoding::Char), 5);
+    assert_eq!(index.units_of(100, Encoding::Utf16), 8);
+    assert_eq!(index.offset_of_units(6, Encoding::Utf16), 8);
+    assert_eq!(index.offset_of_units(5, Encoding::Char), 8);
+    assert_eq!(index.offset_of_units(2, Encoding::Utf16), 1);
+    assert_eq!(index.offset_of_units(100, Encoding::Utf16), TEXT.len());
+}
@@ -0,0 +1,54 @@
+use spew::lexer::{Symbol, Token, TokenKind, TokenSet};
+use spew::source::Span;
+
+fn ident(token: Option<Token>) -> String {
+    match token {
+        Some(Token::Ident(name)) => name.to_string(),
+        token => panic!("{:?} isn't an identifier", token),
+    }
+}
+
+const TEXT: &str = "a // one\nb /* two\nlines */ /* three */ c // four";
+
+#[test]
+fn peeks_past_comments() {
+    let mut tokens = TokenSet::new(TEXT);
+    assert_eq!(ident(tokens.peek(2)), "c");
+    assert_eq!(ident(tokens.peek(1)), "b");
+    assert_eq!(ident(tokens.peek(0)), "a");
+    assert_eq!(tokens.comments().len(), 3);
+    assert_eq!(tokens.peek(3), None);
+    assert_eq!(tokens.peek_span(2), Span::new(39, 40));
+
+    assert_eq!(ident(tokens.next_token()), "a");
+    assert_eq!(ident(tokens.next_token()), "b");
+    assert_eq!(tokens.span(), Span::new(9, 10));
+    assert_eq!(ident(tokens.next_token()), "c");
+    assert_eq!(tokens.next_token(), None);
+    assert_eq!(tokens.comments().len(), 4);
+}
+
+#[test]
+fn rewinds_past_comments() {
+    let mut tokens = TokenSet::new(TEXT);
+    tokens.next_token();
+    let checkpoint = tokens.checkpoint();
+    assert_eq!(ident(tokens.next_token()), "b");
+    assert_eq!(ident(tokens.next_token()), "c");
+    tokens.rewind(checkpoint);
+    // Rewinding lands on the token after the checkpoint, not on a comment
+    assert_eq!(ident(tokens.peek(0)), "b");
+    assert_eq!(tokens.checkpoint(), checkpoint);
+    assert_eq!(ident(tokens.next_token()), "b");
+    assert_eq!(tokens.comments().len(), 3);
+}
+
+#[test]
+fn expects_kinds() {
+    let mut tokens = TokenSet::new("a /* x */ ( // y\n");
+    let found = tokens.expect(TokenKind::Symbol(Symbol::OpenParen));
+    assert_eq!(found.unwrap_err().map(|token| token.kind()), Some(TokenKind::Ident));
+    assert_eq!(ident(tokens.expect(TokenKind::Ident).ok()), "a");
+    assert_eq!(tokens.expect(TokenKind::Symbol(Symbol::OpenParen)), Ok(Token::Symbol(Symbol::OpenParen)));
+    assert_eq!(tokens.expect(TokenKind::Ident), Err(None));
+}
@@ -0,0 +1,36 @@
+use spew::ast::ASTSource;
+use spew::lexer::TokenSet;
+use spew::visibility::{exports, visibility};
+
+mod common;
+
+fn parse(text: &str) -> ASTSource {
+    ASTSource::parse_ast(TokenSet::new(text)).unwrap()
+}
+
+#[test]
+fn exported() {
+    let text = "pub struct A {\n  pub id: num\n  name: str\n}\nstruct B {}\npub trait T {}\npub type Id = num\npub fun f() {}\nfun g() {}\npub const LIMIT = 1\nimpl A {\n  pub static fun make() -> A { return A(1, \"\") }\n}";
+    let source = parse(text);
+    let names: Vec<_> = exports(&source, 0).iter().map(|(name, _)| name.to_string()).collect();
+    assert_eq!(names, vec!["A", "T", "Id", "f", "LIMIT"]);
+    assert_eq!(visibility(&source), Vec::new());
+}
+
+#[test]
+fn invalid_targets() {
+    let text = "trait T {\n  pub fun a()\n}\nstruct S {}\nimpl T for S {\n  pub fun a() {}\n}\nimpl S {\n  pub const ONE = 1\n}\nfun f() {\n  pub let x = 1\n  let g = fun () { let pub y = 2 }\n}";
+    let source = parse(text);
+    assert_eq!(
+        common::messages(text, &visibility(&source)),
+        vec![
+            "functions of trait `T` are as visible as the trait, remove `pub` at `pub fun a()`",
+            "functions of trait `T` are as visible as the trait, remove `pub` at `pub fun a() {}`",
+            "`x` is local so it can't be `pub`, only items, fields and members of impls can at `pub let x = 1`",
+            "`y` is local so it can't be `pub`, only items, fields and members of impls can at `let pub y = 2`",
+        ]
+    );
+    // Only functions and variables take other modifiers
+    assert!(ASTSource::parse_ast(TokenSet::new("pub impl S {}")).is_err());
+    assert!(ASTSource::parse_ast(TokenSet::new("static struct S {}")).is_err());
+}
@@ -0,0 +1,88 @@
+use spew::ast::{ASTSource, DataType, Literal, NodeId, SpewFunction, AST};
+use spew::intern::Name;
+use spew::lexer::TokenSet;
+use spew::printer::print_source;
+use spew::visit::{walk_function, MutVisitor, Visitor};
+
+fn parse(text: &str) -> ASTSource {
+    ASTSource::parse_ast(TokenSet::new(text)).unwrap()
+}
+
+const TEXT: &str = "struct A { id: num }\ntrait T { fun name() -> str }\nimpl T for A {\n  fun name() -> str {\n    let n: num = 1 + 2\n    if n > 2 { return \"big\" }\n    return match n { 3 => \"three\", _ => \"other\" }\n  }\n}\nfun f(a: A) -> num { return a.id * 2 }";
+
+#[derive(Default)]
+struct Counter {
+    functions: usize,
+    literals: usize,
+    identifiers: Vec<Name>,
+    types: Vec<Name>,
+}
+
+impl<'a> Visitor<'a> for Counter {
+    fn visit_function(
+        &mut self,
+        source: &'a ASTSource,
+        id: NodeId<SpewFunction>,
+        value: &'a SpewFunction,
+    ) {
+        self.functions += 1;
+        walk_function(self, source, id, value);
+    }
+
+    fn visit_identifier(&mut self, _source: &'a ASTSource, _id: NodeId, name: Name) {
+        self.identifiers.push(name);
+    }
+
+    fn visit_literal(&mut self, _source: &'a ASTSource, _id: NodeId, _value: &'a Literal) {
+        self.literals += 1;
+    }
+
+    fn visit_data_type(&mut self, _source: &'a ASTSource, _id: NodeId, value: &'a DataType) {
+        self.types.push(value.name);
+    }
+}
+
+#[test]
+fn counts() {
+    let source = parse(TEXT);
+    let mut counter = Counter::default();
+    counter.visit_source(&source);
+    assert_eq!(counter.functions, 2);
+    assert_eq!(counter.literals, 7);
+    let identifiers: Vec<_> = counter
+        .identifiers
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    assert_eq!(identifiers, vec!["n", "n", "a"]);
+    let types: Vec<_> = counter.types.iter().map(|name| name.to_string()).collect();
+    assert_eq!(types, vec!["num", "str", "str", "num", "A", "num"]);
+}
+
+// Doubles every number literal
+struct Doubler;
+
+impl MutVisitor for Doubler {
+    fn visit_literal(&mut self, source: &mut ASTSource, id: NodeId) {
+        if let AST::Literal(Literal::Number(value)) = source.get(id) {
+            let doubled = value.parse::<f64>().unwrap() * 2.0;
+            source.replace(id, AST::Literal(Literal::Number(doubled.to_string())));
+        }
+    }
+
+    fn visit_data_type(&mut self, value: &mut DataType) {
+        if value.name == "num" {
+            value.nullable = true;
+        }
+    }
+}
+
+#[test]
+fn rewrites() {
+    let mut source = parse("fun f(a: num) -> num {\n  let b: num = 1 + 2\n  return a * 3\n}");
+    Doubler.visit_source(&mut source);
+    assert_eq!(
+        print_source(&source),
+        "fun f(a: num?) -> num? {\n  let b: num? = 2 + 4\n  return a * 6\n}\n"
+    );
+}